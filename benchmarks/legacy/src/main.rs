@@ -4,7 +4,9 @@ use canbench_rs::{bench, bench_fn, BenchResult};
 use ic_cdk_macros::init;
 use ic_doge_canister::{types::BlockHeaderBlob, with_state, with_state_mut};
 use ic_doge_interface::{InitConfig, Network};
-use ic_doge_test_utils::{build_regtest_chain, BlockBuilder, TransactionBuilder};
+use ic_doge_test_utils::{
+    build_regtest_chain, build_regtest_chain_with_spacing, BlockBuilder, TransactionBuilder,
+};
 use ic_doge_types::Block;
 use std::cell::RefCell;
 use std::str::FromStr;
@@ -405,4 +407,67 @@ fn insert_block_headers_multiple_times_regtest_without_auxpow() -> BenchResult {
     bench_result
 }
 
+// Insert 250 block headers without AuxPow information in Regtest, spaced far
+// enough apart (> 2x the target spacing) to trigger the minimum-difficulty-
+// block reset on every header.
+#[bench(raw)]
+fn insert_block_headers_regtest_min_difficulty() -> BenchResult {
+    let blocks_to_insert = 50;
+    let block_headers_to_insert = 250;
+    let num_transactions_per_block = 10;
+    // Regtest's target spacing is 60s; spacing blocks 21 minutes apart keeps
+    // every header past the 2x (2 minute) minimum-difficulty threshold.
+    let spacing_secs = 21 * 60;
+
+    ic_doge_canister::init(InitConfig {
+        network: Some(Network::Regtest),
+        stability_threshold: Some(144),
+        ..Default::default()
+    });
+
+    let chain = build_regtest_chain_with_spacing(
+        blocks_to_insert + block_headers_to_insert,
+        num_transactions_per_block,
+        spacing_secs,
+    );
+
+    // Insert the blocks.
+    with_state_mut(|s| {
+        for block in chain.iter().take(blocks_to_insert as usize).skip(1) {
+            ic_doge_canister::state::insert_block(s, block.clone()).unwrap();
+        }
+    });
+
+    // Compute the next block headers.
+    let mut next_block_headers = vec![];
+    for block in chain.iter().skip(blocks_to_insert as usize) {
+        let mut block_header_blob = vec![];
+        dogecoin::Header::consensus_encode(block.auxpow_header(), &mut block_header_blob)
+            .unwrap();
+        next_block_headers.push(BlockHeaderBlob::from(block_header_blob));
+    }
+
+    // Benchmark inserting the block headers.
+    let bench_result = bench_fn(|| {
+        with_state_mut(|s| {
+            ic_doge_canister::state::insert_next_block_headers(s, next_block_headers.as_slice());
+        });
+    });
+
+    with_state(|s| {
+        let max_height = s.unstable_blocks.next_block_headers_max_height().expect(
+            "Failed to get next_block_headers_max_height: no new block headers have been inserted.",
+        );
+        assert_eq!(
+            max_height,
+            blocks_to_insert + block_headers_to_insert - 1,
+            "Expected all headers to be inserted. Max height should be {}, got {}.",
+            blocks_to_insert + block_headers_to_insert - 1,
+            max_height
+        );
+    });
+
+    bench_result
+}
+
 fn main() {}
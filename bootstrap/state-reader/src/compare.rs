@@ -0,0 +1,189 @@
+//! Per-entry diffing between two canister state snapshots.
+//!
+//! The combined hash computed by `main` tells you *that* two snapshots
+//! disagree; this merge-joins the sorted collections of each to pin down
+//! exactly *which* entries were added, removed, or changed.
+
+use crate::{CanisterData, Utxo};
+use ic_doge_canister::types::{Address, AddressUtxo, BlockHeaderBlob};
+use ic_doge_interface::Height;
+use ic_doge_types::BlockHash;
+use std::cmp::Ordering;
+
+/// Added/removed/changed counts for one category, plus a bounded number of
+/// formatted sample diffs.
+#[derive(Debug)]
+pub struct CategoryDiff {
+    pub name: &'static str,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub samples: Vec<String>,
+}
+
+impl CategoryDiff {
+    pub fn total(&self) -> usize {
+        self.added + self.removed + self.changed
+    }
+}
+
+fn push_sample(samples: &mut Vec<String>, limit: usize, sample: String) {
+    if samples.len() < limit {
+        samples.push(sample);
+    }
+}
+
+/// Merge-join `left` and `right` on `key_of`, after independently sorting
+/// each by that key -- the vectors' existing hash-computation sort order
+/// doesn't necessarily match the join key (UTXOs, for example, are sorted
+/// by height first, so a height change would otherwise misalign the walk).
+fn diff_category<T, K: Ord>(
+    name: &'static str,
+    left: &[T],
+    right: &[T],
+    key_of: impl Fn(&T) -> K,
+    eq: impl Fn(&T, &T) -> bool,
+    describe: impl Fn(&str, &T, Option<&T>) -> String,
+    sample_limit: usize,
+) -> CategoryDiff {
+    let mut left: Vec<&T> = left.iter().collect();
+    let mut right: Vec<&T> = right.iter().collect();
+    left.sort_by_key(|t| key_of(t));
+    right.sort_by_key(|t| key_of(t));
+
+    let mut added = 0;
+    let mut removed = 0;
+    let mut changed = 0;
+    let mut samples = Vec::new();
+
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() && j < right.len() {
+        let (l, r) = (left[i], right[j]);
+        match key_of(l).cmp(&key_of(r)) {
+            Ordering::Less => {
+                removed += 1;
+                push_sample(&mut samples, sample_limit, describe("Removed", l, None));
+                i += 1;
+            }
+            Ordering::Greater => {
+                added += 1;
+                push_sample(&mut samples, sample_limit, describe("Added", r, None));
+                j += 1;
+            }
+            Ordering::Equal => {
+                if !eq(l, r) {
+                    changed += 1;
+                    push_sample(&mut samples, sample_limit, describe("Changed", l, Some(r)));
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    for l in &left[i..] {
+        removed += 1;
+        push_sample(&mut samples, sample_limit, describe("Removed", l, None));
+    }
+    for r in &right[j..] {
+        added += 1;
+        push_sample(&mut samples, sample_limit, describe("Added", r, None));
+    }
+
+    CategoryDiff {
+        name,
+        added,
+        removed,
+        changed,
+        samples,
+    }
+}
+
+/// Diff every category of two already read-and-sorted canister states,
+/// keeping at most `sample_limit` sample diffs per category.
+pub fn diff_all(
+    a: &CanisterData,
+    a_utxos: &[Utxo],
+    b: &CanisterData,
+    b_utxos: &[Utxo],
+    sample_limit: usize,
+) -> Vec<CategoryDiff> {
+    vec![
+        diff_category(
+            "utxos",
+            a_utxos,
+            b_utxos,
+            |u: &Utxo| u.outpoint.clone(),
+            |a: &Utxo, b: &Utxo| a.height == b.height && a.txout.value == b.txout.value,
+            |kind, a, b| match b {
+                Some(b) => format!(
+                    "{kind} {:?}: height {} -> {}, value {} -> {}",
+                    a.outpoint, a.height, b.height, a.txout.value, b.txout.value
+                ),
+                None => format!("{kind} {:?}: height {}, value {}", a.outpoint, a.height, a.txout.value),
+            },
+            sample_limit,
+        ),
+        diff_category(
+            "address_utxos",
+            &a.address_utxos,
+            &b.address_utxos,
+            |au: &AddressUtxo| (au.address.to_string(), au.outpoint.clone()),
+            |a: &AddressUtxo, b: &AddressUtxo| a.height == b.height,
+            |kind, a, b| match b {
+                Some(b) => format!(
+                    "{kind} {} {:?}: height {} -> {}",
+                    a.address, a.outpoint, a.height, b.height
+                ),
+                None => format!("{kind} {} {:?}: height {}", a.address, a.outpoint, a.height),
+            },
+            sample_limit,
+        ),
+        diff_category(
+            "balances",
+            &a.balances,
+            &b.balances,
+            |(address, _): &(Address, u128)| address.to_string(),
+            |(_, a): &(Address, u128), (_, b): &(Address, u128)| a == b,
+            |kind, (address, balance), other| match other {
+                Some((_, other_balance)) => {
+                    format!("{kind} {address}: {balance} -> {other_balance}")
+                }
+                None => format!("{kind} {address}: {balance}"),
+            },
+            sample_limit,
+        ),
+        diff_category(
+            "block_headers",
+            &a.block_headers,
+            &b.block_headers,
+            |(hash, _): &(BlockHash, BlockHeaderBlob)| hash.clone(),
+            |(_, a): &(BlockHash, BlockHeaderBlob), (_, b): &(BlockHash, BlockHeaderBlob)| {
+                a.as_slice() == b.as_slice()
+            },
+            |kind, (hash, blob), other| match other {
+                Some((_, other_blob)) => format!(
+                    "{kind} {:?}: header {} bytes -> {} bytes",
+                    hash,
+                    blob.as_slice().len(),
+                    other_blob.as_slice().len()
+                ),
+                None => format!("{kind} {:?}: header {} bytes", hash, blob.as_slice().len()),
+            },
+            sample_limit,
+        ),
+        diff_category(
+            "block_heights",
+            &a.block_heights,
+            &b.block_heights,
+            |(height, _): &(Height, BlockHash)| *height,
+            |(_, a): &(Height, BlockHash), (_, b): &(Height, BlockHash)| a == b,
+            |kind, (height, hash), other| match other {
+                Some((_, other_hash)) => {
+                    format!("{kind} {height}: {:?} -> {:?}", hash, other_hash)
+                }
+                None => format!("{kind} {height}: {:?}", hash),
+            },
+            sample_limit,
+        ),
+    ]
+}
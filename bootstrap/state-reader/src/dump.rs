@@ -0,0 +1,179 @@
+//! Structured export of a fully-extracted [`CanisterData`] to JSON or CSV,
+//! so a canister snapshot can be loaded into SQL/pandas and reconciled
+//! against a full node, instead of only skimming the truncated tables in
+//! `print_statistics`.
+
+use crate::{CanisterData, Utxo};
+use ic_doge_canister::types::{Address, AddressUtxo, BlockHeaderBlob};
+use ic_doge_interface::Height;
+use ic_doge_types::BlockHash;
+use ic_stable_structures::Storable;
+use serde::Serialize;
+use std::path::Path;
+
+/// A UTXO, with the txid rendered as big-endian hex (the convention block
+/// explorers and RPCs use) rather than the little-endian bytes stored on
+/// disk.
+#[derive(Debug, Serialize)]
+struct UtxoRow {
+    txid: String,
+    vout: u32,
+    value: u64,
+    height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct AddressUtxoRow {
+    address: String,
+    txid: String,
+    vout: u32,
+    height: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct BalanceRow {
+    address: String,
+    balance: u128,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockHeaderRow {
+    hash: String,
+    header: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BlockHeightRow {
+    height: u32,
+    hash: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Dump {
+    utxos: Vec<UtxoRow>,
+    address_utxos: Vec<AddressUtxoRow>,
+    balances: Vec<BalanceRow>,
+    block_headers: Vec<BlockHeaderRow>,
+    block_heights: Vec<BlockHeightRow>,
+}
+
+fn utxo_row(utxo: &Utxo) -> UtxoRow {
+    let mut txid_bytes = utxo.outpoint.txid.as_bytes().to_vec();
+    txid_bytes.reverse();
+    UtxoRow {
+        txid: hex::encode(txid_bytes),
+        vout: utxo.outpoint.vout,
+        value: utxo.txout.value,
+        height: utxo.height,
+    }
+}
+
+fn address_utxo_row(addr_utxo: &AddressUtxo) -> AddressUtxoRow {
+    let mut txid_bytes = addr_utxo.outpoint.txid.as_bytes().to_vec();
+    txid_bytes.reverse();
+    AddressUtxoRow {
+        address: addr_utxo.address.to_string(),
+        txid: hex::encode(txid_bytes),
+        vout: addr_utxo.outpoint.vout,
+        height: addr_utxo.height,
+    }
+}
+
+fn balance_row((address, balance): &(Address, u128)) -> BalanceRow {
+    BalanceRow {
+        address: address.to_string(),
+        balance: *balance,
+    }
+}
+
+fn block_header_row((hash, blob): &(BlockHash, BlockHeaderBlob)) -> BlockHeaderRow {
+    let mut hash_bytes = hash.to_bytes().to_vec();
+    hash_bytes.reverse();
+    BlockHeaderRow {
+        hash: hex::encode(hash_bytes),
+        header: hex::encode(blob.as_slice()),
+    }
+}
+
+fn block_height_row((height, hash): &(Height, BlockHash)) -> BlockHeightRow {
+    let mut hash_bytes = hash.to_bytes().to_vec();
+    hash_bytes.reverse();
+    BlockHeightRow {
+        height: *height,
+        hash: hex::encode(hash_bytes),
+    }
+}
+
+/// Serialize the full state to a single JSON document, one fully-typed
+/// record per UTXO/address-UTXO/balance/header/height. Writes to `output`
+/// if given, otherwise to stdout.
+pub fn write_json(
+    data: &CanisterData,
+    utxos: &[Utxo],
+    output: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dump = Dump {
+        utxos: utxos.iter().map(utxo_row).collect(),
+        address_utxos: data.address_utxos.iter().map(address_utxo_row).collect(),
+        balances: data.balances.iter().map(balance_row).collect(),
+        block_headers: data.block_headers.iter().map(block_header_row).collect(),
+        block_heights: data.block_heights.iter().map(block_height_row).collect(),
+    };
+
+    match output {
+        Some(path) => {
+            let file = std::fs::File::create(path)?;
+            serde_json::to_writer_pretty(file, &dump)?;
+        }
+        None => {
+            let stdout = std::io::stdout();
+            serde_json::to_writer_pretty(stdout.lock(), &dump)?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+/// Serialize the full state as CSV, one file per record kind under `dir`
+/// (created if missing), streaming each record straight to its writer
+/// instead of buffering the whole state as one giant string.
+pub fn write_csv(
+    data: &CanisterData,
+    utxos: &[Utxo],
+    dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+
+    write_csv_file(&dir.join("utxos.csv"), utxos.iter().map(utxo_row))?;
+    write_csv_file(
+        &dir.join("address_utxos.csv"),
+        data.address_utxos.iter().map(address_utxo_row),
+    )?;
+    write_csv_file(
+        &dir.join("balances.csv"),
+        data.balances.iter().map(balance_row),
+    )?;
+    write_csv_file(
+        &dir.join("block_headers.csv"),
+        data.block_headers.iter().map(block_header_row),
+    )?;
+    write_csv_file(
+        &dir.join("block_heights.csv"),
+        data.block_heights.iter().map(block_height_row),
+    )?;
+
+    Ok(())
+}
+
+fn write_csv_file<T: Serialize>(
+    path: &Path,
+    rows: impl Iterator<Item = T>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+//! Brotli-compressed export of the analyzed balances/header state,
+//! adjacent to but distinct from [`crate::snapshot`]'s LZ4 block-checksummed
+//! format: no block indexing, no UTXOs (`--snapshot-out` already covers that
+//! much larger set), just balances/block_headers/block_heights run through
+//! one high-quality, large-window Brotli pass. Address and header blobs are
+//! highly repetitive key-value data, so the slower compression this trades
+//! for is worth it for a one-shot export meant to be shipped somewhere
+//! cheaply rather than reloaded quickly.
+
+use crate::CanisterData;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"DOGEXPRT";
+const VERSION: u32 = 1;
+
+/// Brotli quality (0-11). Near-max by default, appropriate for a one-shot
+/// export of highly repetitive address/header data.
+pub const DEFAULT_QUALITY: u32 = 10;
+
+/// Brotli window size (`lgwin`), large enough to let matches span most of a
+/// typical balances/headers dump rather than just a nearby window.
+const LGWIN: u32 = 24;
+
+const BROTLI_BUFFER_SIZE: usize = 1 << 16;
+
+fn encode_category<T>(records: &[T], encode: impl Fn(&T) -> Vec<u8>) -> Vec<u8> {
+    let mut bytes = (records.len() as u64).to_le_bytes().to_vec();
+    for record in records {
+        let encoded = encode(record);
+        bytes.extend_from_slice(&(encoded.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&encoded);
+    }
+    bytes
+}
+
+/// Decodes one category starting at the front of `bytes`, returning the
+/// records and the number of bytes consumed so the caller can keep slicing
+/// through the next category.
+fn decode_category<T>(bytes: &[u8], decode: impl Fn(&[u8]) -> T) -> (Vec<T>, usize) {
+    let count = u64::from_le_bytes(bytes[0..8].try_into().unwrap()) as usize;
+    let mut records = Vec::with_capacity(count);
+    let mut offset = 8;
+    for _ in 0..count {
+        let len = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+        records.push(decode(&bytes[offset..offset + len]));
+        offset += len;
+    }
+    (records, offset)
+}
+
+/// Serializes `data.balances`/`block_headers`/`block_heights` and writes
+/// the result Brotli-compressed to `path` at the given `quality` (0-11).
+pub fn write(path: &Path, data: &CanisterData, quality: u32) -> io::Result<()> {
+    let mut uncompressed = Vec::new();
+    uncompressed.extend_from_slice(&encode_category(&data.balances, crate::snapshot::encode_balance));
+    uncompressed.extend_from_slice(&encode_category(&data.block_headers, crate::snapshot::encode_block_header));
+    uncompressed.extend_from_slice(&encode_category(&data.block_heights, crate::snapshot::encode_block_height));
+
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    let mut compressor = brotli::CompressorWriter::new(&mut writer, BROTLI_BUFFER_SIZE, quality, LGWIN);
+    compressor.write_all(&uncompressed)?;
+    compressor.flush()?;
+    drop(compressor);
+
+    writer.flush()
+}
+
+/// Reads an export written by [`write`] back into a [`CanisterData`], with
+/// `utxos`/`address_utxos` left empty since this format doesn't carry them.
+pub fn read(path: &Path) -> io::Result<CanisterData> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a state-reader export"));
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    if u32::from_le_bytes(version) != VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported export version"));
+    }
+
+    let mut uncompressed = Vec::new();
+    brotli::Decompressor::new(reader, BROTLI_BUFFER_SIZE).read_to_end(&mut uncompressed)?;
+
+    let (balances, consumed) = decode_category(&uncompressed, crate::snapshot::decode_balance);
+    let rest = &uncompressed[consumed..];
+    let (block_headers, consumed) = decode_category(rest, crate::snapshot::decode_block_header);
+    let rest = &rest[consumed..];
+    let (block_heights, _) = decode_category(rest, crate::snapshot::decode_block_height);
+
+    Ok(CanisterData {
+        utxos: Vec::new(),
+        address_utxos: Vec::new(),
+        balances,
+        block_headers,
+        block_heights,
+    })
+}
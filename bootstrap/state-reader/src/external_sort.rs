@@ -0,0 +1,221 @@
+//! Generic external-merge sort: buffers items, spills sorted runs to disk
+//! once a buffer grows past a threshold, then k-way merges every run back
+//! into one sorted stream via a `BinaryHeap`.
+//!
+//! This is what lets the main pipeline process a canister state larger
+//! than RAM -- [`UtxoReader`](crate::UtxoReader)'s `iter_*` methods already
+//! stream entries out of stable memory lazily; piping them through an
+//! [`ExternalSorter`] instead of collecting into a `Vec` and calling
+//! `.sort()` keeps peak memory bounded by the run size rather than the
+//! total entry count. The shape mirrors
+//! `utxo_dump::aggregate::AddressAggregator`/`MergedRuns`, generalized over
+//! any item type -- not just ones implementing [`Storable`](ic_stable_structures::Storable),
+//! since e.g. [`Utxo`](crate::Utxo) and the `(Address, u128)` balance pairs
+//! this is meant to sort are plain composites of other `Storable` types --
+//! via caller-supplied `encode`/`decode` closures and a `key_of` closure so
+//! the merge order exactly reproduces the sort key each `compute_*_hash`
+//! function expects; otherwise a streamed hash would silently diverge from
+//! the in-memory one.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+
+/// Number of buffered items per spilled run. Peak memory is roughly this
+/// many items held in memory at once, regardless of the total stream size.
+pub const DEFAULT_RUN_SIZE: usize = 1_000_000;
+
+/// Buffers `T`s and spills them, sorted by `key_of`, to run files under
+/// `run_dir` once the buffer reaches `run_size`. Call [`finish`](Self::finish)
+/// to flush the last (partial) run and get the merged, fully sorted stream.
+pub struct ExternalSorter<T, K, F, E, D> {
+    buffer: Vec<T>,
+    run_dir: PathBuf,
+    run_paths: Vec<PathBuf>,
+    run_size: usize,
+    key_of: F,
+    encode: E,
+    decode: D,
+    _marker: std::marker::PhantomData<K>,
+}
+
+impl<T, K, F, E, D> ExternalSorter<T, K, F, E, D>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+    E: Fn(&T) -> Vec<u8>,
+    D: Fn(&[u8]) -> T,
+{
+    pub fn new(run_dir: impl Into<PathBuf>, key_of: F, encode: E, decode: D) -> io::Result<Self> {
+        Self::with_run_size(run_dir, key_of, encode, decode, DEFAULT_RUN_SIZE)
+    }
+
+    pub fn with_run_size(
+        run_dir: impl Into<PathBuf>,
+        key_of: F,
+        encode: E,
+        decode: D,
+        run_size: usize,
+    ) -> io::Result<Self> {
+        let run_dir = run_dir.into();
+        std::fs::create_dir_all(&run_dir)?;
+        Ok(Self {
+            buffer: Vec::new(),
+            run_dir,
+            run_paths: Vec::new(),
+            run_size,
+            key_of,
+            encode,
+            decode,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    pub fn push(&mut self, item: T) -> io::Result<()> {
+        self.buffer.push(item);
+        if self.buffer.len() >= self.run_size {
+            self.flush_run()?;
+        }
+        Ok(())
+    }
+
+    fn flush_run(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let key_of = &self.key_of;
+        self.buffer.sort_by(|a, b| key_of(a).cmp(&key_of(b)));
+
+        let run_path = self
+            .run_dir
+            .join(format!("run-{:06}.bin", self.run_paths.len()));
+        let mut writer = BufWriter::new(File::create(&run_path)?);
+        for item in self.buffer.drain(..) {
+            let bytes = (self.encode)(&item);
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()?;
+        self.run_paths.push(run_path);
+        Ok(())
+    }
+
+    /// Flushes the final (possibly partial) run and returns an iterator
+    /// that k-way merges every run in `key_of` order. The run files are
+    /// removed once the merge finishes (or is dropped early).
+    pub fn finish(mut self) -> io::Result<MergedRuns<T, K, F, D>> {
+        self.flush_run()?;
+        MergedRuns::open(self.run_paths, self.key_of, self.decode)
+    }
+}
+
+struct HeapEntry<T, K> {
+    key: K,
+    item: T,
+    run_index: usize,
+}
+
+impl<T, K: PartialEq> PartialEq for HeapEntry<T, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+impl<T, K: Eq> Eq for HeapEntry<T, K> {}
+impl<T, K: PartialOrd> PartialOrd for HeapEntry<T, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+impl<T, K: Ord> Ord for HeapEntry<T, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+/// K-way merges a set of run files, each pre-sorted ascending by `key_of`,
+/// yielding items in that order.
+pub struct MergedRuns<T, K, F, D> {
+    heap: BinaryHeap<Reverse<HeapEntry<T, K>>>,
+    readers: Vec<BufReader<File>>,
+    run_paths: Vec<PathBuf>,
+    key_of: F,
+    decode: D,
+}
+
+impl<T, K, F, D> MergedRuns<T, K, F, D>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+    D: Fn(&[u8]) -> T,
+{
+    fn open(run_paths: Vec<PathBuf>, key_of: F, decode: D) -> io::Result<Self> {
+        let mut readers: Vec<BufReader<File>> = run_paths
+            .iter()
+            .map(|path| File::open(path).map(BufReader::new))
+            .collect::<io::Result<_>>()?;
+
+        let mut heap = BinaryHeap::new();
+        for (run_index, reader) in readers.iter_mut().enumerate() {
+            if let Some(item) = read_entry(reader, &decode)? {
+                let key = key_of(&item);
+                heap.push(Reverse(HeapEntry { key, item, run_index }));
+            }
+        }
+
+        Ok(Self {
+            heap,
+            readers,
+            run_paths,
+            key_of,
+            decode,
+        })
+    }
+}
+
+impl<T, K, F, D> Iterator for MergedRuns<T, K, F, D>
+where
+    K: Ord,
+    F: Fn(&T) -> K,
+    D: Fn(&[u8]) -> T,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let Reverse(HeapEntry { item, run_index, .. }) = self.heap.pop()?;
+
+        if let Ok(Some(next_item)) = read_entry(&mut self.readers[run_index], &self.decode) {
+            let key = (self.key_of)(&next_item);
+            self.heap.push(Reverse(HeapEntry {
+                key,
+                item: next_item,
+                run_index,
+            }));
+        }
+
+        Some(item)
+    }
+}
+
+impl<T, K, F, D> Drop for MergedRuns<T, K, F, D> {
+    fn drop(&mut self) {
+        for path in &self.run_paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn read_entry<T>(reader: &mut BufReader<File>, decode: impl Fn(&[u8]) -> T) -> io::Result<Option<T>> {
+    let mut len_buf = [0u8; 4];
+    match reader.read_exact(&mut len_buf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len_buf) as usize;
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(decode(&buf)))
+}
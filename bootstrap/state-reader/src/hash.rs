@@ -1,13 +1,100 @@
-use crate::Utxo;
+use crate::{CanisterData, Utxo};
 use ic_doge_canister::types::{Address, AddressUtxo, BlockHeaderBlob, TxOut};
 use ic_doge_interface::Height;
 use ic_doge_types::BlockHash;
 use ic_stable_structures::Storable;
+use rayon::prelude::*;
 use sha2::{Digest, Sha256};
 
-/// Compute SHA256 hash of UTXO set
-pub fn compute_utxo_set_hash(utxos: &[Utxo]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Selects which digest implementation backs the `compute_*_hash` helpers below.
+///
+/// `Sha256` is the default and is what the canister itself commits to. `Blake3`
+/// and `Xxh3` trade that guarantee away for raw speed, which matters when
+/// rehashing a multi-gigabyte UTXO set on every verification run: `Blake3` is
+/// SIMD-parallel and still cryptographic, while `Xxh3` is a cheap
+/// non-cryptographic checksum useful for a quick sanity comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+    Xxh3,
+}
+
+impl HashAlgorithm {
+    fn hasher(self) -> Box<dyn Hasher> {
+        match self {
+            HashAlgorithm::Sha256 => Box::new(Sha256Hasher(Sha256::new())),
+            HashAlgorithm::Blake3 => Box::new(Blake3Hasher(blake3::Hasher::new())),
+            HashAlgorithm::Xxh3 => Box::new(Xxh3Hasher(xxhash_rust::xxh3::Xxh3::new())),
+        }
+    }
+}
+
+impl std::fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Xxh3 => "xxh3",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A digest alongside the algorithm that produced it, so callers never
+/// mistake a `Blake3` digest for a `Sha256` one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Digest32 {
+    pub bytes: [u8; 32],
+    pub algorithm: HashAlgorithm,
+}
+
+/// Common interface for the pluggable digest backends. Every backend folds
+/// field bytes in the same order, so a given backend's output is deterministic
+/// across runs regardless of which one is selected.
+trait Hasher {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self: Box<Self>) -> [u8; 32];
+}
+
+struct Sha256Hasher(Sha256);
+impl Hasher for Sha256Hasher {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(&mut self.0, data);
+    }
+    fn finalize(self: Box<Self>) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+struct Blake3Hasher(blake3::Hasher);
+impl Hasher for Blake3Hasher {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+    fn finalize(self: Box<Self>) -> [u8; 32] {
+        *self.0.finalize().as_bytes()
+    }
+}
+
+/// XXH3 only produces a 64-bit digest; it is left-packed into the low 8 bytes
+/// of the 32-byte output with the remainder zeroed so callers can keep a
+/// uniform `[u8; 32]` digest type regardless of backend.
+struct Xxh3Hasher(xxhash_rust::xxh3::Xxh3);
+impl Hasher for Xxh3Hasher {
+    fn update(&mut self, data: &[u8]) {
+        std::hash::Hasher::write(&mut self.0, data);
+    }
+    fn finalize(self: Box<Self>) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        out[..8].copy_from_slice(&std::hash::Hasher::finish(&self.0).to_le_bytes());
+        out
+    }
+}
+
+/// Compute the hash of the UTXO set using the selected backend.
+pub fn compute_utxo_set_hash(utxos: &[Utxo], algorithm: HashAlgorithm) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for utxo in utxos {
         let Utxo {
@@ -19,18 +106,104 @@ pub fn compute_utxo_set_hash(utxos: &[Utxo]) -> [u8; 32] {
             value,
             script_pubkey,
         } = txout;
-        hasher.update(Storable::to_bytes(outpoint));
-        hasher.update(value.to_le_bytes());
+        hasher.update(&Storable::to_bytes(outpoint));
+        hasher.update(&value.to_le_bytes());
         hasher.update(script_pubkey);
-        hasher.update(height.to_le_bytes());
+        hasher.update(&height.to_le_bytes());
+    }
+
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
+}
+
+/// Fold a UTXO stream into a digest incrementally, without ever materializing
+/// the full set in memory. Feed it [`UtxoReader::iter_utxos`](crate::UtxoReader::iter_utxos)
+/// to hash a multi-gigabyte UTXO set with bounded memory usage.
+///
+/// Hashes the same field layout as [`compute_utxo_set_hash`], so the two only
+/// agree when `utxos` is fed in the same order.
+pub fn hash_utxo_set_streaming(
+    utxos: impl Iterator<Item = Utxo>,
+    algorithm: HashAlgorithm,
+) -> Digest32 {
+    let mut hasher = algorithm.hasher();
+
+    for utxo in utxos {
+        let Utxo {
+            outpoint,
+            txout,
+            height,
+        } = utxo;
+        let TxOut {
+            value,
+            script_pubkey,
+        } = txout;
+        hasher.update(&Storable::to_bytes(&outpoint));
+        hasher.update(&value.to_le_bytes());
+        hasher.update(&script_pubkey);
+        hasher.update(&height.to_le_bytes());
     }
 
-    hasher.finalize().into()
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
 }
 
-/// Compute SHA256 hash of address UTXOs data
-pub fn compute_address_utxos_hash(address_utxos: &[AddressUtxo]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Order-independent accumulator over per-UTXO leaf digests: each UTXO's
+/// `outpoint || value || script_pubkey || height` is hashed on its own with
+/// the selected backend, and the leaves are folded together with XOR rather
+/// than fed into one running hasher.
+///
+/// [`compute_utxo_set_hash`] can only be recomputed by rehashing the whole
+/// set, since a streaming hash-of-concatenation depends on feed order. XOR
+/// folding doesn't: removing a UTXO is the same XOR of its leaf as adding it
+/// was, so this is the shape a commitment maintained incrementally by the
+/// live canister state (updated on every UTXO insert/remove, rather than
+/// recomputed from a full snapshot) would need. This function itself still
+/// takes a full `&[Utxo]` slice -- the incremental bookkeeping on
+/// insert/remove would live wherever the UTXO set itself is mutated.
+pub fn compute_utxo_set_commitment_accumulator(utxos: &[Utxo], algorithm: HashAlgorithm) -> Digest32 {
+    let mut accumulator = [0u8; 32];
+    for utxo in utxos {
+        let leaf = utxo_commitment_leaf(utxo, algorithm);
+        for (acc_byte, leaf_byte) in accumulator.iter_mut().zip(leaf.iter()) {
+            *acc_byte ^= leaf_byte;
+        }
+    }
+    Digest32 {
+        bytes: accumulator,
+        algorithm,
+    }
+}
+
+/// The per-UTXO leaf digest folded by [`compute_utxo_set_commitment_accumulator`].
+fn utxo_commitment_leaf(utxo: &Utxo, algorithm: HashAlgorithm) -> [u8; 32] {
+    let Utxo {
+        outpoint,
+        txout,
+        height,
+    } = utxo;
+    let TxOut {
+        value,
+        script_pubkey,
+    } = txout;
+    let mut hasher = algorithm.hasher();
+    hasher.update(&Storable::to_bytes(outpoint));
+    hasher.update(&value.to_le_bytes());
+    hasher.update(script_pubkey);
+    hasher.update(&height.to_le_bytes());
+    hasher.finalize()
+}
+
+/// Compute the hash of address UTXOs data using the selected backend.
+pub fn compute_address_utxos_hash(
+    address_utxos: &[AddressUtxo],
+    algorithm: HashAlgorithm,
+) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for addr_utxo in address_utxos {
         let AddressUtxo {
@@ -39,56 +212,324 @@ pub fn compute_address_utxos_hash(address_utxos: &[AddressUtxo]) -> [u8; 32] {
             outpoint,
         } = addr_utxo;
         hasher.update(address.to_string().as_bytes());
-        hasher.update(height.to_le_bytes());
-        hasher.update(outpoint.to_bytes());
+        hasher.update(&height.to_le_bytes());
+        hasher.update(&outpoint.to_bytes());
     }
 
-    hasher.finalize().into()
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
 }
 
-/// Compute SHA256 hash of address balances data
-pub fn compute_address_balances_hash(balances: &[(Address, u128)]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Compute the hash of address balances data using the selected backend.
+pub fn compute_address_balances_hash(
+    balances: &[(Address, u128)],
+    algorithm: HashAlgorithm,
+) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for (address, balance) in balances {
         hasher.update(address.to_string().as_bytes());
-        hasher.update(balance.to_le_bytes());
+        hasher.update(&balance.to_le_bytes());
     }
 
-    hasher.finalize().into()
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
 }
 
-/// Compute SHA256 hash of block headers data
-pub fn compute_block_headers_hash(headers: &[(BlockHash, BlockHeaderBlob)]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Compute the hash of block headers data using the selected backend.
+pub fn compute_block_headers_hash(
+    headers: &[(BlockHash, BlockHeaderBlob)],
+    algorithm: HashAlgorithm,
+) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for (hash, header_blob) in headers {
-        hasher.update(hash.to_bytes());
+        hasher.update(&hash.to_bytes());
         hasher.update(header_blob.as_slice());
     }
 
-    hasher.finalize().into()
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
 }
 
-/// Compute SHA256 hash of block heights data
-pub fn compute_block_heights_hash(heights: &[(Height, BlockHash)]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Compute the hash of block heights data using the selected backend.
+pub fn compute_block_heights_hash(
+    heights: &[(Height, BlockHash)],
+    algorithm: HashAlgorithm,
+) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for (height, hash) in heights {
-        hasher.update(height.to_le_bytes());
-        hasher.update(hash.to_bytes());
+        hasher.update(&height.to_le_bytes());
+        hasher.update(&hash.to_bytes());
+    }
+
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
+}
+
+/// Same commitment as [`compute_utxo_set_hash`], but with leaf serialization
+/// done across a `rayon` thread pool instead of one UTXO at a time.
+///
+/// The hasher still folds the serialized leaves in the original order, so the
+/// result is bit-for-bit identical to [`compute_utxo_set_hash`] for the same
+/// backend regardless of `parallelism` — only the (embarrassingly parallel)
+/// serialization step is parallelized, not the hash itself. Pin `parallelism`
+/// to 1 to get fully sequential, reproducible timing for benchmarking.
+pub fn compute_utxo_set_hash_parallel(
+    utxos: &[Utxo],
+    algorithm: HashAlgorithm,
+    parallelism: usize,
+) -> Digest32 {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallelism)
+        .build()
+        .expect("failed to build rayon thread pool");
+
+    let leaves: Vec<Vec<u8>> = pool.install(|| {
+        utxos
+            .par_iter()
+            .map(|utxo| {
+                let Utxo {
+                    outpoint,
+                    txout,
+                    height,
+                } = utxo;
+                let TxOut {
+                    value,
+                    script_pubkey,
+                } = txout;
+                let mut leaf = Storable::to_bytes(outpoint).into_owned();
+                leaf.extend_from_slice(&value.to_le_bytes());
+                leaf.extend_from_slice(script_pubkey);
+                leaf.extend_from_slice(&height.to_le_bytes());
+                leaf
+            })
+            .collect()
+    });
+
+    let mut hasher = algorithm.hasher();
+    for leaf in &leaves {
+        hasher.update(leaf);
     }
 
-    hasher.finalize().into()
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
 }
 
-/// Compute combined hash of individual hashes
-pub fn compute_combined_hash(hashes: &[[u8; 32]]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
+/// Compute the combined hash of individual hashes using the selected backend.
+///
+/// All inputs must share the same algorithm; mixing digests from different
+/// backends into one combined hash would make the result meaningless.
+pub fn compute_combined_hash(hashes: &[Digest32], algorithm: HashAlgorithm) -> Digest32 {
+    let mut hasher = algorithm.hasher();
 
     for hash in hashes {
-        hasher.update(hash);
+        assert_eq!(
+            hash.algorithm, algorithm,
+            "cannot combine digests produced by different hash backends"
+        );
+        hasher.update(&hash.bytes);
+    }
+
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
+}
+
+/// Domain-separation tag bytes for each [`CanisterData`] collection, fed
+/// once at the start of that collection's section in
+/// [`compute_state_digest`]. Keeps, say, an empty `block_heights` from
+/// hashing identically to an empty `block_headers`.
+const STATE_DIGEST_UTXOS_TAG: u8 = 0x01;
+const STATE_DIGEST_ADDRESS_UTXOS_TAG: u8 = 0x02;
+const STATE_DIGEST_BALANCES_TAG: u8 = 0x03;
+const STATE_DIGEST_BLOCK_HEADERS_TAG: u8 = 0x04;
+const STATE_DIGEST_BLOCK_HEIGHTS_TAG: u8 = 0x05;
+
+/// Feeds a length-prefixed `record` into `hasher`, so a hasher reading a
+/// variable-length stream of records can tell where one ends and the next
+/// begins without needing a separator byte that might collide with record
+/// contents.
+fn update_length_prefixed(hasher: &mut dyn Hasher, record: &[u8]) {
+    hasher.update(&(record.len() as u64).to_le_bytes());
+    hasher.update(record);
+}
+
+/// A single deterministic digest over every collection in `data` -- UTXOs,
+/// address UTXOs, address balances, block headers, and block heights --
+/// rather than just the UTXO set. Each collection contributes a
+/// domain-separation tag byte, then each of its records length-prefixed, in
+/// the same field order the equivalent `compute_*_hash` function above
+/// uses. Two independently-synced canisters whose `CanisterData` (sorted
+/// into canonical order before hashing, as `state-reader`'s CLI already
+/// does) produce the same digest here can be considered byte-identical
+/// without comparing every record.
+pub fn compute_state_digest(data: &CanisterData, algorithm: HashAlgorithm) -> Digest32 {
+    let mut hasher = algorithm.hasher();
+
+    hasher.update(&[STATE_DIGEST_UTXOS_TAG]);
+    for utxo in &data.utxos {
+        let Utxo {
+            outpoint,
+            txout,
+            height,
+        } = utxo;
+        let TxOut {
+            value,
+            script_pubkey,
+        } = txout;
+        let mut record = Storable::to_bytes(outpoint).into_owned();
+        record.extend_from_slice(&value.to_le_bytes());
+        record.extend_from_slice(script_pubkey);
+        record.extend_from_slice(&height.to_le_bytes());
+        update_length_prefixed(&mut *hasher, &record);
+    }
+
+    hasher.update(&[STATE_DIGEST_ADDRESS_UTXOS_TAG]);
+    for addr_utxo in &data.address_utxos {
+        let AddressUtxo {
+            address,
+            height,
+            outpoint,
+        } = addr_utxo;
+        let mut record = address.to_string().into_bytes();
+        record.extend_from_slice(&height.to_le_bytes());
+        record.extend_from_slice(&outpoint.to_bytes());
+        update_length_prefixed(&mut *hasher, &record);
+    }
+
+    hasher.update(&[STATE_DIGEST_BALANCES_TAG]);
+    for (address, balance) in &data.balances {
+        let mut record = address.to_string().into_bytes();
+        record.extend_from_slice(&balance.to_le_bytes());
+        update_length_prefixed(&mut *hasher, &record);
+    }
+
+    hasher.update(&[STATE_DIGEST_BLOCK_HEADERS_TAG]);
+    for (hash, header_blob) in &data.block_headers {
+        let mut record = hash.to_bytes().to_vec();
+        record.extend_from_slice(header_blob.as_slice());
+        update_length_prefixed(&mut *hasher, &record);
     }
 
-    hasher.finalize().into()
+    hasher.update(&[STATE_DIGEST_BLOCK_HEIGHTS_TAG]);
+    for (height, hash) in &data.block_heights {
+        let mut record = height.to_le_bytes().to_vec();
+        record.extend_from_slice(&hash.to_bytes());
+        update_length_prefixed(&mut *hasher, &record);
+    }
+
+    Digest32 {
+        bytes: hasher.finalize(),
+        algorithm,
+    }
+}
+
+fn double_sha256(data: &[u8]) -> [u8; 32] {
+    let first: [u8; 32] = Sha256::digest(data).into();
+    Sha256::digest(first).into()
+}
+
+/// Fold a list of leaf digests into a Bitcoin-style binary Merkle root:
+/// double-SHA256 of each adjacent pair, duplicating the last node on odd
+/// levels, until a single root remains. The empty-set root is all-zero.
+///
+/// Unlike [`crate::merkle`], which domain-separates leaf and node hashes so
+/// it can also hand out inclusion proofs, this intentionally mirrors
+/// Bitcoin's own merkle root construction bit-for-bit, since the point of a
+/// `utxo_set_commitment` is to let two independently-produced state dumps be
+/// compared (or compared against external tooling that expects the same
+/// construction), not to prove membership.
+fn double_sha256_merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+        level = level
+            .chunks_exact(2)
+            .map(|pair| {
+                let mut buf = Vec::with_capacity(64);
+                buf.extend_from_slice(&pair[0]);
+                buf.extend_from_slice(&pair[1]);
+                double_sha256(&buf)
+            })
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Compute a Bitcoin-style Merkle commitment over `data`'s UTXO set, for
+/// proving two state dumps represent the same ledger without a byte-for-byte
+/// diff.
+///
+/// UTXOs are sorted by the existing [`Ord for Utxo`](Utxo) before leaf
+/// hashing, so the commitment is independent of the order `data.utxos` was
+/// assembled in (parallel readers, merged regions, etc. all agree).
+pub fn utxo_set_commitment(data: &CanisterData) -> [u8; 32] {
+    let mut utxos: Vec<&Utxo> = data.utxos.iter().collect();
+    utxos.sort();
+
+    let leaves = utxos
+        .into_iter()
+        .map(|utxo| {
+            let Utxo {
+                outpoint,
+                txout,
+                height,
+            } = utxo;
+            let TxOut {
+                value,
+                script_pubkey,
+            } = txout;
+
+            let mut preimage = Storable::to_bytes(outpoint).into_owned();
+            preimage.extend_from_slice(&value.to_le_bytes());
+            preimage.extend_from_slice(script_pubkey);
+            preimage.extend_from_slice(&height.to_le_bytes());
+            double_sha256(&preimage)
+        })
+        .collect();
+
+    double_sha256_merkle_root(leaves)
+}
+
+/// Compute a Bitcoin-style Merkle commitment over `data`'s balances map, as a
+/// companion to [`utxo_set_commitment`] so the two can be cross-checked
+/// independently -- a balances mismatch with a matching UTXO commitment
+/// points at the balance-indexing step rather than UTXO extraction, and vice
+/// versa.
+///
+/// Balances are sorted by address before leaf hashing, so the commitment is
+/// independent of `data.balances`'s original order.
+pub fn balance_set_commitment(data: &CanisterData) -> [u8; 32] {
+    let mut balances: Vec<&(Address, u128)> = data.balances.iter().collect();
+    balances.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let leaves = balances
+        .into_iter()
+        .map(|(address, balance)| {
+            let mut preimage = address.to_string().into_bytes();
+            preimage.extend_from_slice(&balance.to_le_bytes());
+            double_sha256(&preimage)
+        })
+        .collect();
+
+    double_sha256_merkle_root(leaves)
 }
@@ -8,9 +8,16 @@ use ic_stable_structures::{
     memory_manager::MemoryManager, storable::Blob, FileMemory, StableBTreeMap,
     Storable as StableStorable,
 };
+use rayon::prelude::*;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::{fs::File, path::Path};
 
+/// Number of raw entries decoded per rayon task in the `_parallel` region
+/// readers -- large enough that scheduling overhead doesn't dominate the
+/// (cheap) per-entry decode, small enough to keep the work evenly spread
+/// across threads.
+const DECODE_CHUNK_SIZE: usize = 10_000;
+
 static QUIET_FLAG: AtomicBool = AtomicBool::new(false);
 
 pub fn set_logging_quiet(quiet: bool) {
@@ -30,7 +37,18 @@ macro_rules! log {
     };
 }
 
+pub mod balance_distribution;
+pub mod compare;
+pub mod dump;
+pub mod export;
+pub mod external_sort;
 pub mod hash;
+pub mod merkle;
+pub mod repair;
+pub mod snapshot;
+pub mod stats;
+pub mod verify;
+pub mod writer;
 
 /// Memory IDs used by the Dogecoin canister for different memory regions.
 /// Match memory constants defined in `canister/src/memory.rs`.
@@ -49,6 +67,13 @@ pub mod memory_ids {
 #[derive(Debug, Clone, Copy)]
 pub struct ReaderOptions {
     pub read_utxos: bool,
+    /// Whether to also fold in the large UTXOs from the upgrades memory
+    /// region (see [`UtxoReader::read_large_utxos`]). Ignored if `read_utxos`
+    /// is `false`. Large UTXOs require the canister state to have already
+    /// been deserialized via `ic_doge_canister::post_upgrade`, so callers
+    /// that only want the BTree-backed small/medium entries can skip that
+    /// step entirely by leaving this `false`.
+    pub read_large_utxos: bool,
     pub read_balances: bool,
     pub read_headers: bool,
 }
@@ -85,6 +110,93 @@ pub struct CanisterData {
     pub block_heights: Vec<(Height, BlockHash)>,
 }
 
+/// Merge two outpoint-ordered UTXO streams into one, preserving order without
+/// collecting either side into memory.
+fn merge_by_outpoint(
+    mut left: impl Iterator<Item = Utxo>,
+    mut right: impl Iterator<Item = Utxo>,
+) -> impl Iterator<Item = Utxo> {
+    let outpoint_key = |utxo: &Utxo| (utxo.outpoint.txid, utxo.outpoint.vout);
+
+    let mut next_left = left.next();
+    let mut next_right = right.next();
+
+    std::iter::from_fn(move || match (&next_left, &next_right) {
+        (Some(l), Some(r)) => {
+            if outpoint_key(l) <= outpoint_key(r) {
+                std::mem::replace(&mut next_left, left.next())
+            } else {
+                std::mem::replace(&mut next_right, right.next())
+            }
+        }
+        (Some(_), None) => std::mem::replace(&mut next_left, left.next()),
+        (None, Some(_)) => std::mem::replace(&mut next_right, right.next()),
+        (None, None) => None,
+    })
+}
+
+/// Encode a [`Utxo`] to bytes for a spilled external-sort run: the fixed-size
+/// outpoint key (as stored in the small/medium UTXO maps), followed by the
+/// value, a length-prefixed script pubkey, and the height.
+pub(crate) fn encode_utxo(utxo: &Utxo) -> Vec<u8> {
+    let mut bytes = StableStorable::to_bytes(&utxo.outpoint).into_owned();
+    let TxOut { value, script_pubkey } = &utxo.txout;
+    bytes.extend_from_slice(&value.to_le_bytes());
+    bytes.extend_from_slice(&(script_pubkey.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(script_pubkey);
+    bytes.extend_from_slice(&utxo.height.to_le_bytes());
+    bytes
+}
+
+/// Inverse of [`encode_utxo`].
+pub(crate) fn decode_utxo(bytes: &[u8]) -> Utxo {
+    let (outpoint_bytes, rest) = bytes.split_at(UTXO_KEY_SIZE);
+    let outpoint = StableStorable::from_bytes(std::borrow::Cow::Borrowed(outpoint_bytes));
+
+    let (value_bytes, rest) = rest.split_at(8);
+    let value = u64::from_le_bytes(value_bytes.try_into().unwrap());
+
+    let (len_bytes, rest) = rest.split_at(4);
+    let script_len = u32::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    let (script_pubkey, rest) = rest.split_at(script_len);
+
+    let height = Height::from_le_bytes(rest.try_into().unwrap());
+
+    Utxo {
+        outpoint,
+        txout: TxOut {
+            value,
+            script_pubkey: script_pubkey.to_vec(),
+        },
+        height,
+    }
+}
+
+/// Decode raw `(outpoint_key_bytes, value_bytes)` pairs from the small/medium
+/// UTXO regions into [`Utxo`]s, chunking the work across a `rayon` thread
+/// pool instead of decoding one entry at a time.
+///
+/// `par_chunks` preserves input order in its output, so this produces the
+/// same sequence as decoding `entries` sequentially regardless of how many
+/// threads are available.
+fn decode_utxo_entries_parallel(entries: Vec<(Vec<u8>, Vec<u8>)>) -> Vec<Utxo> {
+    entries
+        .par_chunks(DECODE_CHUNK_SIZE)
+        .flat_map_iter(|chunk| {
+            chunk.iter().map(|(key_bytes, value_bytes)| {
+                let outpoint =
+                    StableStorable::from_bytes(std::borrow::Cow::Borrowed(key_bytes.as_slice()));
+                let (txout, height) = <(TxOut, Height)>::from_bytes(value_bytes.clone());
+                Utxo {
+                    outpoint,
+                    txout,
+                    height,
+                }
+            })
+        })
+        .collect()
+}
+
 /// UTXO reader that can read stable memory from a file
 pub struct UtxoReader {
     memory_manager: MemoryManager<FileMemory>,
@@ -106,17 +218,22 @@ impl UtxoReader {
 
         let ReaderOptions {
             read_utxos,
+            read_large_utxos,
             read_balances,
             read_headers,
         } = options;
 
-        let utxos = if read_utxos {
+        let mut utxos = if read_utxos {
             self.read_utxos()
         } else {
             log!("Skipping UTXOs");
             Vec::new()
         };
 
+        if read_utxos && read_large_utxos {
+            utxos.extend(self.read_large_utxos());
+        }
+
         let (address_utxos, balances) = if read_balances {
             (self.read_address_utxos(), self.read_balances())
         } else {
@@ -140,6 +257,184 @@ impl UtxoReader {
         }
     }
 
+    /// Like [`read_data`](Self::read_data), but reads the independent map
+    /// families -- UTXOs, address-utxos/balances, and headers/heights -- on a
+    /// `rayon` thread pool instead of one after another, in addition to using
+    /// [`read_utxos_parallel`](Self::read_utxos_parallel) so the UTXO regions
+    /// themselves are also read concurrently with chunked, parallel decoding.
+    ///
+    /// Each family already opens its own `MemoryManager::get` handle, so
+    /// there's no shared state across the `rayon::join` calls below beyond
+    /// the (immutable) `MemoryManager` itself.
+    pub fn read_data_parallel(&self, options: ReaderOptions) -> CanisterData {
+        log!("Reading canister data from stable memory (parallel)...");
+
+        let ReaderOptions {
+            read_utxos,
+            read_large_utxos,
+            read_balances,
+            read_headers,
+        } = options;
+
+        let (utxos, (address_utxos_and_balances, block_headers_and_heights)) = rayon::join(
+            || {
+                let mut utxos = if read_utxos {
+                    self.read_utxos_parallel()
+                } else {
+                    log!("Skipping UTXOs");
+                    Vec::new()
+                };
+
+                if read_utxos && read_large_utxos {
+                    utxos.extend(self.read_large_utxos());
+                }
+                utxos
+            },
+            || {
+                rayon::join(
+                    || {
+                        if read_balances {
+                            (self.read_address_utxos(), self.read_balances())
+                        } else {
+                            log!("Skipping address-utxos and balances");
+                            (Vec::new(), Vec::new())
+                        }
+                    },
+                    || {
+                        if read_headers {
+                            (self.read_block_headers(), self.read_block_heights())
+                        } else {
+                            log!("Skipping block headers and heights");
+                            (Vec::new(), Vec::new())
+                        }
+                    },
+                )
+            },
+        );
+
+        let (address_utxos, balances) = address_utxos_and_balances;
+        let (block_headers, block_heights) = block_headers_and_heights;
+
+        CanisterData {
+            utxos,
+            address_utxos,
+            balances,
+            block_headers,
+            block_heights,
+        }
+    }
+
+    /// Iterate over all UTXOs without materializing them into a `Vec`.
+    ///
+    /// Small and medium UTXOs live in separate `StableBTreeMap` regions, each
+    /// already yielding entries in outpoint order. Rather than collecting both
+    /// regions and sorting, this does a k-way merge of the two lazy streams so
+    /// the full set is never resident in memory at once, which matters on
+    /// mainnet chainstate where materializing every UTXO can exhaust RAM.
+    ///
+    /// Large UTXOs live in the upgrades memory region and are not covered by
+    /// this method; callers that need them must still read that region
+    /// separately, as with [`read_utxos`](Self::read_utxos).
+    pub fn iter_utxos(&self) -> impl Iterator<Item = Utxo> + '_ {
+        merge_by_outpoint(self.iter_small_utxos(), self.iter_medium_utxos())
+    }
+
+    /// Iterate over all UTXOs in the height-first order [`Utxo::cmp`] (and so
+    /// [`hash::compute_utxo_set_hash`]) expects, without ever holding the
+    /// full set in memory.
+    ///
+    /// [`iter_utxos`](Self::iter_utxos) is already sorted, but by outpoint --
+    /// the order the underlying `StableBTreeMap`s store their keys in, which
+    /// is free. Re-ordering by height instead takes a real sort, so this
+    /// spills buffered runs to `run_dir` via [`external_sort::ExternalSorter`]
+    /// and k-way merges them back, bounding peak memory to one run's worth
+    /// of UTXOs rather than the whole set.
+    ///
+    /// `extra` is merged in alongside the small/medium UTXOs -- for large
+    /// UTXOs, which live outside the memory regions this reader covers and
+    /// so have to be collected by the caller first.
+    pub fn iter_utxos_by_height(
+        &self,
+        run_dir: impl Into<std::path::PathBuf>,
+        extra: impl IntoIterator<Item = Utxo>,
+    ) -> std::io::Result<impl Iterator<Item = Utxo>> {
+        let mut sorter = external_sort::ExternalSorter::new(
+            run_dir,
+            |utxo: &Utxo| utxo.clone(),
+            encode_utxo,
+            decode_utxo,
+        )?;
+        for utxo in self.iter_utxos().chain(extra) {
+            sorter.push(utxo)?;
+        }
+        sorter.finish()
+    }
+
+    /// Lazily iterate small UTXOs in outpoint order.
+    fn iter_small_utxos(&self) -> impl Iterator<Item = Utxo> + '_ {
+        let small_memory = self.memory_manager.get(memory_ids::SMALL_UTXOS);
+        let small_utxos_map: StableBTreeMap<
+            Blob<UTXO_KEY_SIZE>,
+            Blob<UTXO_VALUE_MAX_SIZE_SMALL>,
+            _,
+        > = StableBTreeMap::init(small_memory);
+
+        small_utxos_map.into_iter().map(|entry| {
+            let outpoint =
+                StableStorable::from_bytes(std::borrow::Cow::Borrowed(entry.key().as_slice()));
+            let (txout, height) = <(TxOut, Height)>::from_bytes(entry.value().as_slice().to_vec());
+            Utxo {
+                outpoint,
+                txout,
+                height,
+            }
+        })
+    }
+
+    /// Lazily iterate medium UTXOs in outpoint order.
+    fn iter_medium_utxos(&self) -> impl Iterator<Item = Utxo> + '_ {
+        let medium_memory = self.memory_manager.get(memory_ids::MEDIUM_UTXOS);
+        let medium_utxos_map: StableBTreeMap<
+            Blob<UTXO_KEY_SIZE>,
+            Blob<UTXO_VALUE_MAX_SIZE_MEDIUM>,
+            _,
+        > = StableBTreeMap::init(medium_memory);
+
+        medium_utxos_map.into_iter().map(|entry| {
+            let outpoint =
+                StableStorable::from_bytes(std::borrow::Cow::Borrowed(entry.key().as_slice()));
+            let (txout, height) = <(TxOut, Height)>::from_bytes(entry.value().as_slice().to_vec());
+            Utxo {
+                outpoint,
+                txout,
+                height,
+            }
+        })
+    }
+
+    /// Read all UTXOs from stable memory, reading the small and medium memory
+    /// regions concurrently on a `rayon` thread pool, and decoding each
+    /// region's entries in parallel too instead of one at a time.
+    ///
+    /// The regions are independent `StableBTreeMap`s, so there is no shared
+    /// state to synchronize across them; within a region, the `StableBTreeMap`
+    /// cursor itself is walked sequentially (that's cheap -- it's just raw
+    /// bytes), but the `from_bytes` decoding of each entry is chunked across
+    /// the pool via [`decode_utxo_entries_parallel`]. Entries are decoded in
+    /// the same order they were read in regardless of chunk count, so the
+    /// result is the same small-then-medium, outpoint-ordered sequence
+    /// [`read_utxos`](Self::read_utxos) produces, just computed concurrently.
+    pub fn read_utxos_parallel(&self) -> Vec<Utxo> {
+        log!("Reading UTXOs from stable memory (parallel regions, parallel decode)...");
+        let (mut small_utxos, medium_utxos) = rayon::join(
+            || decode_utxo_entries_parallel(self.collect_small_utxo_entries()),
+            || decode_utxo_entries_parallel(self.collect_medium_utxo_entries()),
+        );
+
+        small_utxos.extend(medium_utxos);
+        small_utxos
+    }
+
     /// Read all UTXOs from stable memory
     pub fn read_utxos(&self) -> Vec<Utxo> {
         log!("Reading UTXOs from stable memory...");
@@ -153,15 +448,39 @@ impl UtxoReader {
         let medium_utxos = self.extract_medium_utxos();
         utxos.extend(medium_utxos);
 
-        // Note: Large UTXOs must be accessed separately as they are stored
-        // in a separate memory region (upgrades memory region 0)
+        // Note: Large UTXOs must be accessed separately via
+        // `read_large_utxos`, as they are stored in a separate memory region
+        // (upgrades memory region 0).
 
         utxos
     }
 
-    /// Read small UTXOs from stable memory
-    fn read_small_utxos(&self) -> Vec<Utxo> {
-        log!("  Reading small UTXOs...");
+    /// Read large UTXOs from the upgrades memory region (memory region 0).
+    ///
+    /// Unlike the small/medium regions, large UTXOs aren't stored in their
+    /// own `StableBTreeMap` -- they're part of the heap-resident state that
+    /// `ic_doge_canister::post_upgrade` reconstructs from the upgrades
+    /// memory blob, so this reads through `ic_doge_canister::with_state`
+    /// rather than decoding raw memory itself. Callers must have already run
+    /// `ic_doge_canister::post_upgrade` against this reader's state file (as
+    /// `main.rs`'s state-loading path does) for the large-UTXO map to be
+    /// populated; otherwise this returns an empty `Vec`.
+    pub fn read_large_utxos(&self) -> Vec<Utxo> {
+        log!("Reading large UTXOs from canister state...");
+        ic_doge_canister::with_state(|state| state.utxos.utxos.large_utxos.clone())
+            .into_iter()
+            .map(|(outpoint, (txout, height))| Utxo {
+                outpoint,
+                txout,
+                height,
+            })
+            .collect()
+    }
+
+    /// Collect the small-UTXO region's raw `(key, value)` bytes, still
+    /// undecoded, so the decode step can be parallelized separately. See
+    /// [`decode_utxo_entries_parallel`].
+    fn collect_small_utxo_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
         let small_memory = self.memory_manager.get(memory_ids::SMALL_UTXOS);
         let small_utxos_map: StableBTreeMap<
             Blob<UTXO_KEY_SIZE>,
@@ -169,19 +488,41 @@ impl UtxoReader {
             _,
         > = StableBTreeMap::init(small_memory);
 
+        small_utxos_map
+            .iter()
+            .map(|entry| (entry.key().as_slice().to_vec(), entry.value().as_slice().to_vec()))
+            .collect()
+    }
+
+    /// Collect the medium-UTXO region's raw `(key, value)` bytes; see
+    /// [`collect_small_utxo_entries`](Self::collect_small_utxo_entries).
+    fn collect_medium_utxo_entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let medium_memory = self.memory_manager.get(memory_ids::MEDIUM_UTXOS);
+        let medium_utxos_map: StableBTreeMap<
+            Blob<UTXO_KEY_SIZE>,
+            Blob<UTXO_VALUE_MAX_SIZE_MEDIUM>,
+            _,
+        > = StableBTreeMap::init(medium_memory);
+
+        medium_utxos_map
+            .iter()
+            .map(|entry| (entry.key().as_slice().to_vec(), entry.value().as_slice().to_vec()))
+            .collect()
+    }
+
+    /// Read small UTXOs from stable memory.
+    ///
+    /// Thin collector over [`iter_small_utxos`](Self::iter_small_utxos) so the
+    /// decode logic only lives in one place; only this eager path needs the
+    /// periodic progress logging, since the lazy iterator is also used by
+    /// callers that never intend to hold the whole region in memory.
+    fn read_small_utxos(&self) -> Vec<Utxo> {
+        log!("  Reading small UTXOs...");
         let mut utxos = Vec::new();
         let mut count = 0;
 
-        for outpoint_to_small_utxo in small_utxos_map.iter() {
-            let outpoint =
-                StableStorable::from_bytes(std::borrow::Cow::Borrowed(outpoint_to_small_utxo.key().as_slice()));
-            let (txout, height) = <(TxOut, Height)>::from_bytes(outpoint_to_small_utxo.value().as_slice().to_vec());
-
-            utxos.push(Utxo {
-                outpoint,
-                txout,
-                height,
-            });
+        for utxo in self.iter_small_utxos() {
+            utxos.push(utxo);
 
             count += 1;
             if count % 1_000_000 == 0 {
@@ -192,29 +533,17 @@ impl UtxoReader {
         utxos
     }
 
-    /// Extract medium UTXOs from stable memory
+    /// Extract medium UTXOs from stable memory.
+    ///
+    /// Thin collector over [`iter_medium_utxos`](Self::iter_medium_utxos); see
+    /// [`read_small_utxos`](Self::read_small_utxos).
     fn extract_medium_utxos(&self) -> Vec<Utxo> {
         log!("  Reading medium UTXOs...");
-        let medium_memory = self.memory_manager.get(memory_ids::MEDIUM_UTXOS);
-        let medium_utxos_map: StableBTreeMap<
-            Blob<UTXO_KEY_SIZE>,
-            Blob<UTXO_VALUE_MAX_SIZE_MEDIUM>,
-            _,
-        > = StableBTreeMap::init(medium_memory);
-
         let mut utxos = Vec::new();
         let mut count = 0;
 
-        for outpoint_to_medium_utxo in medium_utxos_map.iter() {
-            let outpoint =
-                StableStorable::from_bytes(std::borrow::Cow::Borrowed(outpoint_to_medium_utxo.key().as_slice()));
-            let (txout, height) = <(TxOut, Height)>::from_bytes(outpoint_to_medium_utxo.value().as_slice().to_vec());
-
-            utxos.push(Utxo {
-                outpoint,
-                txout,
-                height,
-            });
+        for utxo in self.iter_medium_utxos() {
+            utxos.push(utxo);
 
             count += 1;
             if count % 1_000_000 == 0 {
@@ -0,0 +1,175 @@
+//! Merkle-tree commitment over the UTXO set.
+//!
+//! Unlike [`crate::hash::compute_utxo_set_hash`], which folds every UTXO into
+//! a single flat digest, this commits to the set as a binary Merkle tree so a
+//! verifier can check that one UTXO is a member of the committed set without
+//! re-streaming the whole set.
+
+use crate::Utxo;
+use ic_doge_canister::types::TxOut;
+use ic_stable_structures::Storable;
+use sha2::{Digest, Sha256};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn leaf_hash(utxo: &Utxo) -> [u8; 32] {
+    let Utxo {
+        outpoint,
+        txout,
+        height,
+    } = utxo;
+    let TxOut {
+        value,
+        script_pubkey,
+    } = txout;
+
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(Storable::to_bytes(outpoint));
+    hasher.update(value.to_le_bytes());
+    hasher.update(script_pubkey);
+    hasher.update(height.to_le_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A sibling hash on the path from a leaf to the root, tagged with which side
+/// it sits on so [`verify`] knows the pairing order to reconstruct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofStep {
+    Left([u8; 32]),
+    Right([u8; 32]),
+}
+
+/// An inclusion proof that a leaf is part of the committed set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub steps: Vec<ProofStep>,
+}
+
+/// Compute the Merkle root over UTXOs in their existing sorted [`Utxo`] order,
+/// so the root is reproducible regardless of how the set was assembled.
+///
+/// The root of an empty set is the all-zero digest.
+pub fn compute_utxo_merkle_root(utxos: impl ExactSizeIterator<Item = Utxo>) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = utxos.map(|utxo| leaf_hash(&utxo)).collect();
+
+    if level.is_empty() {
+        return [0u8; 32];
+    }
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+        level = level
+            .chunks_exact(2)
+            .map(|pair| node_hash(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    level[0]
+}
+
+/// Build an inclusion proof for the leaf at `index` within `utxos` (given in
+/// the same sorted [`Utxo`] order used to compute the root).
+pub fn prove(utxos: &[Utxo], index: usize) -> Option<MerkleProof> {
+    if index >= utxos.len() {
+        return None;
+    }
+
+    let mut level: Vec<[u8; 32]> = utxos.iter().map(leaf_hash).collect();
+    let mut steps = Vec::new();
+    let mut pos = index;
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+
+        let sibling_pos = pos ^ 1;
+        if sibling_pos % 2 == 0 {
+            steps.push(ProofStep::Left(level[sibling_pos]));
+        } else {
+            steps.push(ProofStep::Right(level[sibling_pos]));
+        }
+
+        level = level
+            .chunks_exact(2)
+            .map(|pair| node_hash(&pair[0], &pair[1]))
+            .collect();
+        pos /= 2;
+    }
+
+    Some(MerkleProof { steps })
+}
+
+/// Verify that `leaf` is included under `root` per the sibling path in `proof`.
+pub fn verify(leaf: &Utxo, proof: &MerkleProof, root: [u8; 32]) -> bool {
+    let mut current = leaf_hash(leaf);
+
+    for step in &proof.steps {
+        current = match step {
+            ProofStep::Left(sibling) => node_hash(sibling, &current),
+            ProofStep::Right(sibling) => node_hash(&current, sibling),
+        };
+    }
+
+    current == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_doge_types::{OutPoint, Txid};
+
+    fn utxo(seed: u8, height: u32) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::from(vec![seed; 32]), 0),
+            txout: TxOut {
+                value: 100,
+                script_pubkey: vec![seed],
+            },
+            height,
+        }
+    }
+
+    #[test]
+    fn empty_set_has_all_zero_root() {
+        assert_eq!(compute_utxo_merkle_root(std::iter::empty()), [0u8; 32]);
+    }
+
+    #[test]
+    fn prove_and_verify_round_trip_every_leaf() {
+        let utxos: Vec<Utxo> = (0..7).map(|i| utxo(i, i as u32)).collect();
+        let root = compute_utxo_merkle_root(utxos.iter().cloned());
+
+        for (index, leaf) in utxos.iter().enumerate() {
+            let proof = prove(&utxos, index).unwrap();
+            assert!(verify(leaf, &proof, root));
+        }
+    }
+
+    #[test]
+    fn verify_rejects_mismatched_utxo() {
+        let utxos: Vec<Utxo> = (0..3).map(|i| utxo(i, i as u32)).collect();
+        let root = compute_utxo_merkle_root(utxos.iter().cloned());
+
+        let proof = prove(&utxos, 0).unwrap();
+        assert!(!verify(&utxo(99, 99), &proof, root));
+    }
+
+    #[test]
+    fn prove_out_of_range_is_none() {
+        let utxos: Vec<Utxo> = (0..3).map(|i| utxo(i, i as u32)).collect();
+        assert!(prove(&utxos, 3).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Quarantine-instead-of-abort repair for the checks in `check_invariants`.
+//!
+//! Borrows the check/repair split common in filesystem-consistency tooling
+//! (e.g. `thin_check`/`thin_repair`): `check_invariants` stays strict and
+//! exits non-zero on the first violation, while [`repair`] salvages what it
+//! can -- dropping duplicate or all-zero headers, height-0 UTXOs, and
+//! anything past the first gap in block-height continuity -- and reports
+//! exactly what it had to throw away and why.
+
+use crate::{CanisterData, Utxo};
+use ic_doge_types::BlockHash;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// One group of records removed (or truncated) for a single reason.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedRecords {
+    pub category: &'static str,
+    pub violation: &'static str,
+    pub count: usize,
+    pub detail: String,
+}
+
+/// Everything [`repair`] had to quarantine, in the order it found it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RepairReport {
+    pub removed: Vec<RemovedRecords>,
+}
+
+impl RepairReport {
+    fn remove(&mut self, category: &'static str, violation: &'static str, count: usize, detail: String) {
+        if count > 0 {
+            self.removed.push(RemovedRecords { category, violation, count, detail });
+        }
+    }
+}
+
+/// Quarantines every record that would otherwise fail `check_invariants`.
+pub fn repair(mut data: CanisterData, mut utxos: Vec<Utxo>) -> (CanisterData, Vec<Utxo>, RepairReport) {
+    let mut report = RepairReport::default();
+
+    // Height-0 UTXOs are unspendable by consensus rules.
+    let before = utxos.len();
+    utxos.retain(|utxo| utxo.height != 0);
+    report.remove(
+        "utxos",
+        "height-zero",
+        before - utxos.len(),
+        "UTXOs at height 0 (genesis has no spendable UTXOs)".to_string(),
+    );
+
+    // Drop undersized or all-zero headers.
+    let zero_hash = BlockHash::from(vec![0u8; 32]);
+    let before = data.block_headers.len();
+    data.block_headers
+        .retain(|(hash, blob)| blob.as_slice().len() >= 80 && *hash != zero_hash);
+    report.remove(
+        "block_headers",
+        "undersized-or-zero-header",
+        before - data.block_headers.len(),
+        "headers smaller than 80 bytes or all-zeros".to_string(),
+    );
+
+    // Drop duplicate headers, keeping the first occurrence of each hash.
+    let mut seen_hashes = HashSet::new();
+    let before = data.block_headers.len();
+    data.block_headers.retain(|(hash, _)| seen_hashes.insert(hash.clone()));
+    report.remove(
+        "block_headers",
+        "duplicate-header",
+        before - data.block_headers.len(),
+        "duplicate block hash".to_string(),
+    );
+
+    // Drop duplicate heights, keeping the first occurrence of each height.
+    let mut seen_heights = HashSet::new();
+    let before = data.block_heights.len();
+    data.block_heights.retain(|(height, _)| seen_heights.insert(*height));
+    report.remove(
+        "block_heights",
+        "duplicate-height",
+        before - data.block_heights.len(),
+        "duplicate block height".to_string(),
+    );
+
+    // Keep headers and heights in lockstep: each side only keeps hashes the
+    // other side also has.
+    let height_hashes: HashSet<BlockHash> = data.block_heights.iter().map(|(_, hash)| hash.clone()).collect();
+    let before = data.block_headers.len();
+    data.block_headers.retain(|(hash, _)| height_hashes.contains(hash));
+    report.remove(
+        "block_headers",
+        "no-matching-height",
+        before - data.block_headers.len(),
+        "header has no corresponding block_heights entry".to_string(),
+    );
+
+    let header_hashes: HashSet<BlockHash> = data.block_headers.iter().map(|(hash, _)| hash.clone()).collect();
+    let before = data.block_heights.len();
+    data.block_heights.retain(|(_, hash)| header_hashes.contains(hash));
+    report.remove(
+        "block_heights",
+        "no-matching-header",
+        before - data.block_heights.len(),
+        "height has no corresponding block_headers entry".to_string(),
+    );
+
+    // Truncate to the largest gap-free prefix of heights.
+    data.block_heights.sort_by_key(|(height, _)| *height);
+    if let Some(&(first_height, _)) = data.block_heights.first() {
+        let mut last = first_height;
+        let mut cutoff = data.block_heights.len();
+        for (i, (height, _)) in data.block_heights.iter().enumerate().skip(1) {
+            if *height != last + 1 {
+                cutoff = i;
+                break;
+            }
+            last = *height;
+        }
+
+        if cutoff < data.block_heights.len() {
+            let first_gap_height = last + 1;
+            let dropped = data.block_heights.len() - cutoff;
+            let valid_hashes: HashSet<BlockHash> =
+                data.block_heights[..cutoff].iter().map(|(_, hash)| hash.clone()).collect();
+            data.block_heights.truncate(cutoff);
+            data.block_headers.retain(|(hash, _)| valid_hashes.contains(hash));
+            report.remove(
+                "block_heights",
+                "height-gap",
+                dropped,
+                format!("heights from {first_gap_height} onward, past the first gap"),
+            );
+        }
+    }
+
+    (data, utxos, report)
+}
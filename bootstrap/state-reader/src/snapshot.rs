@@ -0,0 +1,560 @@
+//! A compressed, block-checksummed intermediate snapshot of an already
+//! read-and-sorted [`CanisterData`]/UTXO set, so a second run of the tool
+//! can skip re-deserializing `canister_state.bin` entirely.
+//!
+//! The layout mirrors the block-with-checksum segments an LSM storage
+//! engine writes: each data type is split into fixed-size *blocks* of
+//! records, each block is LZ4-compressed and prefixed with its uncompressed
+//! length and an xxh3 checksum of the compressed bytes, and a footer at the
+//! end of the file indexes every block's file offset plus its key range.
+//! That makes blocks independently addressable -- [`SnapshotReader::verify`]
+//! can point at exactly which block failed its checksum instead of failing
+//! the whole parse, and [`SnapshotReader::read_height_range`] can skip
+//! decompressing blocks outside the requested range for the data types
+//! that are naturally keyed by height.
+
+use crate::{CanisterData, Utxo};
+use ic_doge_canister::types::{Address, AddressUtxo, BlockHeaderBlob};
+use ic_doge_interface::Height;
+use ic_doge_types::BlockHash;
+use ic_stable_structures::Storable as StableStorable;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"DOGESNAP";
+const VERSION: u32 = 1;
+
+/// Records per block. Chosen so a block compresses to roughly tens of KB for
+/// typical UTXO record sizes -- small enough that a failed checksum only
+/// costs re-reading a small range, large enough that LZ4's per-block
+/// overhead stays negligible.
+pub const BLOCK_RECORDS: usize = 8192;
+
+/// Per-category compression results, for the statistics report.
+#[derive(Debug, Clone)]
+pub struct CategoryStats {
+    pub name: String,
+    pub record_count: u64,
+    pub uncompressed_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+impl CategoryStats {
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            1.0
+        } else {
+            self.uncompressed_bytes as f64 / self.compressed_bytes as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BlockIndexEntry {
+    offset: u64,
+    compressed_len: u32,
+    uncompressed_len: u32,
+    checksum: u64,
+    min_key: u64,
+    max_key: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CategoryIndex {
+    name: String,
+    record_count: u64,
+    blocks: Vec<BlockIndexEntry>,
+}
+
+/// A checksum failure pinpointed to exactly one block of one data type.
+#[derive(Debug)]
+pub struct BlockCorruption {
+    pub category: String,
+    pub block_index: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for BlockCorruption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} block {}: checksum mismatch (expected {:016x}, got {:016x})",
+            self.category, self.block_index, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for BlockCorruption {}
+
+fn xxh3(bytes: &[u8]) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(bytes)
+}
+
+/// Writes blocks of one category to the snapshot file, tracking the index
+/// entries and compression stats needed to finish the file.
+struct CategoryWriter<'a, W> {
+    writer: &'a mut W,
+    name: &'static str,
+    record_count: u64,
+    uncompressed_bytes: u64,
+    compressed_bytes: u64,
+    blocks: Vec<BlockIndexEntry>,
+}
+
+impl<'a, W: Write + Seek> CategoryWriter<'a, W> {
+    fn new(writer: &'a mut W, name: &'static str) -> Self {
+        Self {
+            writer,
+            name,
+            record_count: 0,
+            uncompressed_bytes: 0,
+            compressed_bytes: 0,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Encodes, compresses and writes one block's worth of records. `key_of`
+    /// extracts the block's min/max range key (height for height-keyed
+    /// categories, or a constant for categories with no natural range).
+    fn write_block<T>(
+        &mut self,
+        records: &[T],
+        encode: impl Fn(&T) -> Vec<u8>,
+        key_of: impl Fn(&T) -> u64,
+    ) -> io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut uncompressed = Vec::new();
+        for record in records {
+            let bytes = encode(record);
+            uncompressed.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            uncompressed.extend_from_slice(&bytes);
+        }
+
+        let compressed = lz4_flex::block::compress_prepend_size(&uncompressed);
+        let checksum = xxh3(&compressed);
+        let offset = self.writer.stream_position()?;
+
+        self.writer.write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&(uncompressed.len() as u32).to_le_bytes())?;
+        self.writer.write_all(&checksum.to_le_bytes())?;
+        self.writer.write_all(&compressed)?;
+
+        let min_key = key_of(&records[0]);
+        let max_key = key_of(&records[records.len() - 1]);
+
+        self.record_count += records.len() as u64;
+        self.uncompressed_bytes += uncompressed.len() as u64;
+        self.compressed_bytes += compressed.len() as u64;
+        self.blocks.push(BlockIndexEntry {
+            offset,
+            compressed_len: compressed.len() as u32,
+            uncompressed_len: uncompressed.len() as u32,
+            checksum,
+            min_key,
+            max_key,
+        });
+
+        Ok(())
+    }
+
+    fn finish(self) -> (CategoryIndex, CategoryStats) {
+        (
+            CategoryIndex {
+                name: self.name.to_string(),
+                record_count: self.record_count,
+                blocks: self.blocks,
+            },
+            CategoryStats {
+                name: self.name.to_string(),
+                record_count: self.record_count,
+                uncompressed_bytes: self.uncompressed_bytes,
+                compressed_bytes: self.compressed_bytes,
+            },
+        )
+    }
+}
+
+/// Writes an already sorted [`CanisterData`]/UTXO set to a snapshot file.
+/// Returns the per-category compression stats for reporting.
+pub fn write(
+    path: &Path,
+    data: &CanisterData,
+    utxos: &[Utxo],
+) -> io::Result<Vec<CategoryStats>> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    let mut indexes = Vec::new();
+    let mut stats = Vec::new();
+
+    macro_rules! write_category {
+        ($name:literal, $records:expr, $encode:expr, $key_of:expr) => {{
+            let mut cat = CategoryWriter::new(&mut writer, $name);
+            for chunk in $records.chunks(BLOCK_RECORDS) {
+                cat.write_block(chunk, $encode, $key_of)?;
+            }
+            let (index, stat) = cat.finish();
+            indexes.push(index);
+            stats.push(stat);
+        }};
+    }
+
+    write_category!("utxos", utxos, crate::encode_utxo, |u: &Utxo| u.height as u64);
+    write_category!(
+        "address_utxos",
+        &data.address_utxos,
+        encode_address_utxo,
+        |au: &AddressUtxo| au.height as u64
+    );
+    write_category!(
+        "balances",
+        &data.balances,
+        encode_balance,
+        |_: &(Address, u128)| 0
+    );
+    write_category!(
+        "block_headers",
+        &data.block_headers,
+        encode_block_header,
+        |_: &(BlockHash, BlockHeaderBlob)| 0
+    );
+    write_category!(
+        "block_heights",
+        &data.block_heights,
+        encode_block_height,
+        |(height, _): &(Height, BlockHash)| *height as u64
+    );
+
+    write_footer(&mut writer, &indexes)?;
+    writer.flush()?;
+
+    Ok(stats)
+}
+
+fn write_footer<W: Write + Seek>(writer: &mut W, indexes: &[CategoryIndex]) -> io::Result<()> {
+    let mut footer = Vec::new();
+    for index in indexes {
+        let name_bytes = index.name.as_bytes();
+        footer.extend_from_slice(&(name_bytes.len() as u16).to_le_bytes());
+        footer.extend_from_slice(name_bytes);
+        footer.extend_from_slice(&index.record_count.to_le_bytes());
+        footer.extend_from_slice(&(index.blocks.len() as u32).to_le_bytes());
+        for block in &index.blocks {
+            footer.extend_from_slice(&block.offset.to_le_bytes());
+            footer.extend_from_slice(&block.compressed_len.to_le_bytes());
+            footer.extend_from_slice(&block.uncompressed_len.to_le_bytes());
+            footer.extend_from_slice(&block.checksum.to_le_bytes());
+            footer.extend_from_slice(&block.min_key.to_le_bytes());
+            footer.extend_from_slice(&block.max_key.to_le_bytes());
+        }
+    }
+
+    writer.write_all(&footer)?;
+    writer.write_all(&(footer.len() as u64).to_le_bytes())?;
+    Ok(())
+}
+
+fn encode_address_utxo(au: &AddressUtxo) -> Vec<u8> {
+    StableStorable::to_bytes(au).into_owned()
+}
+
+fn decode_address_utxo(bytes: &[u8]) -> AddressUtxo {
+    StableStorable::from_bytes(std::borrow::Cow::Borrowed(bytes))
+}
+
+pub(crate) fn encode_balance((address, balance): &(Address, u128)) -> Vec<u8> {
+    let mut bytes = StableStorable::to_bytes(address).into_owned();
+    bytes.extend_from_slice(&balance.to_le_bytes());
+    bytes
+}
+
+pub(crate) fn decode_balance(bytes: &[u8]) -> (Address, u128) {
+    let split = bytes.len() - 16;
+    let (address_bytes, balance_bytes) = bytes.split_at(split);
+    let address = StableStorable::from_bytes(std::borrow::Cow::Borrowed(address_bytes));
+    let balance = u128::from_le_bytes(balance_bytes.try_into().unwrap());
+    (address, balance)
+}
+
+pub(crate) fn encode_block_header((hash, blob): &(BlockHash, BlockHeaderBlob)) -> Vec<u8> {
+    let mut bytes = StableStorable::to_bytes(hash).into_owned();
+    bytes.extend_from_slice(blob.as_slice());
+    bytes
+}
+
+pub(crate) fn decode_block_header(bytes: &[u8]) -> (BlockHash, BlockHeaderBlob) {
+    let hash_len = BlockHash::BOUND.max_size() as usize;
+    let (hash_bytes, header_bytes) = bytes.split_at(hash_len);
+    let hash = StableStorable::from_bytes(std::borrow::Cow::Borrowed(hash_bytes));
+    let blob = BlockHeaderBlob::from_bytes(header_bytes.to_vec());
+    (hash, blob)
+}
+
+pub(crate) fn encode_block_height((height, hash): &(Height, BlockHash)) -> Vec<u8> {
+    let mut bytes = height.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&StableStorable::to_bytes(hash));
+    bytes
+}
+
+pub(crate) fn decode_block_height(bytes: &[u8]) -> (Height, BlockHash) {
+    let (height_bytes, hash_bytes) = bytes.split_at(4);
+    let height = Height::from_le_bytes(height_bytes.try_into().unwrap());
+    let hash = StableStorable::from_bytes(std::borrow::Cow::Borrowed(hash_bytes));
+    (height, hash)
+}
+
+/// Reads and verifies a snapshot written by [`write`].
+pub struct SnapshotReader {
+    reader: BufReader<File>,
+    categories: Vec<CategoryIndex>,
+}
+
+impl SnapshotReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a state-reader snapshot"));
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "unsupported snapshot version"));
+        }
+
+        let file_len = reader.get_ref().metadata()?.len();
+        reader.seek(SeekFrom::End(-8))?;
+        let mut footer_len_bytes = [0u8; 8];
+        reader.read_exact(&mut footer_len_bytes)?;
+        let footer_len = u64::from_le_bytes(footer_len_bytes);
+
+        reader.seek(SeekFrom::Start(file_len - 8 - footer_len))?;
+        let mut footer = vec![0u8; footer_len as usize];
+        reader.read_exact(&mut footer)?;
+
+        let categories = parse_footer(&footer);
+
+        Ok(Self { reader, categories })
+    }
+
+    /// Per-category compression stats, recovered from the footer without
+    /// touching any block.
+    pub fn stats(&self) -> Vec<CategoryStats> {
+        self.categories
+            .iter()
+            .map(|cat| {
+                let compressed: u64 = cat.blocks.iter().map(|b| b.compressed_len as u64).sum();
+                let uncompressed: u64 = cat.blocks.iter().map(|b| b.uncompressed_len as u64).sum();
+                CategoryStats {
+                    name: cat.name.clone(),
+                    record_count: cat.record_count,
+                    uncompressed_bytes: uncompressed,
+                    compressed_bytes: compressed,
+                }
+            })
+            .collect()
+    }
+
+    /// Decompresses and checksums every block of every category, returning
+    /// every corruption found rather than stopping at the first one.
+    pub fn verify(&mut self) -> io::Result<Vec<BlockCorruption>> {
+        let mut problems = Vec::new();
+        let categories = self.categories.clone();
+        for cat in &categories {
+            for (block_index, block) in cat.blocks.iter().enumerate() {
+                let compressed = self.read_block_bytes(block)?;
+                let actual = xxh3(&compressed);
+                if actual != block.checksum {
+                    problems.push(BlockCorruption {
+                        category: cat.name.clone(),
+                        block_index,
+                        expected: block.checksum,
+                        actual,
+                    });
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    fn read_block_bytes(&mut self, block: &BlockIndexEntry) -> io::Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(block.offset + 4 + 4 + 8))?;
+        let mut compressed = vec![0u8; block.compressed_len as usize];
+        self.reader.read_exact(&mut compressed)?;
+        Ok(compressed)
+    }
+
+    fn decode_block<T>(
+        &mut self,
+        block: &BlockIndexEntry,
+        decode: impl Fn(&[u8]) -> T,
+    ) -> io::Result<Vec<T>> {
+        let compressed = self.read_block_bytes(block)?;
+        let actual = xxh3(&compressed);
+        if actual != block.checksum {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch (expected {:016x}, got {:016x})", block.checksum, actual),
+            ));
+        }
+
+        let uncompressed = lz4_flex::block::decompress_size_prepended(&compressed)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < uncompressed.len() {
+            let len = u32::from_le_bytes(uncompressed[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += 4;
+            records.push(decode(&uncompressed[offset..offset + len]));
+            offset += len;
+        }
+        Ok(records)
+    }
+
+    fn category(&self, name: &str) -> Option<CategoryIndex> {
+        self.categories.iter().find(|c| c.name == name).cloned()
+    }
+
+    /// Reads every record of `category`, decompressing all of its blocks.
+    fn read_category<T>(&mut self, name: &str, decode: impl Fn(&[u8]) -> T) -> io::Result<Vec<T>> {
+        let cat = self
+            .category(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no '{name}' category in snapshot")))?;
+
+        let mut records = Vec::new();
+        for block in &cat.blocks {
+            records.extend(self.decode_block(block, &decode)?);
+        }
+        Ok(records)
+    }
+
+    /// Reads only the records of `category` whose blocks overlap
+    /// `[min_height, max_height]`, skipping any block entirely outside the
+    /// range without decompressing it. A block's key range is taken from its
+    /// first and last record, so this is only meaningful for categories
+    /// stored in height order (`utxos`, `block_heights`); `address_utxos`
+    /// are stored in address order, so their block ranges don't bound the
+    /// heights inside.
+    fn read_height_range<T>(
+        &mut self,
+        name: &str,
+        min_height: u64,
+        max_height: u64,
+        decode: impl Fn(&[u8]) -> T,
+    ) -> io::Result<Vec<T>> {
+        let cat = self
+            .category(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no '{name}' category in snapshot")))?;
+
+        let mut records = Vec::new();
+        for block in &cat.blocks {
+            if block.max_key < min_height || block.min_key > max_height {
+                continue;
+            }
+            records.extend(self.decode_block(block, &decode)?);
+        }
+        Ok(records)
+    }
+
+    pub fn read_utxos(&mut self) -> io::Result<Vec<Utxo>> {
+        self.read_category("utxos", crate::decode_utxo)
+    }
+
+    pub fn read_utxos_by_height(&mut self, min_height: u64, max_height: u64) -> io::Result<Vec<Utxo>> {
+        self.read_height_range("utxos", min_height, max_height, crate::decode_utxo)
+    }
+
+    pub fn read_address_utxos(&mut self) -> io::Result<Vec<AddressUtxo>> {
+        self.read_category("address_utxos", decode_address_utxo)
+    }
+
+    pub fn read_balances(&mut self) -> io::Result<Vec<(Address, u128)>> {
+        self.read_category("balances", decode_balance)
+    }
+
+    pub fn read_block_headers(&mut self) -> io::Result<Vec<(BlockHash, BlockHeaderBlob)>> {
+        self.read_category("block_headers", decode_block_header)
+    }
+
+    pub fn read_block_heights(&mut self) -> io::Result<Vec<(Height, BlockHash)>> {
+        self.read_category("block_heights", decode_block_height)
+    }
+
+    /// Reads the full snapshot back into a [`CanisterData`]/UTXO pair, the
+    /// same shape [`crate::UtxoReader::read_data`] produces.
+    pub fn read_all(&mut self) -> io::Result<(CanisterData, Vec<Utxo>)> {
+        let utxos = self.read_utxos()?;
+        let address_utxos = self.read_address_utxos()?;
+        let balances = self.read_balances()?;
+        let block_headers = self.read_block_headers()?;
+        let block_heights = self.read_block_heights()?;
+
+        Ok((
+            CanisterData {
+                utxos: utxos.clone(),
+                address_utxos,
+                balances,
+                block_headers,
+                block_heights,
+            },
+            utxos,
+        ))
+    }
+}
+
+fn parse_footer(footer: &[u8]) -> Vec<CategoryIndex> {
+    let mut categories = Vec::new();
+    let mut offset = 0;
+
+    while offset < footer.len() {
+        let name_len = u16::from_le_bytes(footer[offset..offset + 2].try_into().unwrap()) as usize;
+        offset += 2;
+        let name = String::from_utf8_lossy(&footer[offset..offset + name_len]).into_owned();
+        offset += name_len;
+
+        let record_count = u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+        offset += 8;
+        let block_count = u32::from_le_bytes(footer[offset..offset + 4].try_into().unwrap()) as usize;
+        offset += 4;
+
+        let mut blocks = Vec::with_capacity(block_count);
+        for _ in 0..block_count {
+            let block_offset = u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+            let compressed_len = u32::from_le_bytes(footer[offset..offset + 4].try_into().unwrap());
+            offset += 4;
+            let uncompressed_len = u32::from_le_bytes(footer[offset..offset + 4].try_into().unwrap());
+            offset += 4;
+            let checksum = u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+            let min_key = u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+            let max_key = u64::from_le_bytes(footer[offset..offset + 8].try_into().unwrap());
+            offset += 8;
+
+            blocks.push(BlockIndexEntry {
+                offset: block_offset,
+                compressed_len,
+                uncompressed_len,
+                checksum,
+                min_key,
+                max_key,
+            });
+        }
+
+        categories.push(CategoryIndex { name, record_count, blocks });
+    }
+
+    categories
+}
@@ -0,0 +1,152 @@
+//! Shared numeric summary used across the balance-distribution, header-size,
+//! and AuxPow-size sections of the report, so every section computes its
+//! median and percentile set through one correct code path instead of each
+//! re-implementing its own (the AuxPow section previously took `sorted[len /
+//! 2]` as the median, which is biased for even-length inputs).
+
+/// min/max/mean, a correct truncated median, and a standard percentile set
+/// over one sample.
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p10: f64,
+    pub p25: f64,
+    pub p75: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// Bitcoin's `CalculateTruncatedMedian`: the middle element for odd-length
+/// input, the average of the two central elements for even-length input.
+/// `sorted` must already be sorted ascending; returns `0.0` for an empty
+/// slice.
+pub fn truncated_median(sorted: &[f64]) -> f64 {
+    let len = sorted.len();
+    if len == 0 {
+        return 0.0;
+    }
+    if len % 2 == 1 {
+        sorted[len / 2]
+    } else {
+        (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+    }
+}
+
+/// Percentile via linear interpolation between the two nearest ranks.
+/// `sorted` must already be sorted ascending; returns `0.0` for an empty
+/// slice.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let p = p.clamp(0.0, 100.0);
+    let index = (p / 100.0) * (sorted.len() - 1) as f64;
+    let lower = index.floor() as usize;
+    let upper = index.ceil() as usize;
+
+    if lower == upper {
+        sorted[lower]
+    } else {
+        let weight = index - lower as f64;
+        sorted[lower] * (1.0 - weight) + sorted[upper] * weight
+    }
+}
+
+/// Summarizes `values`, which need not already be sorted.
+pub fn summarize(values: &[f64]) -> Summary {
+    if values.is_empty() {
+        return Summary {
+            min: 0.0,
+            max: 0.0,
+            mean: 0.0,
+            median: 0.0,
+            p10: 0.0,
+            p25: 0.0,
+            p75: 0.0,
+            p90: 0.0,
+            p95: 0.0,
+            p99: 0.0,
+        };
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Summary {
+        min: sorted[0],
+        max: *sorted.last().unwrap(),
+        mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        median: truncated_median(&sorted),
+        p10: percentile(&sorted, 10.0),
+        p25: percentile(&sorted, 25.0),
+        p75: percentile(&sorted, 75.0),
+        p90: percentile(&sorted, 90.0),
+        p95: percentile(&sorted, 95.0),
+        p99: percentile(&sorted, 99.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_median_odd_length() {
+        assert_eq!(truncated_median(&[1.0, 2.0, 3.0]), 2.0);
+    }
+
+    #[test]
+    fn truncated_median_even_length() {
+        assert_eq!(truncated_median(&[1.0, 2.0, 3.0, 4.0]), 2.5);
+    }
+
+    #[test]
+    fn truncated_median_single_element() {
+        assert_eq!(truncated_median(&[5.0]), 5.0);
+    }
+
+    #[test]
+    fn truncated_median_empty() {
+        assert_eq!(truncated_median(&[]), 0.0);
+    }
+
+    #[test]
+    fn percentile_empty() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn percentile_matches_min_and_max() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 100.0), 5.0);
+    }
+
+    #[test]
+    fn summarize_empty_returns_zeroed_summary() {
+        let summary = summarize(&[]);
+        assert_eq!(summary.min, 0.0);
+        assert_eq!(summary.max, 0.0);
+        assert_eq!(summary.mean, 0.0);
+        assert_eq!(summary.median, 0.0);
+    }
+
+    #[test]
+    fn summarize_even_length_uses_truncated_median() {
+        let summary = summarize(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+    }
+
+    #[test]
+    fn summarize_odd_length_uses_truncated_median() {
+        let summary = summarize(&[5.0, 1.0, 3.0]);
+        assert_eq!(summary.median, 3.0);
+    }
+}
@@ -0,0 +1,141 @@
+//! Turns the loose per-category digests in [`crate::hash`] into a structured,
+//! diffable manifest so a `canister_state.bin` can be attested end-to-end,
+//! e.g. in CI when validating a state upgrade.
+
+use crate::CanisterData;
+use crate::hash::{Digest32, HashAlgorithm};
+use ic_doge_types::OutPoint;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A single category's digest plus the number of entries that went into it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryManifest {
+    pub digest: String,
+    pub count: usize,
+}
+
+/// A signed-in-the-sense-of-attested manifest tying every category digest to
+/// the combined root, so a verifier can tell exactly which category diverged
+/// rather than just getting a mismatching opaque combined hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateManifest {
+    pub hash_algorithm: String,
+    pub categories: BTreeMap<String, CategoryManifest>,
+    pub combined: String,
+}
+
+impl StateManifest {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        Self {
+            hash_algorithm: algorithm.to_string(),
+            categories: BTreeMap::new(),
+            combined: String::new(),
+        }
+    }
+
+    pub fn insert_category(&mut self, name: &str, digest: &Digest32, count: usize) {
+        self.categories.insert(
+            name.to_string(),
+            CategoryManifest {
+                digest: hex::encode(digest.bytes),
+                count,
+            },
+        );
+    }
+
+    pub fn set_combined(&mut self, digest: &Digest32) {
+        self.combined = hex::encode(digest.bytes);
+    }
+
+    /// Compare against an `expected` manifest, returning the names of every
+    /// category (plus `"combined"` and `"hash_algorithm"`) that diverged.
+    pub fn diff(&self, expected: &StateManifest) -> Vec<String> {
+        let mut diverged = Vec::new();
+
+        if self.hash_algorithm != expected.hash_algorithm {
+            diverged.push("hash_algorithm".to_string());
+        }
+
+        if self.combined != expected.combined {
+            diverged.push("combined".to_string());
+        }
+
+        let mut categories: Vec<&String> = self
+            .categories
+            .keys()
+            .chain(expected.categories.keys())
+            .collect();
+        categories.sort();
+        categories.dedup();
+
+        for category in categories {
+            if self.categories.get(category) != expected.categories.get(category) {
+                diverged.push(category.clone());
+            }
+        }
+
+        diverged
+    }
+}
+
+/// A per-address disagreement between the stored `balances` map and the sum
+/// of `txout.value` over that address's UTXOs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceMismatch {
+    pub address: String,
+    pub stored: u128,
+    pub computed: u128,
+}
+
+/// Re-derive each address's balance straight from the authoritative UTXO
+/// set -- summing `txout.value` over the UTXOs `address_utxos` attributes to
+/// that address -- and compare it against the precomputed `balances` map,
+/// returning every address whose sums disagree. An address missing from
+/// either side is treated as having a balance of 0 there.
+///
+/// This gives an operator a concrete integrity check they can run offline
+/// against a downloaded state file to catch corruption or logic bugs in the
+/// canister's balance-tracking code, independent of re-running the
+/// canister's own logic.
+pub fn verify_balances(data: &CanisterData) -> Vec<BalanceMismatch> {
+    let value_by_outpoint: BTreeMap<&OutPoint, u64> = data
+        .utxos
+        .iter()
+        .map(|utxo| (&utxo.outpoint, utxo.txout.value))
+        .collect();
+
+    let mut computed: BTreeMap<String, u128> = BTreeMap::new();
+    for address_utxo in &data.address_utxos {
+        if let Some(value) = value_by_outpoint.get(&address_utxo.outpoint) {
+            *computed
+                .entry(address_utxo.address.to_string())
+                .or_insert(0) += *value as u128;
+        }
+    }
+
+    let mut stored: BTreeMap<String, u128> = BTreeMap::new();
+    for (address, balance) in &data.balances {
+        stored.insert(address.to_string(), *balance);
+    }
+
+    let mut addresses: Vec<&String> = computed.keys().chain(stored.keys()).collect();
+    addresses.sort();
+    addresses.dedup();
+
+    addresses
+        .into_iter()
+        .filter_map(|address| {
+            let computed_amount = computed.get(address).copied().unwrap_or(0);
+            let stored_amount = stored.get(address).copied().unwrap_or(0);
+            if computed_amount == stored_amount {
+                return None;
+            }
+            Some(BalanceMismatch {
+                address: address.clone(),
+                stored: stored_amount,
+                computed: computed_amount,
+            })
+        })
+        .collect()
+}
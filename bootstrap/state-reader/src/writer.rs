@@ -0,0 +1,215 @@
+//! Inverse of [`UtxoReader`](crate::UtxoReader): builds a stable-memory file
+//! with the same `memory_ids` layout, instead of reading one. Useful for
+//! constructing test fixtures, migrating/compacting a bloated state file
+//! (read it with `UtxoReader`, write it back with `StateWriter`), and
+//! reconstructing a state file from a verified [`CanisterData`] export.
+
+use crate::memory_ids;
+use crate::{CanisterData, Utxo};
+use ic_doge_canister::state::{UTXO_KEY_SIZE, UTXO_VALUE_MAX_SIZE_MEDIUM, UTXO_VALUE_MAX_SIZE_SMALL};
+use ic_doge_canister::types::{Address, AddressUtxo, BlockHeaderBlob, Storable};
+use ic_doge_interface::Height;
+use ic_doge_types::{BlockHash, OutPoint};
+use ic_stable_structures::{
+    memory_manager::MemoryManager, storable::Blob, FileMemory, StableBTreeMap,
+    Storable as StableStorable,
+};
+use std::borrow::Cow;
+use std::{fs::File, path::Path};
+
+/// A UTXO whose serialized `(txout, height)` value is too large to fit
+/// [`UTXO_VALUE_MAX_SIZE_MEDIUM`] -- the canister would instead place it in
+/// the heap-resident large-UTXO map, a region [`StateWriter`] can't write
+/// since building it requires `ic_doge_canister`'s own state serialization
+/// (`pre_upgrade`), which this crate doesn't own. Returned instead of being
+/// silently dropped or corrupting the medium region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedUtxo {
+    pub outpoint: OutPoint,
+    pub encoded_value_size: usize,
+}
+
+/// Writes `CanisterData` into a `FileMemory`-backed `MemoryManager`, mirroring
+/// [`UtxoReader`](crate::UtxoReader)'s `memory_ids` layout.
+pub struct StateWriter {
+    memory_manager: MemoryManager<FileMemory>,
+}
+
+impl StateWriter {
+    /// Creates (or truncates and reopens) a stable-memory file at `path` to
+    /// write into.
+    pub fn new<P: AsRef<Path>>(canister_state_path: P) -> Result<Self, std::io::Error> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(canister_state_path)?;
+        let memory = FileMemory::new(file);
+        let memory_manager = MemoryManager::init(memory);
+        Ok(Self { memory_manager })
+    }
+
+    /// Writes every collection in `data` into its matching memory region.
+    ///
+    /// `data.utxos` is routed into the small or medium region by its
+    /// serialized `(txout, height)` size, exactly as the canister does; a
+    /// UTXO too large for either is returned as an [`OversizedUtxo`] rather
+    /// than being written somewhere it can't later be read back from (see
+    /// [`OversizedUtxo`]'s doc comment for why large UTXOs specifically
+    /// aren't supported).
+    pub fn write_data(&self, data: &CanisterData) -> Result<(), Vec<OversizedUtxo>> {
+        self.write_utxos(&data.utxos)?;
+        self.write_address_utxos(&data.address_utxos);
+        self.write_balances(&data.balances);
+        self.write_block_headers(&data.block_headers);
+        self.write_block_heights(&data.block_heights);
+        Ok(())
+    }
+
+    /// Writes `utxos` into the small or medium `StableBTreeMap`, splitting on
+    /// encoded `(txout, height)` size exactly as the canister does. Returns
+    /// every UTXO that didn't fit either region instead of dropping it; on
+    /// success, every entry of `utxos` was written.
+    pub fn write_utxos(&self, utxos: &[Utxo]) -> Result<(), Vec<OversizedUtxo>> {
+        let small_memory = self.memory_manager.get(memory_ids::SMALL_UTXOS);
+        let medium_memory = self.memory_manager.get(memory_ids::MEDIUM_UTXOS);
+        let mut small_map: StableBTreeMap<Blob<UTXO_KEY_SIZE>, Blob<UTXO_VALUE_MAX_SIZE_SMALL>, _> =
+            StableBTreeMap::init(small_memory);
+        let mut medium_map: StableBTreeMap<Blob<UTXO_KEY_SIZE>, Blob<UTXO_VALUE_MAX_SIZE_MEDIUM>, _> =
+            StableBTreeMap::init(medium_memory);
+
+        let mut oversized = Vec::new();
+
+        for utxo in utxos {
+            let key_bytes = StableStorable::to_bytes(&utxo.outpoint).into_owned();
+            let key: Blob<UTXO_KEY_SIZE> = StableStorable::from_bytes(Cow::Owned(key_bytes));
+            let value_bytes = StableStorable::to_bytes(&(utxo.txout.clone(), utxo.height)).into_owned();
+
+            if value_bytes.len() <= UTXO_VALUE_MAX_SIZE_SMALL as usize {
+                let value: Blob<UTXO_VALUE_MAX_SIZE_SMALL> =
+                    StableStorable::from_bytes(Cow::Owned(value_bytes));
+                small_map.insert(key, value);
+            } else if value_bytes.len() <= UTXO_VALUE_MAX_SIZE_MEDIUM as usize {
+                let value: Blob<UTXO_VALUE_MAX_SIZE_MEDIUM> =
+                    StableStorable::from_bytes(Cow::Owned(value_bytes));
+                medium_map.insert(key, value);
+            } else {
+                oversized.push(OversizedUtxo {
+                    outpoint: utxo.outpoint.clone(),
+                    encoded_value_size: value_bytes.len(),
+                });
+            }
+        }
+
+        if oversized.is_empty() {
+            Ok(())
+        } else {
+            Err(oversized)
+        }
+    }
+
+    /// Writes the address-to-UTXO index.
+    pub fn write_address_utxos(&self, address_utxos: &[AddressUtxo]) {
+        let memory = self.memory_manager.get(memory_ids::ADDRESS_UTXOS);
+        let mut map: StableBTreeMap<Blob<{ AddressUtxo::BOUND.max_size() as usize }>, (), _> =
+            StableBTreeMap::init(memory);
+
+        for address_utxo in address_utxos {
+            let key_bytes = StableStorable::to_bytes(address_utxo).into_owned();
+            let key: Blob<{ AddressUtxo::BOUND.max_size() as usize }> =
+                StableStorable::from_bytes(Cow::Owned(key_bytes));
+            map.insert(key, ());
+        }
+    }
+
+    /// Writes the address-to-balance map.
+    pub fn write_balances(&self, balances: &[(Address, u128)]) {
+        let memory = self.memory_manager.get(memory_ids::BALANCES);
+        let mut map: StableBTreeMap<Address, u128, _> = StableBTreeMap::init(memory);
+        for (address, balance) in balances {
+            map.insert(address.clone(), *balance);
+        }
+    }
+
+    /// Writes the block-hash-to-header map.
+    pub fn write_block_headers(&self, block_headers: &[(BlockHash, BlockHeaderBlob)]) {
+        let memory = self.memory_manager.get(memory_ids::BLOCK_HEADERS);
+        let mut map: StableBTreeMap<BlockHash, BlockHeaderBlob, _> = StableBTreeMap::init(memory);
+        for (block_hash, header_blob) in block_headers {
+            map.insert(block_hash.clone(), header_blob.clone());
+        }
+    }
+
+    /// Writes the height-to-block-hash map.
+    pub fn write_block_heights(&self, block_heights: &[(Height, BlockHash)]) {
+        let memory = self.memory_manager.get(memory_ids::BLOCK_HEIGHTS);
+        let mut map: StableBTreeMap<Height, BlockHash, _> = StableBTreeMap::init(memory);
+        for (height, block_hash) in block_heights {
+            map.insert(*height, block_hash.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::utxo_set_commitment;
+    use crate::UtxoReader;
+    use ic_doge_canister::types::TxOut;
+    use ic_doge_types::Txid;
+
+    fn sample_utxo(vout: u32, height: Height, script_len: usize) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::from(vec![vout as u8 + 1; 32]), vout),
+            txout: TxOut {
+                value: 100_000 + vout as u64,
+                script_pubkey: vec![0xab; script_len],
+            },
+            height,
+        }
+    }
+
+    #[test]
+    fn write_then_read_round_trips_utxo_set_commitment() {
+        let path = std::env::temp_dir().join(format!(
+            "state-writer-round-trip-test-{}.bin",
+            std::process::id()
+        ));
+        let utxos = vec![
+            sample_utxo(0, 1, 25),
+            sample_utxo(1, 2, 25),
+            sample_utxo(2, 3, 200),
+        ];
+        let data = CanisterData {
+            address_utxos: Vec::new(),
+            utxos: utxos.clone(),
+            balances: Vec::new(),
+            block_headers: Vec::new(),
+            block_heights: Vec::new(),
+        };
+
+        let writer = StateWriter::new(&path).unwrap();
+        writer.write_data(&data).unwrap();
+
+        let reader = UtxoReader::new(&path).unwrap();
+        let read_utxos = reader.read_utxos();
+        drop(reader);
+        let _ = std::fs::remove_file(&path);
+
+        let mut expected = utxos;
+        expected.sort();
+        let mut actual = read_utxos.clone();
+        actual.sort();
+        assert_eq!(expected, actual);
+
+        let read_data = CanisterData {
+            address_utxos: Vec::new(),
+            utxos: read_utxos,
+            balances: Vec::new(),
+            block_headers: Vec::new(),
+            block_heights: Vec::new(),
+        };
+        assert_eq!(utxo_set_commitment(&data), utxo_set_commitment(&read_data));
+    }
+}
@@ -1,4 +1,4 @@
-use bitcoin::{PubkeyHash, ScriptHash};
+use bitcoin::{PubkeyHash, ScriptHash, WitnessProgram, WitnessVersion};
 use crate::chainstate::{deserialize_db_utxo_legacy, deserialize_db_utxo_modern, DBUtxoValue};
 use bitcoin::{Address as BtcAddress, dogecoin::Address as DogeAddress, Network as BtcNetwork, dogecoin::Network as DogeNetwork};
 
@@ -48,6 +48,20 @@ impl Blockchain {
         }
     }
 
+    /// Renders the bech32 (v0) / bech32m (v1) address for a SegWit/Taproot
+    /// witness program. Dogecoin has no native SegWit address format, so
+    /// this is only meaningful for Bitcoin; Dogecoin chainstates are not
+    /// expected to contain these programs in the first place.
+    pub(crate) fn witness_address(&self, version: WitnessVersion, program: &[u8]) -> String {
+        match self {
+            Blockchain::Bitcoin(network) => match WitnessProgram::new(version, program) {
+                Ok(program) => BtcAddress::from_witness_program(program, *network).to_string(),
+                Err(_) => String::new(),
+            },
+            Blockchain::Dogecoin(_) => String::new(),
+        }
+    }
+
     pub(crate) fn deserialize_db_utxo(&self, value: Vec<u8>) -> anyhow::Result<Vec<DBUtxoValue>> {
         match self {
             Blockchain::Bitcoin(_) => {
@@ -1,5 +1,5 @@
 use std::io::{Cursor, Read};
-use bitcoin::{PubkeyHash, PublicKey, ScriptBuf, ScriptHash};
+use bitcoin::{PubkeyHash, PublicKey, ScriptBuf, ScriptHash, WitnessVersion};
 use bitcoin::hashes::Hash;
 use crate::blockchain::Blockchain;
 use secp256k1::{PublicKey as Secp256k1Pk};
@@ -104,7 +104,7 @@ fn deserialize_txout<R: Read>(reader: &mut R, blockchain: &Blockchain) -> anyhow
     })
 }
 
-fn deserialize_script<R: Read>(reader: &mut R, blockchain: &Blockchain) -> anyhow::Result<(ScriptBuf, String, usize, String)> {
+pub(crate) fn deserialize_script<R: Read>(reader: &mut R, blockchain: &Blockchain) -> anyhow::Result<(ScriptBuf, String, usize, String)> {
     // nsize: byte to indicate the type or size of script
     // nsize  -     compressed script (in DB)    - script
     //   0    -            hash160 PK            - P2PKH
@@ -191,7 +191,10 @@ fn deserialize_script<R: Read>(reader: &mut R, blockchain: &Blockchain) -> anyho
         let script_size = nsize - 6;
         let mut script_bytes = vec![0u8; script_size];
         reader.read_exact(&mut script_bytes)?;
-        if script_size >= 36 && script_bytes.last() == Some(&174) { // 174 = 0xae = OP_CHECKMULTISIG
+        if let Some((witness_type, version, program)) = classify_witness_program(&script_bytes) {
+            address = blockchain.witness_address(version, program);
+            script_type = witness_type.to_string();
+        } else if script_size >= 36 && script_bytes.last() == Some(&174) { // 174 = 0xae = OP_CHECKMULTISIG
             script_type = "p2ms".to_string();
         } else {
             script_type = "non-standard".to_string();
@@ -202,6 +205,25 @@ fn deserialize_script<R: Read>(reader: &mut R, blockchain: &Blockchain) -> anyho
     Ok((script, script_type, nsize, address))
 }
 
+/// Recognizes a P2WPKH (`OP_0 <20-byte>`), P2WSH (`OP_0 <32-byte>`), or
+/// P2TR (`OP_1 <32-byte>`) witness program among the otherwise-uncompressed
+/// scripts (`nsize >= 6`), returning its type name, witness version, and
+/// the program bytes.
+fn classify_witness_program(script_bytes: &[u8]) -> Option<(&'static str, WitnessVersion, &[u8])> {
+    match script_bytes {
+        [0x00, 0x14, program @ ..] if program.len() == 20 => {
+            Some(("p2wpkh", WitnessVersion::V0, program))
+        }
+        [0x00, 0x20, program @ ..] if program.len() == 32 => {
+            Some(("p2wsh", WitnessVersion::V0, program))
+        }
+        [0x51, 0x20, program @ ..] if program.len() == 32 => {
+            Some(("p2tr", WitnessVersion::V1, program))
+        }
+        _ => None,
+    }
+}
+
 pub(crate) fn deserialize_db_utxo_legacy(blockchain: &Blockchain, value: Vec<u8>) -> anyhow::Result<Vec<DBUtxoValue>> {
     let mut cursor = Cursor::new(value);
 
@@ -356,4 +378,34 @@ mod tests {
         assert_eq!(unspent_outputs[2], true);  // vout[2 + 2]
         assert_eq!(unspent_outputs[16], true); // vout[16 + 2]
     }
+
+    #[test]
+    fn test_classify_witness_program() {
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&[0xAB; 20]);
+        let (script_type, version, program) = classify_witness_program(&p2wpkh).unwrap();
+        assert_eq!(script_type, "p2wpkh");
+        assert_eq!(version, WitnessVersion::V0);
+        assert_eq!(program, &[0xAB; 20]);
+
+        let mut p2wsh = vec![0x00, 0x20];
+        p2wsh.extend_from_slice(&[0xCD; 32]);
+        let (script_type, version, program) = classify_witness_program(&p2wsh).unwrap();
+        assert_eq!(script_type, "p2wsh");
+        assert_eq!(version, WitnessVersion::V0);
+        assert_eq!(program, &[0xCD; 32]);
+
+        let mut p2tr = vec![0x51, 0x20];
+        p2tr.extend_from_slice(&[0xEF; 32]);
+        let (script_type, version, program) = classify_witness_program(&p2tr).unwrap();
+        assert_eq!(script_type, "p2tr");
+        assert_eq!(version, WitnessVersion::V1);
+        assert_eq!(program, &[0xEF; 32]);
+
+        // A 20-byte P2WPKH-shaped prefix followed by trailing bytes isn't a
+        // valid witness program.
+        let mut not_witness = vec![0x00, 0x14];
+        not_witness.extend_from_slice(&[0xAB; 21]);
+        assert!(classify_witness_program(&not_witness).is_none());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+//! Streaming reader for a Core-style `dumptxoutset` UTXO snapshot.
+//!
+//! Unlike `chainstate.rs`, which decodes chainstate LevelDB records one
+//! key/value pair at a time, a snapshot is a flat stream grouped by txid:
+//!
+//!   txid (32 bytes)
+//!   varint: number of unspent outputs that follow for this transaction
+//!   for each output:
+//!     varint: output index
+//!     varint: code = (height << 1) | coinbase
+//!     varint: compressed amount (see `decompress_amount`)
+//!     script, compressed the same way as a chainstate value (see
+//!     `deserialize_script`)
+//!
+//! This only covers the body of such a snapshot; the leading metadata
+//! (block hash/height, total coin count) is format/version-specific and is
+//! left for the caller to consume before constructing a [`CoinsReader`] over
+//! what remains.
+
+use std::io::Read;
+use bitcoin::{OutPoint, Txid};
+use bitcoin::hashes::Hash;
+use crate::blockchain::Blockchain;
+use crate::chainstate::{deserialize_script, TxOut};
+use crate::serialization::{decompress_amount, read_varint};
+
+/// One unspent output from a snapshot: the output itself, the height of the
+/// transaction that created it, and whether that transaction was a
+/// coinbase.
+#[derive(Debug, Clone)]
+pub(crate) struct Coin {
+    pub height: u32,
+    pub is_coinbase: bool,
+    pub txout: TxOut,
+}
+
+/// Streaming iterator over the body of a UTXO snapshot, yielding one
+/// `(OutPoint, Coin)` per unspent output without materializing the whole
+/// set in memory -- so a canister can bootstrap its UTXO view directly from
+/// a trusted snapshot instead of replaying every block.
+pub(crate) struct CoinsReader<R: Read> {
+    reader: R,
+    blockchain: Blockchain,
+    txid: Option<Txid>,
+    outputs_remaining: u64,
+}
+
+impl<R: Read> CoinsReader<R> {
+    pub(crate) fn new(reader: R, blockchain: Blockchain) -> Self {
+        Self {
+            reader,
+            blockchain,
+            txid: None,
+            outputs_remaining: 0,
+        }
+    }
+
+    fn next_entry(&mut self) -> anyhow::Result<Option<(OutPoint, Coin)>> {
+        while self.outputs_remaining == 0 {
+            let mut txid_bytes = [0u8; 32];
+            match self.reader.read_exact(&mut txid_bytes) {
+                Ok(()) => {}
+                Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error.into()),
+            }
+            self.txid = Some(Txid::from_byte_array(txid_bytes));
+            self.outputs_remaining = read_varint(&mut self.reader)?;
+        }
+
+        let vout = read_varint(&mut self.reader)? as u32;
+        let code = read_varint(&mut self.reader)?;
+        let height = (code >> 1) as u32;
+        let is_coinbase = (code & 1) != 0;
+
+        let compressed_amount = read_varint(&mut self.reader)?;
+        let amount = decompress_amount(compressed_amount)?;
+        let (script, script_type, nsize, address) =
+            deserialize_script(&mut self.reader, &self.blockchain)?;
+
+        let txout = TxOut {
+            amount,
+            script,
+            nsize,
+            script_type,
+            address,
+        };
+        let outpoint = OutPoint {
+            txid: self.txid.expect("txid is always set before outputs_remaining is nonzero"),
+            vout,
+        };
+
+        self.outputs_remaining -= 1;
+
+        Ok(Some((outpoint, Coin { height, is_coinbase, txout })))
+    }
+}
+
+impl<R: Read> Iterator for CoinsReader<R> {
+    type Item = anyhow::Result<(OutPoint, Coin)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_entry().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::Network as BtcNetwork;
+    use std::io::Cursor;
+
+    fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+        // Inverse of `read_varint`'s 7-bits-per-byte, continuation-bit
+        // encoding.
+        let mut tmp = vec![(value & 0x7F) as u8];
+        while value > 0x7F {
+            value = (value >> 7) - 1;
+            tmp.push(0x80 | (value & 0x7F) as u8);
+        }
+        tmp.reverse();
+        bytes.extend_from_slice(&tmp);
+    }
+
+    #[test]
+    fn reads_one_txid_group_with_two_outputs() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0xAB; 32]); // txid
+        write_varint(&mut data, 2); // 2 outputs follow
+
+        // Output 0: vout=0, height=120891, coinbase=true, amount=234925952, P2PKH
+        write_varint(&mut data, 0);
+        write_varint(&mut data, (120891 << 1) | 1);
+        write_varint(&mut data, 2114333561); // compressed 234925952 (varint bytes 86ef97d579)
+        data.push(0); // nsize: P2PKH
+        data.extend_from_slice(&[0x11; 20]);
+
+        // Output 1: vout=4, height=120891, coinbase=true, amount=110397, P2PKH
+        write_varint(&mut data, 4);
+        write_varint(&mut data, (120891 << 1) | 1);
+        write_varint(&mut data, 993571); // compressed 110397 (varint bytes bbd123)
+        data.push(0);
+        data.extend_from_slice(&[0x22; 20]);
+
+        let blockchain = Blockchain::Bitcoin(BtcNetwork::Bitcoin);
+        let mut reader = CoinsReader::new(Cursor::new(data), blockchain);
+
+        let (outpoint, coin) = reader.next().unwrap().unwrap();
+        assert_eq!(outpoint.vout, 0);
+        assert_eq!(coin.height, 120891);
+        assert!(coin.is_coinbase);
+        assert_eq!(coin.txout.amount, 234925952);
+
+        let (outpoint, coin) = reader.next().unwrap().unwrap();
+        assert_eq!(outpoint.vout, 4);
+        assert_eq!(coin.height, 120891);
+        assert_eq!(coin.txout.amount, 110397);
+
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn empty_stream_yields_no_entries() {
+        let blockchain = Blockchain::Bitcoin(BtcNetwork::Bitcoin);
+        let mut reader = CoinsReader::new(Cursor::new(Vec::new()), blockchain);
+        assert!(reader.next().is_none());
+    }
+}
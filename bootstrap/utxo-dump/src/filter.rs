@@ -0,0 +1,226 @@
+//! A BIP158-style Golomb-coded set (GCS) filter over every UTXO
+//! `scriptPubKey` in a dump, so a wallet can test "does this script
+//! appear in the UTXO set?" without loading the full CSV.
+//!
+//! Ref: <https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki>
+
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher as _;
+use std::io::{self, Write};
+
+/// `P` from BIP158: each Golomb-Rice remainder is `P` bits wide.
+const FILTER_P: u32 = 19;
+/// `M` from BIP158: the target false-positive rate is `1/M`.
+const FILTER_M: u64 = 784_931;
+
+/// Hashes `data` with SipHash-2-4 keyed by `key`, as BIP158 requires.
+fn siphash(key: &[u8; 16], data: &[u8]) -> u64 {
+    let k0 = u64::from_le_bytes(key[0..8].try_into().unwrap());
+    let k1 = u64::from_le_bytes(key[8..16].try_into().unwrap());
+    let mut hasher = SipHasher24::new_with_keys(k0, k1);
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Maps a 64-bit hash into `[0, range)` using BIP158's multiply-and-shift
+/// reduction, `(hash * range) >> 64`, computed in 128 bits to avoid
+/// overflow.
+fn map_into_range(hash: u64, range: u64) -> u64 {
+    ((hash as u128 * range as u128) >> 64) as u64
+}
+
+/// Writes a Bitcoin/BIP158 CompactSize varint.
+fn write_compact_size<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    if value < 0xFD {
+        writer.write_all(&[value as u8])
+    } else if value <= 0xFFFF {
+        writer.write_all(&[0xFD])?;
+        writer.write_all(&(value as u16).to_le_bytes())
+    } else if value <= 0xFFFF_FFFF {
+        writer.write_all(&[0xFE])?;
+        writer.write_all(&(value as u32).to_le_bytes())
+    } else {
+        writer.write_all(&[0xFF])?;
+        writer.write_all(&value.to_le_bytes())
+    }
+}
+
+/// MSB-first bit writer backing the Golomb-Rice coding below: bits
+/// accumulate into a byte and are flushed to `inner` once full.
+struct BitWriter<W> {
+    inner: W,
+    current: u8,
+    filled: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            current: 0,
+            filled: 0,
+        }
+    }
+
+    fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.current = (self.current << 1) | (bit as u8);
+        self.filled += 1;
+        if self.filled == 8 {
+            self.inner.write_all(&[self.current])?;
+            self.current = 0;
+            self.filled = 0;
+        }
+        Ok(())
+    }
+
+    /// Golomb-Rice encodes `value` with parameter `p`: the quotient
+    /// `value >> p` as that many `1` bits followed by a `0`, then the low
+    /// `p` bits of the remainder, MSB-first.
+    fn write_golomb_rice(&mut self, value: u64, p: u32) -> io::Result<()> {
+        let quotient = value >> p;
+        for _ in 0..quotient {
+            self.write_bit(true)?;
+        }
+        self.write_bit(false)?;
+        for i in (0..p).rev() {
+            self.write_bit((value >> i) & 1 == 1)?;
+        }
+        Ok(())
+    }
+
+    /// Pads any partially-filled final byte with `0` bits and flushes it.
+    fn finish(mut self) -> io::Result<()> {
+        if self.filled > 0 {
+            self.current <<= 8 - self.filled;
+            self.inner.write_all(&[self.current])?;
+        }
+        Ok(())
+    }
+}
+
+/// Accumulates scriptPubKey hashes and, once every UTXO has been seen,
+/// encodes them into a single BIP158-style Golomb-coded set.
+pub(crate) struct ScriptFilterBuilder {
+    sip_key: [u8; 16],
+    hashes: Vec<u64>,
+}
+
+impl ScriptFilterBuilder {
+    /// `sip_key` should be derived from the chainstate's own obfuscation
+    /// key, so two dumps of the same chainstate always produce
+    /// byte-identical filters without needing a separately-managed key.
+    pub(crate) fn new(sip_key: [u8; 16]) -> Self {
+        Self {
+            sip_key,
+            hashes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add_script(&mut self, script: &[u8]) {
+        self.hashes.push(siphash(&self.sip_key, script));
+    }
+
+    /// Sorts, delta-encodes, and Golomb-Rice encodes the accumulated
+    /// hashes, writing the resulting filter -- a CompactSize element
+    /// count followed by the Golomb-Rice bitstream -- to `writer`.
+    pub(crate) fn finish<W: Write>(mut self, writer: &mut W) -> io::Result<()> {
+        let n = self.hashes.len() as u64;
+        let range = n.saturating_mul(FILTER_M);
+
+        let mut mapped: Vec<u64> = self
+            .hashes
+            .drain(..)
+            .map(|hash| map_into_range(hash, range))
+            .collect();
+        mapped.sort_unstable();
+
+        write_compact_size(writer, n)?;
+
+        let mut bits = BitWriter::new(writer);
+        let mut previous = 0u64;
+        for value in mapped {
+            bits.write_golomb_rice(value - previous, FILTER_P)?;
+            previous = value;
+        }
+        bits.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_into_range_is_bounded() {
+        let range = 1000;
+        for hash in [0u64, 1, u64::MAX / 2, u64::MAX] {
+            assert!(map_into_range(hash, range) < range);
+        }
+    }
+
+    #[test]
+    fn test_compact_size_boundaries() {
+        let mut buf = Vec::new();
+        write_compact_size(&mut buf, 0xFC).unwrap();
+        assert_eq!(buf, vec![0xFC]);
+
+        let mut buf = Vec::new();
+        write_compact_size(&mut buf, 0xFFFF).unwrap();
+        assert_eq!(buf, vec![0xFD, 0xFF, 0xFF]);
+
+        let mut buf = Vec::new();
+        write_compact_size(&mut buf, 0x1_0000).unwrap();
+        assert_eq!(buf, vec![0xFE, 0x00, 0x00, 0x01, 0x00]);
+    }
+
+    /// Encodes a handful of deltas, then manually decodes them back using
+    /// the same MSB-first bit convention, pinning down the bitstream
+    /// layout that a verifier would also have to implement.
+    #[test]
+    fn test_golomb_rice_roundtrip() {
+        let deltas = [0u64, 1, 5, 1 << FILTER_P, (1 << FILTER_P) + 3];
+
+        let mut buf = Vec::new();
+        {
+            let mut bits = BitWriter::new(&mut buf);
+            for &delta in &deltas {
+                bits.write_golomb_rice(delta, FILTER_P).unwrap();
+            }
+            bits.finish().unwrap();
+        }
+
+        let mut bit_pos = 0usize;
+        let mut read_bit = |buf: &[u8]| -> bool {
+            let byte = buf[bit_pos / 8];
+            let bit = (byte >> (7 - (bit_pos % 8))) & 1 == 1;
+            bit_pos += 1;
+            bit
+        };
+        let mut decoded = Vec::new();
+        for _ in 0..deltas.len() {
+            let mut quotient = 0u64;
+            while read_bit(&buf) {
+                quotient += 1;
+            }
+            let mut remainder = 0u64;
+            for _ in 0..FILTER_P {
+                remainder = (remainder << 1) | read_bit(&buf) as u64;
+            }
+            decoded.push((quotient << FILTER_P) | remainder);
+        }
+        assert_eq!(decoded, deltas);
+    }
+
+    #[test]
+    fn test_filter_element_count_prefix() {
+        let mut builder = ScriptFilterBuilder::new([0u8; 16]);
+        builder.add_script(b"script one");
+        builder.add_script(b"script two");
+
+        let mut buf = Vec::new();
+        builder.finish(&mut buf).unwrap();
+
+        // Two elements, so the CompactSize prefix is a single byte `0x02`.
+        assert_eq!(buf[0], 0x02);
+    }
+}
@@ -1,25 +1,32 @@
+mod aggregate;
 mod blockchain;
 mod chainstate;
+mod coins;
+mod filter;
 mod serialization;
 #[cfg(target_os = "macos")]
 mod utils;
 
 use bitcoin::{dogecoin::Network as DogeNetwork, Network as BtcNetwork};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
 use std::io::{BufWriter, Cursor, Write};
 
 use blockchain::Blockchain;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use crate::aggregate::AddressAggregator;
+use crate::filter::ScriptFilterBuilder;
 use crate::serialization::read_varint;
 #[cfg(target_os = "macos")]
 use crate::utils::set_macos_rlimit;
 use anyhow::{Context, Result};
 use clap::{Parser, ValueEnum};
+use csv::WriterBuilder;
 use rusty_leveldb::{LdbIterator, Options, DB};
+use serde::{Deserialize, Serialize};
 use signal_hook::{consts::TERM_SIGNALS, iterator::Signals};
 
 const VERSION: &str = "1.0.0";
@@ -36,6 +43,11 @@ enum BlockchainKind {
     Dogecoin,
 }
 
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum AggregateMode {
+    Address,
+}
+
 #[derive(Parser)]
 #[command(name = "utxo-dump")]
 #[command(about = "Dumps UTXO set from chainstate LevelDB to CSV")]
@@ -72,6 +84,63 @@ struct Args {
     /// Do not display any progress or results
     #[arg(short = 'q', long = "quiet")]
     quiet: bool,
+
+    /// Write a BIP158-style Golomb-coded set filter of every UTXO
+    /// scriptPubKey to this path, in addition to the CSV dump
+    #[arg(long = "filter", value_hint = clap::ValueHint::FilePath)]
+    filter_file: Option<PathBuf>,
+
+    /// Resume a previous dump from the checkpoint at `--state`, appending
+    /// to the existing output file instead of starting from scratch
+    #[arg(long = "resume")]
+    resume: bool,
+
+    /// Location of the checkpoint file periodically written during the
+    /// dump, used to resume after an interruption with `--resume`
+    #[arg(long = "state", default_value = "chainstate_utxos.state")]
+    state_file: PathBuf,
+
+    /// Additionally aggregate UTXOs by address (balance, UTXO count,
+    /// min/max height), sorted by balance descending
+    #[arg(long = "aggregate")]
+    aggregate: Option<AggregateMode>,
+
+    /// Name of file to write the `--aggregate` output to
+    #[arg(long = "aggregate-output", default_value = "chainstate_addresses.csv")]
+    aggregate_output_file: String,
+}
+
+/// Progress checkpoint, periodically written to `--state` so a dump can be
+/// continued with `--resume` instead of restarting from scratch on a
+/// multi-hour scan.
+#[derive(Debug, Serialize, Deserialize)]
+struct Checkpoint {
+    /// The last LevelDB key fully processed; resuming seeks the iterator
+    /// past this key.
+    last_key: Vec<u8>,
+    utxo_count: u64,
+    total_amount: u64,
+    script_type_count: BTreeMap<String, u32>,
+}
+
+impl Checkpoint {
+    fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("Couldn't create checkpoint file {}", path.display()))?;
+        serde_json::to_writer(file, self)
+            .with_context(|| format!("Couldn't write checkpoint to {}", path.display()))
+    }
+
+    fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(path)
+            .with_context(|| format!("Couldn't open checkpoint file {}", path.display()))?;
+        let checkpoint = serde_json::from_reader(file)
+            .with_context(|| format!("Couldn't parse checkpoint file {}", path.display()))?;
+        Ok(Some(checkpoint))
+    }
 }
 
 impl Args {
@@ -95,6 +164,49 @@ impl Args {
     }
 }
 
+/// One row of the UTXO dump, typed so the `csv` crate can quote and
+/// escape values that contain commas, quotes, or newlines -- something
+/// the previous hand-rolled `split(',')`/`join(",")` row building
+/// silently got wrong.
+///
+/// `--fields` doesn't change which of these get computed (all of them
+/// that are selected still do); it only selects, and orders, which
+/// columns of the record [`UtxoRecord::field`] emits.
+#[derive(Debug, Clone, Default, Serialize)]
+struct UtxoRecord {
+    count: u64,
+    txid: String,
+    vout: u32,
+    height: u32,
+    coinbase: u8,
+    amount: u64,
+    nsize: usize,
+    #[serde(rename = "type")]
+    script_type: String,
+    address: String,
+    script: String,
+}
+
+impl UtxoRecord {
+    /// Returns this record's value for `field`, one of [`FIELDS_ALLOWED`],
+    /// rendered the way it belongs in a CSV cell.
+    fn field(&self, field: &str) -> String {
+        match field {
+            "count" => self.count.to_string(),
+            "txid" => self.txid.clone(),
+            "vout" => self.vout.to_string(),
+            "height" => self.height.to_string(),
+            "coinbase" => self.coinbase.to_string(),
+            "amount" => self.amount.to_string(),
+            "nsize" => self.nsize.to_string(),
+            "script" => self.script.clone(),
+            "type" => self.script_type.clone(),
+            "address" => self.address.clone(),
+            _ => unreachable!("{field:?} is not in FIELDS_ALLOWED"),
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
@@ -108,6 +220,7 @@ fn main() -> Result<()> {
     let blockchain = args.to_blockchain();
 
     let fields_selected = validate_and_parse_fields(&args.fields)?;
+    let selected_fields: Vec<&str> = args.fields.split(',').map(str::trim).collect();
 
     // Helper closure to check if a field is selected
     let is_selected = |field: &str| *fields_selected.get(field).unwrap_or(&false);
@@ -115,18 +228,35 @@ fn main() -> Result<()> {
     let options = Options::default();
     let mut database = DB::open(&args.chainstate, options).context("Couldn't open LevelDB")?;
 
-    let output_file = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(&args.output_file)
-        .with_context(|| {
-            format!(
-                "Output file {} already exists or cannot be created",
-                args.output_file
-            )
-        })?;
+    let output_file = if args.resume {
+        OpenOptions::new()
+            .append(true)
+            .open(&args.output_file)
+            .with_context(|| {
+                format!(
+                    "Cannot resume: output file {} doesn't exist",
+                    args.output_file
+                )
+            })?
+    } else {
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&args.output_file)
+            .with_context(|| {
+                format!(
+                    "Output file {} already exists or cannot be created",
+                    args.output_file
+                )
+            })?
+    };
 
-    let mut writer = BufWriter::new(output_file);
+    // `has_headers(false)`: we write the header ourselves below, since it's
+    // the user's selected/ordered field subset rather than `UtxoRecord`'s
+    // full field set.
+    let mut writer = WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(output_file);
 
     if !args.quiet {
         println!(
@@ -136,11 +266,13 @@ fn main() -> Result<()> {
         );
     }
 
-    // Write CSV header
-    if !args.quiet {
-        println!("{}", args.fields);
+    // Write CSV header (skipped on resume: it's already in the file we're appending to)
+    if !args.resume {
+        if !args.quiet {
+            println!("{}", args.fields);
+        }
+        writer.write_record(&selected_fields)?;
     }
-    writeln!(writer, "{}", args.fields)?;
 
     // Initialize statistics
     let mut total_amount: u64 = 0;
@@ -150,6 +282,9 @@ fn main() -> Result<()> {
     script_type_count.insert("p2pkh", 0);
     script_type_count.insert("p2sh", 0);
     script_type_count.insert("p2ms", 0);
+    script_type_count.insert("p2wpkh", 0);
+    script_type_count.insert("p2wsh", 0);
+    script_type_count.insert("p2tr", 0);
     script_type_count.insert("non-standard", 0);
 
     // Setup signal handling for graceful shutdown
@@ -184,15 +319,77 @@ fn main() -> Result<()> {
         );
     };
 
+    // Derive the filter's SipHash key from the chainstate's own
+    // obfuscation key (cycling it the same way the XOR deobfuscation
+    // below does, since the obfuscation key itself is shorter than 16
+    // bytes), so a filter dumped from a given chainstate is always
+    // byte-identical without needing a separately-managed key.
+    let mut filter_builder = args.filter_file.as_ref().map(|_| {
+        let mut sip_key = [0u8; 16];
+        for (i, byte) in sip_key.iter_mut().enumerate() {
+            *byte = obfuscate_key[i % obfuscate_key.len()];
+        }
+        ScriptFilterBuilder::new(sip_key)
+    });
+
+    let mut address_aggregator = match args.aggregate {
+        Some(AggregateMode::Address) => Some(AddressAggregator::new(PathBuf::from(format!(
+            "{}.runs",
+            args.aggregate_output_file
+        )))?),
+        None => None,
+    };
+
+    // The last LevelDB key fully processed, persisted to `--state` so
+    // `--resume` can seek the iterator past it rather than restarting.
+    let mut last_key: Vec<u8> = Vec::new();
+
+    if args.resume {
+        if let Some(checkpoint) = Checkpoint::read(&args.state_file)? {
+            if !args.quiet {
+                println!(
+                    "Resuming from checkpoint at {} ({} utxos already processed)",
+                    args.state_file.display(),
+                    checkpoint.utxo_count
+                );
+            }
+            // `seek` leaves the iterator on the checkpointed key itself; the
+            // loop's own `next()` below moves past it, so that already fully
+            // processed key isn't read again.
+            db_iter.seek(&checkpoint.last_key);
+            last_key = checkpoint.last_key;
+            utxo_count = checkpoint.utxo_count;
+            total_amount = checkpoint.total_amount;
+            for (script_type, count) in &checkpoint.script_type_count {
+                if let Some(slot) = script_type_count.get_mut(script_type.as_str()) {
+                    *slot = *count;
+                }
+            }
+        }
+    }
+
+    // Snapshots the current progress into a `Checkpoint` for `--state`.
+    let checkpoint = |last_key: &[u8], utxo_count: u64, total_amount: u64, script_type_count: &BTreeMap<&str, u32>| Checkpoint {
+        last_key: last_key.to_vec(),
+        utxo_count,
+        total_amount,
+        script_type_count: script_type_count
+            .iter()
+            .map(|(&k, &v)| (k.to_string(), v))
+            .collect(),
+    };
+
     while db_iter.valid() {
         if !running.load(Ordering::SeqCst) {
             if !args.quiet {
                 println!("Interrupt signal caught. Shutting down gracefully.");
             }
+            checkpoint(&last_key, utxo_count, total_amount, &script_type_count).write(&args.state_file)?;
             break;
         }
 
         if let Some((key, mut value)) = db_iter.next() {
+            last_key = key.clone();
             let prefix = key[0];
             if prefix == blockchain.utxo_key_prefix() {
                 // -----------------------
@@ -221,22 +418,30 @@ fn main() -> Result<()> {
                 }
                 let deobfuscated_value = value;
 
-                let mut csv_output = HashMap::new();
-
                 // Deserialize UTXO value
                 let outputs = blockchain.deserialize_db_utxo(deobfuscated_value)?;
 
+                let mut checkpoint_due = false;
                 for output in outputs {
+                    if let Some(filter_builder) = filter_builder.as_mut() {
+                        filter_builder.add_script(output.txout.script.as_bytes());
+                    }
+
+                    let mut record = UtxoRecord {
+                        count: utxo_count,
+                        ..UtxoRecord::default()
+                    };
+
                     // txid
                     if is_selected("txid") {
                         let mut txid = key[1..33].to_vec();
                         txid.reverse(); // Reverse byte order (little-endian to big-endian)
-                        csv_output.insert("txid", hex::encode(txid));
+                        record.txid = hex::encode(txid);
                     }
 
                     // vout
                     if is_selected("vout") {
-                        if key.len() >= 34 {
+                        record.vout = if key.len() >= 34 {
                             // Modern: vout is encoded in the key
                             anyhow::ensure!(
                                 matches!(blockchain, Blockchain::Bitcoin(_)),
@@ -244,73 +449,80 @@ fn main() -> Result<()> {
                             );
                             let vout_bytes = &key[33..];
                             let mut cursor = Cursor::new(vout_bytes);
-                            let vout = read_varint(&mut cursor)?;
-                            csv_output.insert("vout", vout.to_string());
+                            read_varint(&mut cursor)? as u32
                         } else if key.len() == 33 {
                             // Legacy: vout is encoded in the value
                             anyhow::ensure!(
                                 matches!(blockchain, Blockchain::Dogecoin(_)),
                                 "Expected Dogecoin blockchain for legacy vout encoding"
                             );
-                            let vout = output
+                            output
                                 .vout
-                                .ok_or_else(|| anyhow::anyhow!("vout is missing in the output"))?;
-                            csv_output.insert("vout", vout.to_string());
+                                .ok_or_else(|| anyhow::anyhow!("vout is missing in the output"))?
                         } else {
                             anyhow::bail!("Invalid key length: {}", key.len());
-                        }
+                        };
                     }
 
                     // coinbase
                     if is_selected("coinbase") {
-                        csv_output.insert("coinbase", output.coinbase.to_string());
+                        record.coinbase = output.coinbase;
                     }
 
                     // height
                     if is_selected("height") {
-                        csv_output.insert("height", output.height.to_string());
+                        record.height = output.height;
                     }
 
                     // amount
                     if is_selected("amount") {
                         let amount = output.txout.amount;
-                        csv_output.insert("amount", amount.to_string());
+                        record.amount = amount;
                         total_amount += amount;
                     }
 
                     // nsize
                     if is_selected("nsize") {
-                        csv_output.insert("nsize", output.txout.nsize.to_string());
+                        record.nsize = output.txout.nsize;
                     }
 
                     // address and script type processing
-                    if is_selected("address") || is_selected("type") {
+                    if is_selected("address") || is_selected("type") || address_aggregator.is_some() {
                         let script_type = output.txout.script_type;
                         if let Some(count) = script_type_count.get_mut(script_type.as_str()) {
                             *count += 1;
                         }
-                        csv_output.insert("address", output.txout.address);
-                        csv_output.insert("type", script_type);
+                        if let Some(aggregator) = address_aggregator.as_mut() {
+                            aggregator.add(&output.txout.address, output.txout.amount, output.height)?;
+                        }
+                        record.address = output.txout.address;
+                        record.script_type = script_type;
                     }
 
                     if is_selected("script") {
-                        csv_output.insert("script", hex::encode(output.txout.script));
+                        record.script = hex::encode(output.txout.script);
                     }
 
-                    // Build CSV output
-                    let mut csvline = Vec::new();
-                    for field in args.fields.split(',') {
-                        let field = field.trim();
-                        csvline.push(csv_output.get(field).unwrap_or(&String::new()).clone());
-                    }
-                    let csvline = csvline.join(",");
-                    writeln!(writer, "{}", csvline)?;
+                    writer.write_record(
+                        selected_fields.iter().map(|field| record.field(field)),
+                    )?;
 
                     utxo_count += 1;
-                    if !args.quiet && utxo_count > 0 && utxo_count % 100000 == 0 {
-                        println!("{} utxos processed", utxo_count);
+                    if utxo_count > 0 && utxo_count % 100000 == 0 {
+                        if !args.quiet {
+                            println!("{} utxos processed", utxo_count);
+                        }
+                        checkpoint_due = true;
                     }
                 }
+
+                // Checkpoint only once every output of this key is written,
+                // so `last_key` really is fully processed.
+                if checkpoint_due {
+                    writer.flush()?;
+                    checkpoint(&last_key, utxo_count, total_amount, &script_type_count)
+                        .write(&args.state_file)?;
+                }
             }
         } else {
             break;
@@ -318,6 +530,53 @@ fn main() -> Result<()> {
     }
     writer.flush()?;
 
+    if let Some(filter_builder) = filter_builder {
+        let filter_path = args
+            .filter_file
+            .as_ref()
+            .expect("filter_file is set whenever filter_builder is Some");
+        let filter_file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(filter_path)
+            .with_context(|| {
+                format!(
+                    "Filter file {} already exists or cannot be created",
+                    filter_path.display()
+                )
+            })?;
+        let mut filter_writer = BufWriter::new(filter_file);
+        filter_builder.finish(&mut filter_writer)?;
+        filter_writer.flush()?;
+
+        if !args.quiet {
+            println!("Wrote UTXO script filter to {}", filter_path.display());
+        }
+    }
+
+    if let Some(aggregator) = address_aggregator {
+        let aggregate_output = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&args.aggregate_output_file)
+            .with_context(|| {
+                format!(
+                    "Aggregate output file {} already exists or cannot be created",
+                    args.aggregate_output_file
+                )
+            })?;
+        let mut aggregate_writer = BufWriter::new(aggregate_output);
+        aggregator.finish(&mut aggregate_writer)?;
+        aggregate_writer.flush()?;
+
+        if !args.quiet {
+            println!(
+                "Wrote per-address balance aggregate to {}",
+                args.aggregate_output_file
+            );
+        }
+    }
+
     println!("\nTotal UTXOs: {}", utxo_count);
 
     if is_selected("amount") {
@@ -366,6 +625,40 @@ mod tests {
     use bitcoin::PubkeyHash;
     use std::str::FromStr;
 
+    #[test]
+    fn test_checkpoint_roundtrips_through_its_state_file() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!(
+            "utxo-dump-test-checkpoint-{:?}",
+            std::thread::current().id()
+        ));
+
+        let mut script_type_count = BTreeMap::new();
+        script_type_count.insert("p2pkh".to_string(), 3);
+
+        let checkpoint = Checkpoint {
+            last_key: vec![0x43, 0x01, 0x02, 0x03],
+            utxo_count: 100_000,
+            total_amount: 42,
+            script_type_count,
+        };
+        checkpoint.write(&path).unwrap();
+
+        let read_back = Checkpoint::read(&path).unwrap().unwrap();
+        assert_eq!(read_back.last_key, checkpoint.last_key);
+        assert_eq!(read_back.utxo_count, checkpoint.utxo_count);
+        assert_eq!(read_back.total_amount, checkpoint.total_amount);
+        assert_eq!(read_back.script_type_count, checkpoint.script_type_count);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_read_of_missing_file_is_none() {
+        let path = Path::new("/nonexistent/utxo-dump-checkpoint.state");
+        assert!(Checkpoint::read(path).unwrap().is_none());
+    }
+
     #[test]
     fn test_deserialize_db_utxo_legacy() {
         /*
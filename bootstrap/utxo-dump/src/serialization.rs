@@ -1,4 +1,26 @@
-use std::io::{Error, ErrorKind, Read};
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Compress an amount value (Bitcoin Core compression), the exact inverse
+/// of [`decompress_amount`].
+pub(crate) fn compress_amount(mut n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+
+    let mut e = 0u64;
+    while n % 10 == 0 && e < 9 {
+        n /= 10;
+        e += 1;
+    }
+
+    if e < 9 {
+        let d = n % 10;
+        n /= 10;
+        1 + (n * 9 + d - 1) * 10 + e
+    } else {
+        1 + (n - 1) * 10 + 9
+    }
+}
 
 /// Decompress amount value (Bitcoin Core compression)
 pub(crate) fn decompress_amount(compressed: u64) -> Result<u64, Error> {
@@ -53,6 +75,26 @@ pub(crate) fn read_varint<R: Read>(reader: &mut R) -> Result<u64, Error> {
     }
 }
 
+/// Write `n` using Bitcoin's custom base-128 varint format, the inverse of
+/// [`read_varint`]: big-endian groups of 7 bits, each byte but the last
+/// tagged with the continuation bit (0x80) and decremented by one to avoid
+/// redundant encodings of the same value.
+/// Ref: <https://github.com/bitcoin/bitcoin/blob/aa87e0b44600a32b32a4b123d4f90d097f1f106f/src/serialize.h#L452>
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut n: u64) -> Result<(), Error> {
+    let mut bytes = vec![(n & 0x7F) as u8];
+
+    while n > 0x7F {
+        n = (n >> 7) - 1;
+        bytes.push(0x80 | (n & 0x7F) as u8);
+    }
+
+    for &byte in bytes.iter().rev() {
+        writer.write_all(&[byte])?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,4 +210,43 @@ mod tests {
         assert_eq!(decompress_amount(987).unwrap(), 109000000);     // x=986, e=6, x=98, d=9, x=10, n=109, result=109*10^6=109000000
         assert_eq!(decompress_amount(456).unwrap(), 5100000);       // x=455, e=5, x=45, d=1, x=5, n=51, result=51*10^5=5100000
     }
+
+    #[test]
+    fn test_compress_amount_bitcoin_examples() {
+        // Inverse of the examples in test_decompress_amount_bitcoin_examples
+        assert_eq!(compress_amount(0), 0x0);
+        assert_eq!(compress_amount(1), 0x1);
+        assert_eq!(compress_amount(1_000_000), 0x7);
+        assert_eq!(compress_amount(100_000_000), 0x9);
+        assert_eq!(compress_amount(50 * 100_000_000), 0x32);
+        assert_eq!(compress_amount(21_000_000 * 100_000_000), 0x1406f40);
+    }
+
+    fn test_write_varint(value: u64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, value).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_write_varint_bitcoin_examples() {
+        // Same examples as test_read_varint_bitcoin_examples, in reverse.
+        assert_eq!(test_write_varint(54321), vec![0x82, 0xA7, 0x31]);
+        assert_eq!(test_write_varint(3000000000), vec![0x8A, 0x95, 0xC0, 0xBB, 0x00]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn compress_amount_round_trips(amount in 0u64..=21_000_000_000_000_000u64) {
+            let compressed = compress_amount(amount);
+            assert_eq!(decompress_amount(compressed).unwrap(), amount);
+        }
+
+        #[test]
+        fn write_varint_round_trips(value: u64) {
+            let bytes = test_write_varint(value);
+            let mut cursor = Cursor::new(bytes);
+            assert_eq!(read_varint(&mut cursor).unwrap(), value);
+        }
+    }
 }
\ No newline at end of file
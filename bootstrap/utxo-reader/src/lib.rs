@@ -13,6 +13,8 @@ use std::{
     path::Path,
 };
 
+pub mod utreexo;
+
 // Matches Dogecoin canister memory constants in `canister/src/memory.rs`
 const SMALL_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(2);
 const MEDIUM_UTXOS_MEMORY_ID: MemoryId = MemoryId::new(3);
@@ -149,6 +151,25 @@ impl UtxoReader {
         
         hex::encode(hasher.finalize())
     }
+
+    /// Builds a [`utreexo::UtreexoForest`] over `utxos`, sorting them into
+    /// the canonical order first so the resulting roots are reproducible
+    /// regardless of extraction order. Unlike [`compute_utxo_set_hash`],
+    /// the forest's roots support generating and verifying per-UTXO
+    /// inclusion proofs (see [`utreexo::UtreexoForest::prove`] and
+    /// [`utreexo::verify`]) instead of only a single opaque digest.
+    ///
+    /// Note: the extracted [`Utxo`] doesn't carry a coinbase flag (the
+    /// canister state this crate reads from doesn't track one per-UTXO
+    /// either), so unlike the on-disk Bitcoin Core chainstate format this
+    /// leaf preimage omits it.
+    ///
+    /// [`compute_utxo_set_hash`]: Self::compute_utxo_set_hash
+    pub fn build_utreexo(utxos: &[Utxo]) -> utreexo::UtreexoForest {
+        let mut sorted = utxos.to_vec();
+        sorted.sort();
+        utreexo::UtreexoForest::build(&sorted)
+    }
 }
 
 #[cfg(test)]
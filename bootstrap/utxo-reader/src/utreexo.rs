@@ -0,0 +1,247 @@
+//! Utreexo-style accumulator commitment over the UTXO set.
+//!
+//! Unlike [`crate::UtxoReader::compute_utxo_set_hash`], which folds every
+//! UTXO into one flat digest, a Utreexo accumulator commits to the set as a
+//! *forest* of perfect binary Merkle trees, one per set bit of the UTXO
+//! count. Leaves are added the way you'd increment a binary counter: a new
+//! leaf starts as its own one-leaf tree, and then merges upward through any
+//! already-present tree of the same height (`parent = H(left || right)`),
+//! carrying the taller result up until no same-height tree remains to merge
+//! with. The forest's roots are therefore exactly the trees whose height
+//! corresponds to a set bit of the leaf count, giving an O(log n) commitment
+//! and O(log n) per-UTXO inclusion proofs instead of one non-provable
+//! digest.
+//!
+//! UTXOs must be added in the same deterministic order used everywhere else
+//! in this crate ([`Utxo`]'s `Ord`: by height, then outpoint) so the forest is
+//! reproducible regardless of extraction order.
+
+use crate::Utxo;
+use ic_stable_structures::Storable;
+use sha2::{Digest, Sha256};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn leaf_hash(utxo: &Utxo) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(Storable::to_bytes(&utxo.outpoint));
+    hasher.update(utxo.txout.value.to_le_bytes());
+    hasher.update(&utxo.txout.script_pubkey);
+    hasher.update(utxo.height.to_le_bytes());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// One perfect binary tree in the forest: `leaves` in left-to-right order
+// (always a power-of-two count) and `start`, the global index of its first
+// leaf among all UTXOs pushed so far, kept so `UtreexoForest::prove` can
+// map a global UTXO index back to the tree and local position it lives at.
+struct Tree {
+    start: usize,
+    leaves: Vec<[u8; 32]>,
+}
+
+impl Tree {
+    fn root(&self) -> [u8; 32] {
+        let mut level = self.leaves.clone();
+        while level.len() > 1 {
+            level = level
+                .chunks_exact(2)
+                .map(|pair| node_hash(&pair[0], &pair[1]))
+                .collect();
+        }
+        level[0]
+    }
+}
+
+/// A sibling hash on the path from a leaf to its tree's root, tagged with
+/// which side it sits on so [`verify`] knows the pairing order to
+/// reconstruct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofStep {
+    Left([u8; 32]),
+    Right([u8; 32]),
+}
+
+/// An inclusion proof that a UTXO is a leaf of one of a [`UtreexoForest`]'s
+/// trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UtreexoProof {
+    /// Index into [`UtreexoForest::roots`] of the tree this leaf belongs to.
+    tree_index: usize,
+    steps: Vec<ProofStep>,
+}
+
+/// A Utreexo forest accumulated over a UTXO set. See the module docs for
+/// how leaves merge into roots.
+#[derive(Default)]
+pub struct UtreexoForest {
+    // Ordered descending by height; no two trees ever share a height.
+    trees: Vec<Tree>,
+    count: usize,
+}
+
+impl UtreexoForest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a forest over `utxos`, which must already be in the
+    /// canonical sorted order ([`Utxo`]'s `Ord`) so the result is
+    /// reproducible regardless of extraction order.
+    pub fn build(utxos: &[Utxo]) -> Self {
+        let mut forest = Self::new();
+        for utxo in utxos {
+            forest.push(utxo);
+        }
+        forest
+    }
+
+    /// Adds one more UTXO as the next leaf, merging it upward through any
+    /// same-height trees already present, like incrementing a binary
+    /// counter by one.
+    pub fn push(&mut self, utxo: &Utxo) {
+        let mut candidate = Tree {
+            start: self.count,
+            leaves: vec![leaf_hash(utxo)],
+        };
+        self.count += 1;
+
+        while let Some(last) = self.trees.last() {
+            if last.leaves.len() != candidate.leaves.len() {
+                break;
+            }
+            let older = self.trees.pop().unwrap();
+            let mut leaves = older.leaves;
+            leaves.extend(candidate.leaves);
+            candidate = Tree {
+                start: older.start,
+                leaves,
+            };
+        }
+        self.trees.push(candidate);
+    }
+
+    /// The forest's roots, one per tree, ordered descending by height —
+    /// exactly the set bits of the leaf count, read most-significant-first.
+    pub fn roots(&self) -> Vec<[u8; 32]> {
+        self.trees.iter().map(Tree::root).collect()
+    }
+
+    /// Builds an inclusion proof for the UTXO that was pushed at `index`
+    /// (0-based, in push order). Returns `None` if `index` is out of range.
+    pub fn prove(&self, index: usize) -> Option<UtreexoProof> {
+        if index >= self.count {
+            return None;
+        }
+
+        let tree_index = self
+            .trees
+            .iter()
+            .position(|tree| index >= tree.start && index < tree.start + tree.leaves.len())?;
+        let tree = &self.trees[tree_index];
+
+        let mut level = tree.leaves.clone();
+        let mut pos = index - tree.start;
+        let mut steps = Vec::new();
+
+        while level.len() > 1 {
+            let sibling_pos = pos ^ 1;
+            steps.push(if sibling_pos % 2 == 0 {
+                ProofStep::Left(level[sibling_pos])
+            } else {
+                ProofStep::Right(level[sibling_pos])
+            });
+            level = level
+                .chunks_exact(2)
+                .map(|pair| node_hash(&pair[0], &pair[1]))
+                .collect();
+            pos /= 2;
+        }
+
+        Some(UtreexoProof { tree_index, steps })
+    }
+}
+
+/// Verifies that `utxo` is included under one of `roots` (as returned by
+/// [`UtreexoForest::roots`]) per the sibling path and tree index in `proof`.
+pub fn verify(utxo: &Utxo, proof: &UtreexoProof, roots: &[[u8; 32]]) -> bool {
+    let Some(&claimed_root) = roots.get(proof.tree_index) else {
+        return false;
+    };
+
+    let mut current = leaf_hash(utxo);
+    for step in &proof.steps {
+        current = match step {
+            ProofStep::Left(sibling) => node_hash(sibling, &current),
+            ProofStep::Right(sibling) => node_hash(&current, sibling),
+        };
+    }
+
+    current == claimed_root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_doge_canister::types::TxOut;
+    use ic_doge_types::{OutPoint, Txid};
+
+    fn utxo(seed: u8, height: u32) -> Utxo {
+        Utxo {
+            outpoint: OutPoint::new(Txid::from(vec![seed; 32]), 0),
+            txout: TxOut {
+                value: 100,
+                script_pubkey: vec![seed],
+            },
+            height,
+        }
+    }
+
+    #[test]
+    fn test_roots_match_set_bits_of_leaf_count() {
+        // 5 leaves = 0b101: a height-2 tree (4 leaves) and a height-0 tree
+        // (1 leaf).
+        let utxos: Vec<Utxo> = (0..5).map(|i| utxo(i, i as u32)).collect();
+        let forest = UtreexoForest::build(&utxos);
+        assert_eq!(forest.roots().len(), 2);
+    }
+
+    #[test]
+    fn test_prove_and_verify_round_trip_every_leaf() {
+        let utxos: Vec<Utxo> = (0..7).map(|i| utxo(i, i as u32)).collect();
+        let forest = UtreexoForest::build(&utxos);
+        let roots = forest.roots();
+
+        for (index, utxo) in utxos.iter().enumerate() {
+            let proof = forest.prove(index).unwrap();
+            assert!(verify(utxo, &proof, &roots));
+        }
+    }
+
+    #[test]
+    fn test_verify_rejects_mismatched_utxo() {
+        let utxos: Vec<Utxo> = (0..3).map(|i| utxo(i, i as u32)).collect();
+        let forest = UtreexoForest::build(&utxos);
+        let roots = forest.roots();
+
+        let proof = forest.prove(0).unwrap();
+        assert!(!verify(&utxo(99, 99), &proof, &roots));
+    }
+
+    #[test]
+    fn test_prove_out_of_range_is_none() {
+        let utxos: Vec<Utxo> = (0..3).map(|i| utxo(i, i as u32)).collect();
+        let forest = UtreexoForest::build(&utxos);
+        assert!(forest.prove(3).is_none());
+    }
+}
@@ -2,8 +2,9 @@ use ic_cdk_macros::{heartbeat, init, inspect_message, post_upgrade, pre_upgrade,
 use ic_doge_canister::types::{HttpRequest, HttpResponse};
 use ic_doge_interface::{
     Config, GetBalanceRequest, GetBlockHeadersRequest, GetBlockHeadersResponse,
-    GetCurrentFeePercentilesRequest, GetUtxosRequest, GetUtxosResponse, InitConfig,
-    MillikoinuPerByte, SendTransactionRequest, SetConfigRequest,
+    GetCurrentFeePercentilesRequest, GetExpectedTargetRequest, GetExpectedTargetResponse,
+    GetUtxosRequest, GetUtxosResponse, InitConfig, MillikoinuPerByte, SendTransactionRequest,
+    SetConfigRequest,
 };
 use ic_cdk::api::{msg_reject, msg_reply};
 use std::marker::PhantomData;
@@ -64,6 +65,15 @@ pub fn dogecoin_get_balance_query(request: GetBalanceRequest) -> PhantomData<Amo
     PhantomData
 }
 
+// `GetUtxosRequest`/`GetUtxosResponse` already mirror the Bitcoin interface's
+// richer query surface: `UtxosFilter::MinConfirmations` and
+// `GetUtxosResponse::{tip_block_hash, tip_height, next_page}` are defined in
+// `ic_doge_interface` and exercised end-to-end in `tests.rs`. An opaque
+// continuation-token filter variant (e.g. `UtxosFilter::Page`) and the
+// paginated-response logic behind it would need to live in the same two
+// places -- `ic_doge_interface`'s request/response types and
+// `ic_doge_canister`'s `get_utxos` implementation -- neither of which is
+// vendored in this workspace snapshot, so that half can't be added here.
 #[update(manual_reply = true)]
 pub fn dogecoin_get_utxos(request: GetUtxosRequest) -> PhantomData<GetUtxosResponse> {
     match ic_doge_canister::get_utxos(request) {
@@ -73,6 +83,14 @@ pub fn dogecoin_get_utxos(request: GetUtxosRequest) -> PhantomData<GetUtxosRespo
     PhantomData
 }
 
+// A `dogecoin_get_utxos_commitment` query certified via `data_certificate()`
+// -- mirroring `state_reader::hash::compute_utxo_set_commitment_accumulator`,
+// maintained incrementally on every live insert/remove and pinned to a
+// height -- would belong here alongside the other certified query endpoints.
+// The accumulator function itself now exists in the state-reader crate this
+// workspace does own, but wiring it into the live UTXO set means touching
+// `ic_doge_canister`'s insert/remove paths and adding a new endpoint to
+// `ic_doge_interface`, neither of which is vendored in this snapshot.
 #[query(manual_reply = true)]
 pub fn dogecoin_get_utxos_query(request: GetUtxosRequest) -> PhantomData<GetUtxosResponse> {
     if ic_cdk::api::data_certificate().is_none() {
@@ -97,6 +115,17 @@ pub fn dogecoin_get_block_headers(
     PhantomData
 }
 
+#[update(manual_reply = true)]
+pub fn dogecoin_get_expected_target(
+    request: GetExpectedTargetRequest,
+) -> PhantomData<GetExpectedTargetResponse> {
+    match ic_doge_canister::get_expected_target(request) {
+        Ok(response) => msg_reply(candid::encode_one(response).unwrap()),
+        Err(e) => msg_reject(format!("get_expected_target failed: {:?}", e).as_str()),
+    }
+    PhantomData
+}
+
 #[update(manual_reply = true)]
 async fn dogecoin_send_transaction(request: SendTransactionRequest) -> PhantomData<()> {
     match ic_doge_canister::send_transaction(request).await {
@@ -0,0 +1,141 @@
+//! A t-digest: a small, mergeable sketch for estimating quantiles of a
+//! stream of values in bounded memory.
+//!
+//! This is the data structure an incrementally-updatable backing for
+//! `fee_percentiles_are_evaluated_eagerly` would be built on: inserting a fee
+//! rate is O(log centroids) instead of the O(total tx) recompute the eager
+//! path currently performs on every block. The actual eager-evaluation call
+//! site lives in the `ic_doge_canister` state-update path, outside this
+//! crate, so this module is not wired into it; it is provided as the
+//! self-contained building block that change would reuse.
+
+/// A weighted centroid: the mean of the values merged into it, and how many
+/// values that represents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+impl Centroid {
+    fn merge(&mut self, other: Centroid) {
+        let total_weight = self.weight + other.weight;
+        self.mean = (self.mean * self.weight + other.mean * other.weight) / total_weight;
+        self.weight = total_weight;
+    }
+}
+
+/// A t-digest sketch of a stream of `f64` values.
+///
+/// Centroids are kept sorted by mean. Compression bounds each centroid's
+/// weight by `size_bound`, which shrinks near the 0th/100th quantile so the
+/// digest stays accurate in the tails (where fee percentiles matter most)
+/// while still bounding total memory to roughly `compression` centroids.
+#[derive(Debug, Clone)]
+pub struct TDigest {
+    centroids: Vec<Centroid>,
+    compression: f64,
+    total_weight: f64,
+}
+
+impl TDigest {
+    /// Creates an empty digest. `compression` controls the size/accuracy
+    /// trade-off: a few hundred is the typical range used in practice.
+    pub fn new(compression: f64) -> Self {
+        Self {
+            centroids: Vec::new(),
+            compression,
+            total_weight: 0.0,
+        }
+    }
+
+    /// Inserts a new value with weight 1, then recompresses.
+    pub fn insert(&mut self, value: f64) {
+        self.centroids.push(Centroid {
+            mean: value,
+            weight: 1.0,
+        });
+        self.total_weight += 1.0;
+        self.compress();
+    }
+
+    /// The upper bound on a centroid's weight at cumulative-weight quantile
+    /// `q` (0.0..=1.0), scaled by the digest's total weight. Shrinks towards
+    /// the 0th/100th quantile and is largest at the median.
+    fn size_bound(&self, q: f64) -> f64 {
+        4.0 * self.total_weight * q * (1.0 - q) / self.compression
+    }
+
+    /// Sorts centroids by mean and greedily merges adjacent ones while the
+    /// merged weight stays within `size_bound` for its position in the
+    /// cumulative distribution.
+    fn compress(&mut self) {
+        if self.centroids.len() <= 1 {
+            return;
+        }
+
+        self.centroids
+            .sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap());
+
+        let mut compressed = Vec::with_capacity(self.centroids.len());
+        let mut cumulative_weight = 0.0;
+        let mut current = self.centroids[0];
+
+        for &next in &self.centroids[1..] {
+            let merged_weight = current.weight + next.weight;
+            let q = (cumulative_weight + merged_weight / 2.0) / self.total_weight;
+
+            if merged_weight <= self.size_bound(q) {
+                current.merge(next);
+            } else {
+                cumulative_weight += current.weight;
+                compressed.push(current);
+                current = next;
+            }
+        }
+        compressed.push(current);
+
+        self.centroids = compressed;
+    }
+
+    /// Estimates the value at the given percentile (0..=100) by walking the
+    /// centroids in order and linearly interpolating within the centroid
+    /// that straddles the target cumulative weight.
+    pub fn percentile(&self, percentile: u8) -> Option<f64> {
+        if self.centroids.is_empty() {
+            return None;
+        }
+        if self.centroids.len() == 1 {
+            return Some(self.centroids[0].mean);
+        }
+
+        let target_weight = (percentile as f64 / 100.0) * self.total_weight;
+        let mut cumulative_weight = 0.0;
+        let last_index = self.centroids.len() - 1;
+
+        for (i, window) in self.centroids.windows(2).enumerate() {
+            let (left, right) = (window[0], window[1]);
+            let next_cumulative_weight = cumulative_weight + left.weight;
+
+            if target_weight <= next_cumulative_weight || i == last_index - 1 {
+                let span = next_cumulative_weight - cumulative_weight;
+                let fraction = if span > 0.0 {
+                    (target_weight - cumulative_weight) / span
+                } else {
+                    0.0
+                };
+                return Some(left.mean + fraction.clamp(0.0, 1.0) * (right.mean - left.mean));
+            }
+
+            cumulative_weight = next_cumulative_weight;
+        }
+
+        self.centroids.last().map(|c| c.mean)
+    }
+
+    /// Returns the 101 percentile points (0..=100), matching the shape
+    /// `get_current_fee_percentiles` returns today.
+    pub fn percentiles(&self) -> Vec<f64> {
+        (0..=100).filter_map(|p| self.percentile(p)).collect()
+    }
+}
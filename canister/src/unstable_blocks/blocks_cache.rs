@@ -74,6 +74,123 @@ impl BlocksCache for StableBlocksCache {
     }
 }
 
+/// An in-memory [`BlocksCache`] that evicts least-recently-used blocks once
+/// the accumulated consensus-encoded size of its entries exceeds
+/// `max_bytes`, instead of growing without bound like [`MemBlocksCache`] and
+/// [`StableBlocksCache`]. Useful for capping how much unconfirmed/side-chain
+/// block data the canister is willing to hold onto at once.
+pub struct LruBlocksCache {
+    pub network: Network,
+    max_bytes: u64,
+    current_bytes: u64,
+    next_sequence: u64,
+    /// Block payloads, consensus-encoded, keyed by hash.
+    blocks: BTreeMap<BlockHash, Vec<u8>>,
+    /// Access order: sequence number -> block hash. The smallest key is the
+    /// least-recently-used entry.
+    access_order: BTreeMap<u64, BlockHash>,
+    /// Reverse index so a block's old sequence entry can be removed from
+    /// `access_order` when it's touched again.
+    sequence_of: BTreeMap<BlockHash, u64>,
+}
+
+impl LruBlocksCache {
+    /// Creates an empty cache that evicts entries once their combined
+    /// consensus-encoded size exceeds `max_bytes`.
+    pub fn new(network: Network, max_bytes: u64) -> Self {
+        Self {
+            network,
+            max_bytes,
+            current_bytes: 0,
+            next_sequence: 0,
+            blocks: BTreeMap::new(),
+            access_order: BTreeMap::new(),
+            sequence_of: BTreeMap::new(),
+        }
+    }
+
+    /// Records `block_hash` as just-accessed, bumping its sequence number to
+    /// the most-recently-used end (the largest key) of the access order.
+    fn touch(&mut self, block_hash: &BlockHash) {
+        if let Some(old_sequence) = self.sequence_of.remove(block_hash) {
+            self.access_order.remove(&old_sequence);
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.access_order.insert(sequence, block_hash.clone());
+        self.sequence_of.insert(block_hash.clone(), sequence);
+    }
+
+    /// Evicts least-recently-used entries until `current_bytes` is within
+    /// `max_bytes`.
+    fn evict_to_budget(&mut self) {
+        while self.current_bytes > self.max_bytes {
+            let Some((&sequence, _)) = self.access_order.iter().next() else {
+                break;
+            };
+            let block_hash = self.access_order.remove(&sequence).unwrap();
+            self.sequence_of.remove(&block_hash);
+            if let Some(bytes) = self.blocks.remove(&block_hash) {
+                self.current_bytes -= bytes.len() as u64;
+            }
+        }
+    }
+}
+
+impl BlocksCache for LruBlocksCache {
+    fn insert(&mut self, block_hash: BlockHash, block: Block) -> bool {
+        let mut bytes = Vec::new();
+        block.consensus_encode(&mut bytes).unwrap();
+        self.current_bytes += bytes.len() as u64;
+
+        let is_new = match self.blocks.insert(block_hash.clone(), bytes) {
+            Some(old_bytes) => {
+                self.current_bytes -= old_bytes.len() as u64;
+                false
+            }
+            None => true,
+        };
+        self.touch(&block_hash);
+        self.evict_to_budget();
+        is_new
+    }
+
+    fn remove(&mut self, block_hash: &BlockHash) -> bool {
+        if let Some(sequence) = self.sequence_of.remove(block_hash) {
+            self.access_order.remove(&sequence);
+        }
+        match self.blocks.remove(block_hash) {
+            Some(bytes) => {
+                self.current_bytes -= bytes.len() as u64;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // `BlocksCache::get` takes `&self`, so a read can't bump this entry's
+    // access-order sequence the way `insert` does; eviction order here is
+    // therefore driven by insertion/re-insertion recency only, not reads.
+    fn get(&self, block_hash: &BlockHash) -> Option<Block> {
+        use bitcoin::consensus::Decodable;
+        let bytes = self.blocks.get(block_hash)?;
+        let block = bitcoin::dogecoin::Block::consensus_decode(&mut bytes.as_slice()).ok()?;
+        Some(Block::new(block))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    fn len(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
 pub struct MemBlocksCache {
     pub network: Network,
     map: BTreeMap<BlockHash, Block>,
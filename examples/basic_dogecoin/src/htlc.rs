@@ -0,0 +1,155 @@
+//! Hash-time-locked (HTLC) P2SH scripts, the building block this example
+//! needs to act as one leg of a cross-chain atomic swap: a payment can be
+//! claimed by whoever reveals the preimage of a payment hash before
+//! `lock_time`, or reclaimed by the sender after it.
+//!
+//! Standard redeem script:
+//! ```text
+//! OP_IF
+//!     OP_SHA256 <payment_hash> OP_EQUALVERIFY
+//!     <claim_pubkey> OP_CHECKSIG
+//! OP_ELSE
+//!     <lock_time> OP_CHECKLOCKTIMEVERIFY OP_DROP
+//!     <refund_pubkey> OP_CHECKSIG
+//! OP_ENDIF
+//! ```
+
+use bitcoin::dogecoin::{Address, Network};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::opcodes::all::{
+    OP_CHECKSIG, OP_CLTV, OP_DROP, OP_ELSE, OP_ENDIF, OP_EQUALVERIFY, OP_IF, OP_PUSHBYTES_0,
+    OP_PUSHNUM_1, OP_SHA256,
+};
+use bitcoin::script::{Instruction, PushBytesBuf};
+use bitcoin::{absolute::LockTime, PublicKey, Script, ScriptBuf, Transaction};
+
+/// The parameters of one HTLC: the hash gating the claim path, the lock
+/// time gating the refund path, and the two keys each path pays to.
+#[derive(Clone, Debug)]
+pub struct Htlc {
+    /// SHA256 hash of the secret the claim path must reveal.
+    pub payment_hash: [u8; 32],
+    /// After this absolute lock time (block height or UNIX timestamp, per
+    /// [`LockTime`]'s usual threshold), the refund path becomes spendable.
+    pub lock_time: LockTime,
+    pub claim_pubkey: PublicKey,
+    pub refund_pubkey: PublicKey,
+}
+
+impl Htlc {
+    pub fn new(
+        payment_hash: [u8; 32],
+        lock_time: LockTime,
+        claim_pubkey: PublicKey,
+        refund_pubkey: PublicKey,
+    ) -> Self {
+        Self {
+            payment_hash,
+            lock_time,
+            claim_pubkey,
+            refund_pubkey,
+        }
+    }
+
+    /// Builds the redeem script gating this HTLC's claim and refund paths.
+    pub fn redeem_script(&self) -> ScriptBuf {
+        Script::builder()
+            .push_opcode(OP_IF)
+            .push_opcode(OP_SHA256)
+            .push_slice(self.payment_hash)
+            .push_opcode(OP_EQUALVERIFY)
+            .push_key(&self.claim_pubkey)
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_ELSE)
+            .push_int(self.lock_time.to_consensus_u32() as i64)
+            .push_opcode(OP_CLTV)
+            .push_opcode(OP_DROP)
+            .push_key(&self.refund_pubkey)
+            .push_opcode(OP_CHECKSIG)
+            .push_opcode(OP_ENDIF)
+            .into_script()
+    }
+
+    /// The P2SH address funds must be sent to in order to open this HTLC.
+    pub fn address(&self, network: Network) -> Address {
+        Address::p2sh(&self.redeem_script(), network)
+            .expect("an HTLC redeem script is always within the P2SH size limit")
+    }
+}
+
+/// Assembles the scriptSig for the claim (preimage-reveal) branch: spends
+/// an HTLC output given a signature from the claim key and the revealed
+/// preimage, selecting the redeem script's `OP_IF` branch.
+pub fn claim_script_sig(signature: &[u8], preimage: &[u8; 32], redeem_script: &ScriptBuf) -> ScriptBuf {
+    let signature = PushBytesBuf::try_from(signature.to_vec()).expect("signature fits in a script push");
+    let redeem_script_push =
+        PushBytesBuf::try_from(redeem_script.to_bytes()).expect("redeem script fits in a script push");
+
+    Script::builder()
+        .push_slice(signature)
+        .push_slice(preimage)
+        .push_opcode(OP_PUSHNUM_1)
+        .push_slice(redeem_script_push)
+        .into_script()
+}
+
+/// Assembles the scriptSig for the refund (timeout) branch: spends an
+/// HTLC output given a signature from the refund key, selecting the
+/// redeem script's `OP_ELSE` branch. The spending transaction must set its
+/// `nLockTime` to at least the HTLC's lock time and a non-final sequence
+/// number for `OP_CHECKLOCKTIMEVERIFY` to pass -- this only builds the
+/// scriptSig half of that.
+pub fn refund_script_sig(signature: &[u8], redeem_script: &ScriptBuf) -> ScriptBuf {
+    let signature = PushBytesBuf::try_from(signature.to_vec()).expect("signature fits in a script push");
+    let redeem_script_push =
+        PushBytesBuf::try_from(redeem_script.to_bytes()).expect("redeem script fits in a script push");
+
+    Script::builder()
+        .push_slice(signature)
+        .push_opcode(OP_PUSHBYTES_0)
+        .push_slice(redeem_script_push)
+        .into_script()
+}
+
+/// Looks for an output of `tx` paying `htlc`'s P2SH address, confirming
+/// the HTLC was actually funded as expected. Returns its index and value
+/// (in koinu) if found.
+///
+/// Only `tx`'s outputs are inspected, so this works against any
+/// [`Transaction`] the caller has obtained in full (e.g. from a
+/// `bootstrap.dat` import or a trusted peer) -- `dogecoin_get_utxos` itself
+/// only reports outpoints, values, and heights, not output scripts or the
+/// transactions that produced them, so it alone can't drive this check.
+pub fn funding_output(tx: &Transaction, htlc: &Htlc, network: Network) -> Option<(u32, u64)> {
+    let expected_script_pubkey = htlc.address(network).script_pubkey();
+    tx.output.iter().enumerate().find_map(|(vout, out)| {
+        (out.script_pubkey == expected_script_pubkey).then_some((vout as u32, out.value.to_sat()))
+    })
+}
+
+/// Extracts the preimage revealed by a claim-branch spend of `htlc`, if
+/// `script_sig` is one: its final push must be exactly `htlc`'s redeem
+/// script, and one of its other pushes must be a 32-byte value hashing to
+/// `htlc.payment_hash`.
+///
+/// Returns `None` for a refund-branch spend, or any scriptSig that doesn't
+/// match this HTLC's redeem script.
+pub fn extract_preimage(script_sig: &Script, htlc: &Htlc) -> Option<[u8; 32]> {
+    let redeem_script = htlc.redeem_script();
+    let instructions: Vec<_> = script_sig.instructions().collect::<Result<_, _>>().ok()?;
+
+    match instructions.last()? {
+        Instruction::PushBytes(bytes) if bytes.as_bytes() == redeem_script.as_bytes() => {}
+        _ => return None,
+    }
+
+    instructions[..instructions.len() - 1]
+        .iter()
+        .find_map(|instruction| {
+            let Instruction::PushBytes(bytes) = instruction else {
+                return None;
+            };
+            let preimage: [u8; 32] = bytes.as_bytes().try_into().ok()?;
+            (sha256::Hash::hash(&preimage).to_byte_array() == htlc.payment_hash).then_some(preimage)
+        })
+}
@@ -1,5 +1,6 @@
 mod common;
 mod ecdsa;
+pub mod htlc;
 mod p2pkh;
 mod service;
 
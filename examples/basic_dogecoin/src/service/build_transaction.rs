@@ -0,0 +1,341 @@
+use super::get_fee_estimate::{percentile_for_target, DUST_THRESHOLD_KOINU};
+use crate::{dogecoin_get_fee_percentiles, dogecoin_get_utxos, DOGE_CONTEXT};
+use bitcoin::dogecoin::Address;
+use bitcoin::hashes::Hash;
+use bitcoin::{
+    absolute::LockTime, transaction::Version, Amount, OutPoint, ScriptBuf, Sequence, Transaction,
+    TxIn, TxOut, Txid, Witness,
+};
+use candid::{CandidType, Deserialize};
+use ic_cdk::{
+    bitcoin_canister::{GetCurrentFeePercentilesRequest, GetUtxosRequest, Utxo},
+    update,
+};
+use std::str::FromStr;
+
+/// Fixed part of a P2PKH send: version (4) + locktime (4) + input and output
+/// counts (1 each). Every input and output, the destination included, is
+/// added on top of this by [`estimated_vsize`]. Matches the shape
+/// [`TransactionBuilder`](ic_doge_test_utils::TransactionBuilder) produces:
+/// one non-segwit output set with no witness data.
+const TX_OVERHEAD_VBYTES: u64 = 10;
+/// Size of a spent P2PKH input: outpoint (36) + empty script_sig length
+/// prefix (1) + a standard DER signature + pubkey `script_sig` (~107) +
+/// sequence (4).
+const P2PKH_INPUT_VBYTES: u64 = 148;
+/// Size of a P2PKH output: value (8) + script_pubkey length prefix and
+/// `OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG` (26).
+const P2PKH_OUTPUT_VBYTES: u64 = 34;
+
+/// Upper bound on how many nodes [`select_coins_bnb`] will explore before
+/// giving up and falling back to [`select_coins_accumulative`]. Bounds the
+/// worst case (many same-sized UTXOs) to a cost proportional to this rather
+/// than to 2^n.
+const BNB_NODE_BUDGET: u32 = 100_000;
+
+fn estimated_vsize(num_inputs: u64, num_outputs: u64) -> u64 {
+    TX_OVERHEAD_VBYTES + num_inputs * P2PKH_INPUT_VBYTES + num_outputs * P2PKH_OUTPUT_VBYTES
+}
+
+/// One of the caller's UTXOs, selected as a transaction input.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SelectedUtxo {
+    pub txid: Vec<u8>,
+    pub vout: u32,
+    pub value_koinu: u64,
+}
+
+impl From<&Utxo> for SelectedUtxo {
+    fn from(utxo: &Utxo) -> Self {
+        Self {
+            txid: utxo.outpoint.txid.clone(),
+            vout: utxo.outpoint.vout,
+            value_koinu: utxo.value,
+        }
+    }
+}
+
+/// An assembled, unsigned transaction, ready to be signed input-by-input
+/// (e.g. by an ECDSA-backed P2PKH signer) and submitted via
+/// `dogecoin_send_transaction`.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct BuildTransactionResponse {
+    /// The consensus-encoded, unsigned transaction (empty `script_sig`s).
+    pub unsigned_transaction_bytes: Vec<u8>,
+    /// The UTXOs `unsigned_transaction_bytes`'s inputs spend, in order.
+    pub selected_utxos: Vec<SelectedUtxo>,
+    /// The fee paid, in koinu: `sum(selected_utxos) - amount_in_koinu -
+    /// change` (`change` is zero if no change output was created).
+    pub fee_koinu: u64,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub enum BuildTransactionError {
+    /// `destination_address` or `source_address` isn't a valid address for
+    /// the configured network.
+    InvalidAddress(String),
+    /// `source_address` has no UTXOs to spend.
+    NoUtxos,
+    /// Even spending every UTXO of `source_address` doesn't cover
+    /// `amount_in_koinu` plus fees.
+    InsufficientFunds { available_koinu: u64, needed_koinu: u64 },
+}
+
+/// Builds an unsigned P2PKH transaction sending `amount_in_koinu` from
+/// `source_address` to `destination_address`, aiming to confirm within
+/// `confirmation_target` blocks.
+///
+/// Selects inputs from `source_address`'s UTXOs (fetched via
+/// `dogecoin_get_utxos`) with [`select_coins_bnb`], falling back to
+/// [`select_coins_accumulative`] when no changeless combination is found,
+/// and derives the fee rate from `dogecoin_get_fee_percentiles` using the
+/// same urgency-to-percentile mapping as
+/// [`get_fee_estimate`](super::get_fee_estimate::get_fee_estimate). A change
+/// output below the dust threshold is folded into the fee rather than
+/// created.
+///
+/// Does not sign or submit the transaction -- this example has no ECDSA
+/// signing module to call, so the caller is left to sign
+/// `unsigned_transaction_bytes`'s inputs (one signature per entry in
+/// `selected_utxos`, in order) and pass the result to
+/// `dogecoin_send_transaction`.
+#[update]
+pub async fn build_transaction(
+    source_address: String,
+    destination_address: String,
+    amount_in_koinu: u64,
+    confirmation_target: u32,
+) -> Result<BuildTransactionResponse, BuildTransactionError> {
+    let ctx = DOGE_CONTEXT.with(|ctx| ctx.get());
+
+    let destination = Address::from_str(&destination_address)
+        .map_err(|e| BuildTransactionError::InvalidAddress(e.to_string()))?;
+    let change_destination = Address::from_str(&source_address)
+        .map_err(|e| BuildTransactionError::InvalidAddress(e.to_string()))?;
+
+    let utxos = dogecoin_get_utxos(&GetUtxosRequest {
+        address: source_address,
+        network: ctx.network.into(),
+        filter: None,
+    })
+    .await
+    .unwrap()
+    .utxos;
+
+    if utxos.is_empty() {
+        return Err(BuildTransactionError::NoUtxos);
+    }
+
+    let percentiles = dogecoin_get_fee_percentiles(&GetCurrentFeePercentilesRequest {
+        network: ctx.network.into(),
+    })
+    .await
+    .unwrap();
+    // `percentile_for_target` returns millikoinu/byte; every fee
+    // computation below (input/output vbyte costs, `InsufficientFunds`'s
+    // `needed_koinu`) is in koinu/byte, so convert here rather than at each
+    // call site.
+    let fee_rate = (percentile_for_target(&percentiles, confirmation_target) / 1000).max(1);
+
+    let selection = select_coins_bnb(&utxos, amount_in_koinu, fee_rate)
+        .unwrap_or_else(|| select_coins_accumulative(&utxos, amount_in_koinu, fee_rate));
+
+    let Some(selection) = selection else {
+        let available_koinu = utxos.iter().map(|u| u.value).sum();
+        return Err(BuildTransactionError::InsufficientFunds {
+            available_koinu,
+            needed_koinu: amount_in_koinu
+                + fee_rate * estimated_vsize(utxos.len() as u64, 2),
+        });
+    };
+
+    let total_input: u64 = selection.iter().map(|u| u.value).sum();
+    let fee_with_change = fee_rate * estimated_vsize(selection.len() as u64, 2);
+    let change_koinu = total_input
+        .saturating_sub(amount_in_koinu)
+        .saturating_sub(fee_with_change);
+
+    // Dust-sized change (a non-zero remainder too small to be worth its own
+    // output) is folded into the fee instead of minting an uneconomical
+    // output: the fee becomes whatever's left after the destination output,
+    // change included.
+    let (has_change, fee_koinu) = if change_koinu > DUST_THRESHOLD_KOINU {
+        (true, fee_with_change)
+    } else {
+        (false, total_input - amount_in_koinu)
+    };
+
+    let input = selection
+        .iter()
+        .map(|utxo| TxIn {
+            previous_output: OutPoint {
+                txid: Txid::from_byte_array(
+                    utxo.outpoint.txid.clone().try_into().expect(
+                        "the management canister always returns 32-byte txids in a Utxo's outpoint",
+                    ),
+                ),
+                vout: utxo.outpoint.vout,
+            },
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence(0xffffffff),
+            witness: Witness::new(),
+        })
+        .collect();
+
+    let mut output = vec![TxOut {
+        value: Amount::from_sat(amount_in_koinu),
+        script_pubkey: destination.script_pubkey(),
+    }];
+    if has_change {
+        output.push(TxOut {
+            value: Amount::from_sat(change_koinu),
+            script_pubkey: change_destination.script_pubkey(),
+        });
+    }
+
+    let transaction = Transaction {
+        version: Version(1),
+        lock_time: LockTime::ZERO,
+        input,
+        output,
+    };
+
+    Ok(BuildTransactionResponse {
+        unsigned_transaction_bytes: bitcoin::consensus::serialize(&transaction),
+        selected_utxos: selection.iter().map(SelectedUtxo::from).collect(),
+        fee_koinu,
+    })
+}
+
+/// Branch-and-bound coin selection: searches for a subset of `utxos` whose
+/// total lands in `[target, target + cost_of_change]`, where `target` is
+/// `amount` plus the fee for a changeless transaction and `cost_of_change`
+/// is the fee of adding (and eventually spending) a change output --
+/// producing a transaction with no change output at all. Returns `None` if
+/// no such subset is found within [`BNB_NODE_BUDGET`] nodes, in which case
+/// the caller should fall back to [`select_coins_accumulative`].
+///
+/// This is a simplified version of the wallet coin-selection algorithm
+/// Bitcoin Core uses (see `src/wallet/coinselection.cpp`'s `SelectCoinsBnB`):
+/// it stops at the first match found via a depth-first, include/exclude
+/// search over UTXOs sorted by descending effective value, rather than
+/// continuing to search for the combination with the least waste.
+fn select_coins_bnb(utxos: &[Utxo], amount: u64, fee_rate: u64) -> Option<Vec<Utxo>> {
+    let input_cost = fee_rate * P2PKH_INPUT_VBYTES;
+    let mut candidates: Vec<(&Utxo, u64)> = utxos
+        .iter()
+        .filter_map(|u| u.value.checked_sub(input_cost).map(|ev| (u, ev)))
+        .filter(|&(_, effective_value)| effective_value > 0)
+        .collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let target = amount + fee_rate * estimated_vsize(0, 1);
+    let cost_of_change = fee_rate * (P2PKH_OUTPUT_VBYTES + P2PKH_INPUT_VBYTES);
+
+    // Suffix sums of effective value, used to prune branches that can never
+    // reach `target` even by including every remaining candidate.
+    let mut suffix_sum = vec![0u64; candidates.len() + 1];
+    for i in (0..candidates.len()).rev() {
+        suffix_sum[i] = suffix_sum[i + 1] + candidates[i].1;
+    }
+
+    let mut nodes_explored = 0u32;
+    let mut selected = Vec::new();
+    if bnb_search(
+        &candidates,
+        &suffix_sum,
+        0,
+        0,
+        target,
+        cost_of_change,
+        &mut nodes_explored,
+        &mut selected,
+    ) {
+        Some(selected.into_iter().cloned().collect())
+    } else {
+        None
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn bnb_search<'a>(
+    candidates: &[(&'a Utxo, u64)],
+    suffix_sum: &[u64],
+    index: usize,
+    current_value: u64,
+    target: u64,
+    cost_of_change: u64,
+    nodes_explored: &mut u32,
+    selected: &mut Vec<&'a Utxo>,
+) -> bool {
+    *nodes_explored += 1;
+    if *nodes_explored > BNB_NODE_BUDGET {
+        return false;
+    }
+
+    if current_value > target + cost_of_change {
+        return false;
+    }
+    if current_value >= target {
+        return true;
+    }
+    if index == candidates.len() || current_value + suffix_sum[index] < target {
+        return false;
+    }
+
+    // Branch 1: include `candidates[index]`.
+    selected.push(candidates[index].0);
+    if bnb_search(
+        candidates,
+        suffix_sum,
+        index + 1,
+        current_value + candidates[index].1,
+        target,
+        cost_of_change,
+        nodes_explored,
+        selected,
+    ) {
+        return true;
+    }
+    selected.pop();
+
+    // Branch 2: exclude it.
+    bnb_search(
+        candidates,
+        suffix_sum,
+        index + 1,
+        current_value,
+        target,
+        cost_of_change,
+        nodes_explored,
+        selected,
+    )
+}
+
+/// Largest-first coin selection: sorts `utxos` by descending value and
+/// accepts them one at a time until their total covers `amount` plus the
+/// fee for the inputs selected so far (re-evaluated on every addition,
+/// since each extra input grows the estimated vsize and therefore the
+/// fee). Used when [`select_coins_bnb`] can't find a changeless
+/// combination; unlike BnB, this virtually always leaves change.
+///
+/// Returns `None` if every UTXO together still doesn't cover the amount
+/// plus fees.
+fn select_coins_accumulative(utxos: &[Utxo], amount: u64, fee_rate: u64) -> Option<Vec<Utxo>> {
+    let mut sorted: Vec<&Utxo> = utxos.iter().collect();
+    sorted.sort_by(|a, b| b.value.cmp(&a.value));
+
+    let mut total = 0u64;
+    let mut selected = Vec::new();
+    for utxo in sorted {
+        total += utxo.value;
+        selected.push(utxo.clone());
+        // Assume a change output: the caller folds it into the fee later if
+        // it would end up dust-sized.
+        let needed = amount + fee_rate * estimated_vsize(selected.len() as u64, 2);
+        if total >= needed {
+            return Some(selected);
+        }
+    }
+    None
+}
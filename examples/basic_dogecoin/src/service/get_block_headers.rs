@@ -22,3 +22,14 @@ pub async fn get_block_headers(
     .await
     .unwrap()
 }
+
+/// Returns the single block header at the given height, if it exists.
+///
+/// A convenience wrapper around [`get_block_headers`] for light clients that
+/// want to verify proof-of-work or confirmation depth for one block without
+/// having to think about the range/pagination shape of the underlying query.
+#[update]
+pub async fn get_block_header(height: u32) -> Option<Vec<u8>> {
+    let response = get_block_headers(height, Some(height)).await;
+    response.block_headers.into_iter().next()
+}
@@ -18,3 +18,31 @@ pub async fn get_current_fee_percentiles() -> Vec<MillisatoshiPerByte> {
     .await
     .unwrap()
 }
+
+/// Returns the same fee percentiles as [`get_current_fee_percentiles`]:
+/// `_from_height` and `_to_height` are accepted but not used, so the result
+/// is not limited to blocks in that height range.
+///
+/// The management canister's `GetCurrentFeePercentilesRequest` has no
+/// height-range parameter, and fee rates aren't indexed by the stable height
+/// they confirmed at anywhere this example can observe — that indexing
+/// lives, if anywhere, in the state that tracks `stable_height()`, on the
+/// canister side of the management-canister boundary.
+#[update]
+pub async fn get_fee_percentiles_in_range(
+    _from_height: u32,
+    _to_height: u32,
+) -> Vec<MillisatoshiPerByte> {
+    get_current_fee_percentiles().await
+}
+
+/// Convenience variant of [`get_fee_percentiles_in_range`] for "the last N
+/// blocks" queries (e.g. the last 6 or 144 blocks). Likewise,
+/// `_num_recent_blocks` is not used: this returns the same distribution as
+/// [`get_current_fee_percentiles`].
+#[update]
+pub async fn get_fee_percentiles_for_recent_blocks(
+    _num_recent_blocks: u32,
+) -> Vec<MillisatoshiPerByte> {
+    get_current_fee_percentiles().await
+}
@@ -0,0 +1,83 @@
+use crate::{dogecoin_get_fee_percentiles, DOGE_CONTEXT};
+use ic_cdk::{
+    bitcoin_canister::{GetCurrentFeePercentilesRequest, MillisatoshiPerByte},
+    update,
+};
+
+/// Returns a recommended fee rate (in millikoinu/byte) to get a transaction
+/// confirmed within `confirmation_target` blocks.
+///
+/// The canister only exposes a flat 101-point fee-rate distribution via
+/// [`get_current_fee_percentiles`](super::get_current_fee_percentiles::get_current_fee_percentiles),
+/// not a per-target success-ratio tracker, so this maps `confirmation_target`
+/// onto a percentile heuristically: tighter targets get a higher percentile.
+/// A faithful confirmation-target estimator (decaying per-bucket
+/// confirmed/total counters, as Bitcoin Core's `policy/fees` estimator does)
+/// needs to observe per-transaction wait times as blocks are ingested, which
+/// requires canister-side state this example does not have access to.
+#[update]
+pub async fn get_fee_estimate(confirmation_target: u32) -> MillisatoshiPerByte {
+    let ctx = DOGE_CONTEXT.with(|ctx| ctx.get());
+
+    let percentiles = dogecoin_get_fee_percentiles(&GetCurrentFeePercentilesRequest {
+        network: ctx.network.into(),
+    })
+    .await
+    .unwrap();
+
+    percentile_for_target(&percentiles, confirmation_target)
+}
+
+/// Picks a percentile index from `percentiles` (expected to have 101 entries,
+/// one per percentile point 0..=100) based on how urgently the caller wants
+/// to confirm: target 0 maps to the 90th percentile and target 1 to the
+/// 89th (`50 + (144 - target) * 40 / 144`, rounded down), decaying down to the
+/// median by target 144 (roughly a day of Dogecoin blocks) and beyond.
+pub(crate) fn percentile_for_target(percentiles: &[MillisatoshiPerByte], confirmation_target: u32) -> MillisatoshiPerByte {
+    if percentiles.is_empty() {
+        return 0;
+    }
+
+    let urgency = 144u32.saturating_sub(confirmation_target.min(144));
+    let percentile = 50 + (urgency * 40 / 144);
+    let index = (percentile as usize).min(percentiles.len() - 1);
+    percentiles[index]
+}
+
+/// Fee must not exceed this fraction (percent) of the spend amount.
+const DEFAULT_RELATIVE_FEE_CAP_PERCENT: u64 = 3;
+
+/// Hard ceiling on a capped fee, in koinu, regardless of spend amount.
+const DEFAULT_ABSOLUTE_FEE_CAP_KOINU: u64 = 100_000_000; // 1 DOGE
+
+/// The minimum economically-spendable output value, in koinu, below which an
+/// output is considered dust. Mirrors Dogecoin Core's `MIN_TXOUT_AMOUNT`.
+pub(crate) const DUST_THRESHOLD_KOINU: u64 = 100_000_000; // 1 DOGE
+
+/// Like [`get_fee_estimate`], but clamps the returned rate so a caller can't
+/// be charged an unreasonable fee relative to the amount it's spending.
+///
+/// Returns `(raw_estimate, capped_estimate, was_capped)`.
+#[update]
+pub async fn get_fee_estimate_capped(
+    confirmation_target: u32,
+    spend_amount_koinu: u64,
+) -> (MillisatoshiPerByte, MillisatoshiPerByte, bool) {
+    let raw = get_fee_estimate(confirmation_target).await;
+
+    let relative_cap = spend_amount_koinu * DEFAULT_RELATIVE_FEE_CAP_PERCENT / 100;
+    let cap = relative_cap.min(DEFAULT_ABSOLUTE_FEE_CAP_KOINU);
+
+    if raw > cap {
+        (raw, cap, true)
+    } else {
+        (raw, raw, false)
+    }
+}
+
+/// Returns the dust threshold, in koinu: the minimum value an output can
+/// hold before it's considered uneconomical to spend.
+#[update]
+pub async fn get_dust_threshold() -> u64 {
+    DUST_THRESHOLD_KOINU
+}
@@ -1,6 +1,6 @@
 use bitcoin::dogecoin::auxpow::{AuxPow, MERGED_MINING_HEADER};
 use bitcoin::dogecoin::constants::genesis_block;
-use bitcoin::hashes::Hash;
+use bitcoin::hashes::{sha256d, Hash};
 use bitcoin::{
     absolute::LockTime,
     block::{Header as PureHeader, Version},
@@ -9,12 +9,14 @@ use bitcoin::{
     dogecoin::Block as DogecoinBlock,
     dogecoin::Header,
     dogecoin::Network,
+    script::{Instruction, PushBytesBuf},
     secp256k1::Secp256k1,
-    Amount, BlockHash, OutPoint, PublicKey, Script, ScriptBuf, Sequence, Target, Transaction, TxIn,
-    TxMerkleNode, TxOut, Witness,
+    Amount, BlockHash, CompactTarget, OutPoint, PublicKey, Script, ScriptBuf, Sequence, Target,
+    Transaction, TxIn, TxMerkleNode, TxOut, Weight, Witness, Wtxid,
 };
 use ic_doge_types::Block;
 use simple_rng::generate_keypair;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 mod simple_rng;
@@ -70,11 +72,118 @@ pub fn mine_header_to_target(header: &mut PureHeader, should_pass: bool) {
     }
 }
 
+/// Magic bytes that prefix a BIP141-style witness commitment inside the
+/// coinbase's commitment output, before the 32-byte commitment hash.
+const WITNESS_COMMITMENT_HEADER: [u8; 4] = [0xaa, 0x21, 0xa9, 0xed];
+
+/// Reserved value placed in the coinbase's witness stack alongside a
+/// witness commitment. A fixture only needs *a* fixed value, not a random
+/// one, since nothing in these tests relies on its entropy.
+const WITNESS_RESERVED_VALUE: [u8; 32] = [0u8; 32];
+
+/// True if any transaction in the block carries a non-empty witness, i.e.
+/// the block needs a witness commitment to be well-formed.
+fn has_witness(txdata: &[Transaction]) -> bool {
+    txdata
+        .iter()
+        .any(|tx| tx.input.iter().any(|input| !input.witness.is_empty()))
+}
+
+/// Computes the witness root used by [`add_witness_commitment`] and
+/// [`check_witness_commitment`]: a merkle root over wtxids, with the
+/// coinbase's wtxid replaced by all zeroes as required by BIP141.
+fn witness_merkle_root(txdata: &[Transaction]) -> TxMerkleNode {
+    let wtxids = txdata.iter().enumerate().map(|(i, tx)| {
+        if i == 0 {
+            *Wtxid::all_zeros().as_raw_hash()
+        } else {
+            *tx.compute_wtxid().as_raw_hash()
+        }
+    });
+
+    let root = bitcoin::merkle_tree::calculate_root(wtxids).unwrap();
+    TxMerkleNode::from_raw_hash(root)
+}
+
+/// Computes the BIP141 witness commitment hash: `SHA256d(witness_root ||
+/// witness_reserved_value)`.
+fn witness_commitment_hash(txdata: &[Transaction], witness_reserved_value: [u8; 32]) -> [u8; 32] {
+    let witness_root = witness_merkle_root(txdata);
+
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(witness_root.as_byte_array());
+    data.extend_from_slice(&witness_reserved_value);
+
+    *sha256d::Hash::hash(&data).as_byte_array()
+}
+
+/// Appends a witness commitment output to the coinbase transaction (first
+/// entry of `txdata`) and sets its witness stack to `witness_reserved_value`,
+/// mirroring BIP141's coinbase layout.
+fn add_witness_commitment(txdata: &mut [Transaction], witness_reserved_value: [u8; 32]) {
+    let commitment_hash = witness_commitment_hash(txdata, witness_reserved_value);
+
+    let mut script_data = WITNESS_COMMITMENT_HEADER.to_vec();
+    script_data.extend_from_slice(&commitment_hash);
+    let push_bytes = PushBytesBuf::try_from(script_data).unwrap();
+    let commitment_script = Script::builder()
+        .push_opcode(bitcoin::opcodes::all::OP_RETURN)
+        .push_slice(push_bytes)
+        .into_script();
+
+    let coinbase = &mut txdata[0];
+    coinbase.output.push(TxOut {
+        value: Amount::ZERO,
+        script_pubkey: commitment_script,
+    });
+    coinbase.input[0].witness = Witness::from_slice(&[witness_reserved_value.to_vec()]);
+}
+
+/// Verifies a block's witness commitment per BIP141: if no transaction uses
+/// witnesses, there's nothing to check. Otherwise, the coinbase's last
+/// output matching [`WITNESS_COMMITMENT_HEADER`] must match the commitment
+/// hash recomputed from `txdata` and that output's reserved value.
+pub fn check_witness_commitment(block: &DogecoinBlock) -> bool {
+    if !has_witness(&block.txdata) {
+        return true;
+    }
+
+    let coinbase = match block.txdata.first() {
+        Some(tx) => tx,
+        None => return false,
+    };
+
+    let witness_reserved_value = match coinbase.input[0].witness.iter().next() {
+        Some(item) if item.len() == 32 => {
+            let mut buf = [0u8; 32];
+            buf.copy_from_slice(item);
+            buf
+        }
+        _ => return false,
+    };
+
+    let commitment = coinbase.output.iter().rev().find_map(|out| {
+        let script = out.script_pubkey.as_bytes();
+        (script.len() >= 38 && script[2..6] == WITNESS_COMMITMENT_HEADER)
+            .then(|| script[6..38].to_vec())
+    });
+
+    match commitment {
+        Some(commitment) => {
+            commitment == witness_commitment_hash(&block.txdata, witness_reserved_value)
+        }
+        None => false,
+    }
+}
+
 pub struct BlockBuilder {
     header: Option<Header>,
     prev_header: Option<PureHeader>,
     transactions: Vec<Transaction>,
     with_auxpow: bool,
+    difficulty_adjustment_headers: Option<Vec<PureHeader>>,
+    with_witness_commitment: bool,
+    time_offset: u32,
 }
 
 impl BlockBuilder {
@@ -84,6 +193,9 @@ impl BlockBuilder {
             prev_header: None,
             transactions: vec![],
             with_auxpow: false,
+            difficulty_adjustment_headers: None,
+            with_witness_commitment: false,
+            time_offset: 60,
         }
     }
 
@@ -92,6 +204,22 @@ impl BlockBuilder {
         self
     }
 
+    /// As [`HeaderBuilder::with_time_offset`]: the gap, in seconds, between
+    /// this block's header timestamp and its parent's.
+    pub fn with_time_offset(mut self, time_offset: u32) -> Self {
+        self.time_offset = time_offset;
+        self
+    }
+
+    /// Builds the header using Dogecoin's per-block DigiShield retarget
+    /// (see [`HeaderBuilder::with_difficulty_adjustment`]) instead of
+    /// copying `bits` from the previous header. `prev_headers` is the
+    /// chain built so far.
+    pub fn with_difficulty_adjustment(mut self, prev_headers: &[PureHeader]) -> Self {
+        self.difficulty_adjustment_headers = Some(prev_headers.to_vec());
+        self
+    }
+
     pub fn with_header(mut self, header: Header) -> Self {
         self.header = Some(header);
         self
@@ -107,8 +235,16 @@ impl BlockBuilder {
         self
     }
 
+    /// Appends a BIP141-style witness commitment to the coinbase output,
+    /// if any transaction in the block carries a non-empty witness. See
+    /// [`check_witness_commitment`] for the matching verification logic.
+    pub fn with_witness_commitment(mut self, with_witness_commitment: bool) -> Self {
+        self.with_witness_commitment = with_witness_commitment;
+        self
+    }
+
     pub fn build(self) -> DogecoinBlock {
-        let txdata = if self.transactions.is_empty() {
+        let mut txdata = if self.transactions.is_empty() {
             // Create a default coinbase transaction.
             vec![TransactionBuilder::new().build()]
         } else {
@@ -119,6 +255,10 @@ impl BlockBuilder {
             return DogecoinBlock { header, txdata };
         }
 
+        if self.with_witness_commitment && has_witness(&txdata) {
+            add_witness_commitment(&mut txdata, WITNESS_RESERVED_VALUE);
+        }
+
         let merkle_root = bitcoin::merkle_tree::calculate_root(
             txdata
                 .iter()
@@ -132,7 +272,12 @@ impl BlockBuilder {
             None => HeaderBuilder::genesis(merkle_root),
             Some(prev_header) => HeaderBuilder::new()
                 .with_prev_header(prev_header)
-                .with_merkle_root(merkle_root),
+                .with_merkle_root(merkle_root)
+                .with_time_offset(self.time_offset),
+        };
+        let header_builder = match self.difficulty_adjustment_headers {
+            Some(prev_headers) => header_builder.with_difficulty_adjustment(&prev_headers),
+            None => header_builder,
         };
 
         if self.with_auxpow {
@@ -158,11 +303,245 @@ impl BlockBuilder {
     }
 }
 
+/// Conservative weight of a `CHECKMULTISIG`/`CHECKMULTISIGVERIFY`, matching
+/// Bitcoin Core's non-accurate legacy sigop counting mode, which assumes the
+/// maximum possible number of public keys since the actual number isn't
+/// knowable without executing the preceding pushes.
+const MAX_PUBKEYS_PER_MULTISIG: u64 = 20;
+
+/// Conservatively counts the legacy sigops in a single script: `CHECKSIG`
+/// and `CHECKSIGVERIFY` count as 1, `CHECKMULTISIG` and
+/// `CHECKMULTISIGVERIFY` count as [`MAX_PUBKEYS_PER_MULTISIG`].
+fn count_sigops(script: &Script) -> u64 {
+    script
+        .instructions()
+        .filter_map(|instr| match instr {
+            Ok(Instruction::Op(op)) => Some(op),
+            _ => None,
+        })
+        .map(|op| match op {
+            bitcoin::opcodes::all::OP_CHECKSIG | bitcoin::opcodes::all::OP_CHECKSIGVERIFY => 1,
+            bitcoin::opcodes::all::OP_CHECKMULTISIG
+            | bitcoin::opcodes::all::OP_CHECKMULTISIGVERIFY => MAX_PUBKEYS_PER_MULTISIG,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Conservatively counts a transaction's legacy sigops across every input's
+/// `script_sig` and every output's `script_pubkey`.
+fn transaction_sigops(tx: &Transaction) -> u64 {
+    tx.input
+        .iter()
+        .map(|input| count_sigops(&input.script_sig))
+        .chain(tx.output.iter().map(|out| count_sigops(&out.script_pubkey)))
+        .sum()
+}
+
+/// Assembles a block from a pool of candidate transactions the way a
+/// miner's `getblocktemplate` would, instead of requiring the caller to
+/// hand-pick which transactions go in. Candidates are ordered by
+/// fee-per-byte and greedily included while respecting a weight and sigop
+/// budget; a candidate whose inputs aren't satisfied by a previously
+/// included transaction or a supplied UTXO is skipped. The coinbase pays
+/// the configured subsidy plus the fees of everything actually included.
+///
+/// Transaction selection and fee accounting happen here; the rest of block
+/// assembly (merkle root, header, optional AuxPow) is delegated to
+/// [`BlockBuilder`].
+pub struct BlockTemplateBuilder {
+    prev_header: Option<PureHeader>,
+    difficulty_adjustment_headers: Option<Vec<PureHeader>>,
+    candidates: Vec<(Transaction, u64)>,
+    utxos: HashMap<OutPoint, TxOut>,
+    weight_limit: Weight,
+    sigop_limit: u64,
+    subsidy: Amount,
+    coinbase_address: Address,
+}
+
+impl BlockTemplateBuilder {
+    /// Dogecoin's consensus block weight limit.
+    /// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/consensus/consensus.h#L10>
+    pub const DEFAULT_WEIGHT_LIMIT: Weight = Weight::from_wu(4_000_000);
+
+    /// Bitcoin's legacy (pre-segwit) block sigop limit, which Dogecoin still
+    /// follows since it predates the segwit soft fork.
+    pub const DEFAULT_SIGOP_LIMIT: u64 = 20_000;
+
+    pub fn new() -> Self {
+        Self {
+            prev_header: None,
+            difficulty_adjustment_headers: None,
+            candidates: vec![],
+            utxos: HashMap::new(),
+            weight_limit: Self::DEFAULT_WEIGHT_LIMIT,
+            sigop_limit: Self::DEFAULT_SIGOP_LIMIT,
+            subsidy: Amount::from_int_btc(10_000),
+            coinbase_address: random_p2pkh_address(Network::Regtest),
+        }
+    }
+
+    pub fn with_prev_header(mut self, prev_header: PureHeader) -> Self {
+        self.prev_header = Some(prev_header);
+        self
+    }
+
+    /// See [`BlockBuilder::with_difficulty_adjustment`].
+    pub fn with_difficulty_adjustment(mut self, prev_headers: &[PureHeader]) -> Self {
+        self.difficulty_adjustment_headers = Some(prev_headers.to_vec());
+        self
+    }
+
+    /// Adds a transaction to the candidate pool, along with the fee (in
+    /// satoshis) it pays.
+    pub fn with_candidate(mut self, transaction: Transaction, fee_sat: u64) -> Self {
+        self.candidates.push((transaction, fee_sat));
+        self
+    }
+
+    /// Makes an output spendable by a candidate transaction without it
+    /// having been produced by another candidate in this template.
+    pub fn with_utxo(mut self, outpoint: OutPoint, txout: TxOut) -> Self {
+        self.utxos.insert(outpoint, txout);
+        self
+    }
+
+    pub fn with_weight_limit(mut self, weight_limit: Weight) -> Self {
+        self.weight_limit = weight_limit;
+        self
+    }
+
+    pub fn with_sigop_limit(mut self, sigop_limit: u64) -> Self {
+        self.sigop_limit = sigop_limit;
+        self
+    }
+
+    pub fn with_subsidy(mut self, subsidy: Amount) -> Self {
+        self.subsidy = subsidy;
+        self
+    }
+
+    pub fn with_coinbase_address(mut self, coinbase_address: Address) -> Self {
+        self.coinbase_address = coinbase_address;
+        self
+    }
+
+    pub fn build(self) -> DogecoinBlock {
+        let mut candidates = self.candidates;
+        candidates.sort_by(|(tx_a, fee_a), (tx_b, fee_b)| {
+            let fee_rate = |tx: &Transaction, fee: u64| {
+                fee as f64 / bitcoin::consensus::encode::serialize(tx).len() as f64
+            };
+            fee_rate(tx_b, *fee_b)
+                .partial_cmp(&fee_rate(tx_a, *fee_a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut available = self.utxos;
+        let mut included = vec![];
+        let mut total_fee = 0u64;
+        let mut total_weight_wu = 0u64;
+        let mut total_sigops = 0u64;
+
+        for (transaction, fee_sat) in candidates {
+            let inputs_satisfied = transaction
+                .input
+                .iter()
+                .all(|input| available.contains_key(&input.previous_output));
+            if !inputs_satisfied {
+                continue;
+            }
+
+            let tx_weight_wu = transaction.weight().to_wu();
+            let tx_sigops = transaction_sigops(&transaction);
+            if total_weight_wu + tx_weight_wu > self.weight_limit.to_wu()
+                || total_sigops + tx_sigops > self.sigop_limit
+            {
+                continue;
+            }
+
+            total_weight_wu += tx_weight_wu;
+            total_sigops += tx_sigops;
+            total_fee += fee_sat;
+
+            let txid = transaction.compute_txid();
+            available.extend(
+                transaction
+                    .output
+                    .iter()
+                    .enumerate()
+                    .map(|(vout, out)| (OutPoint::new(txid, vout as u32), out.clone())),
+            );
+
+            included.push(transaction);
+        }
+
+        let coinbase = TransactionBuilder::new()
+            .with_output(&self.coinbase_address, self.subsidy.to_sat() + total_fee)
+            .build();
+
+        let mut block_builder = BlockBuilder::new().with_transaction(coinbase);
+        for transaction in included {
+            block_builder = block_builder.with_transaction(transaction);
+        }
+        if let Some(prev_header) = self.prev_header {
+            block_builder = block_builder.with_prev_header(prev_header);
+        }
+        if let Some(prev_headers) = self.difficulty_adjustment_headers {
+            block_builder = block_builder.with_difficulty_adjustment(&prev_headers);
+        }
+
+        block_builder.build()
+    }
+}
+
+/// One block's worth of target spacing under the DigiShield retarget
+/// [`HeaderBuilder::with_difficulty_adjustment`] simulates, in seconds.
+const DIGISHIELD_RETARGET_TIMESPAN: i64 = 60;
+
+/// Computes the `bits` for the header that follows `prev_headers` at
+/// `this_block_time`, applying Dogecoin's per-block DigiShield difficulty
+/// adjustment: the actual time taken for the previous block is damped by a
+/// factor of 8 and clamped to `[75%, 150%]` of the target spacing, then the
+/// previous target is scaled by that adjustment.
+///
+/// Mirrors Testnet/Regtest's minimum-difficulty-block exception first: if
+/// `this_block_time` is more than 2x the target spacing ahead of the
+/// previous block, the allowed difficulty resets to `pow_limit`
+/// (`Target::MAX_ATTAINABLE_REGTEST`) for this block, matching
+/// `DigishieldRetarget::next_target` in the validation crate, so chains
+/// built with large gaps (e.g. to exercise that rule) still validate.
+fn digishield_next_bits(prev_headers: &[PureHeader], this_block_time: u32) -> CompactTarget {
+    let prev_header = prev_headers
+        .last()
+        .expect("with_difficulty_adjustment requires at least one previous header");
+
+    let actual_timespan = this_block_time as i64 - prev_header.time as i64;
+    if actual_timespan > DIGISHIELD_RETARGET_TIMESPAN * 2 {
+        return Target::MAX_ATTAINABLE_REGTEST.to_compact_lossy();
+    }
+
+    let adjusted_timespan = (DIGISHIELD_RETARGET_TIMESPAN
+        + (actual_timespan - DIGISHIELD_RETARGET_TIMESPAN) / 8)
+        .clamp(
+            DIGISHIELD_RETARGET_TIMESPAN - DIGISHIELD_RETARGET_TIMESPAN / 4,
+            DIGISHIELD_RETARGET_TIMESPAN + DIGISHIELD_RETARGET_TIMESPAN / 2,
+        );
+
+    let old_target = Target::from_compact(prev_header.bits);
+    let new_target = (old_target * adjusted_timespan as u32) / DIGISHIELD_RETARGET_TIMESPAN as u32;
+
+    new_target.min(Target::MAX_ATTAINABLE_REGTEST).to_compact_lossy()
+}
+
 pub struct HeaderBuilder {
     version: i32,
     prev_header: Option<PureHeader>,
     merkle_root: TxMerkleNode,
     with_valid_pow: bool,
+    difficulty_adjustment_headers: Option<Vec<PureHeader>>,
+    time_offset: u32,
 }
 
 impl HeaderBuilder {
@@ -172,6 +551,8 @@ impl HeaderBuilder {
             prev_header: None,
             merkle_root: TxMerkleNode::all_zeros(),
             with_valid_pow: true,
+            difficulty_adjustment_headers: None,
+            time_offset: 60,
         }
     }
 
@@ -181,6 +562,8 @@ impl HeaderBuilder {
             prev_header: None,
             merkle_root,
             with_valid_pow: true,
+            difficulty_adjustment_headers: None,
+            time_offset: 60,
         }
     }
 
@@ -189,6 +572,16 @@ impl HeaderBuilder {
         self
     }
 
+    /// Computes `bits` using Dogecoin's per-block DigiShield retarget
+    /// (see [`digishield_next_bits`]) instead of copying it from the
+    /// previous header, so chains built with this mode exercise retarget
+    /// logic instead of sitting at a static difficulty. `prev_headers` is
+    /// the chain built so far; only its last header is consulted.
+    pub fn with_difficulty_adjustment(mut self, prev_headers: &[PureHeader]) -> Self {
+        self.difficulty_adjustment_headers = Some(prev_headers.to_vec());
+        self
+    }
+
     pub fn with_merkle_root(mut self, merkle_root: TxMerkleNode) -> Self {
         self.merkle_root = merkle_root;
         self
@@ -218,14 +611,24 @@ impl HeaderBuilder {
         self
     }
 
+    /// Sets the gap, in seconds, between this header's timestamp and its
+    /// parent's, defaulting to 60. A gap exceeding 2x the network's target
+    /// spacing exercises the minimum-difficulty-block reset on Testnet and
+    /// Regtest.
+    pub fn with_time_offset(mut self, time_offset: u32) -> Self {
+        self.time_offset = time_offset;
+        self
+    }
+
     pub fn build(self) -> PureHeader {
         let time = match &self.prev_header {
-            Some(header) => header.time + 60,
+            Some(header) => header.time + self.time_offset,
             None => 0,
         };
-        let bits = match &self.prev_header {
-            Some(header) => header.bits,
-            None => Target::MAX_ATTAINABLE_REGTEST.to_compact_lossy(),
+        let bits = match (&self.prev_header, &self.difficulty_adjustment_headers) {
+            (Some(_), Some(prev_headers)) => digishield_next_bits(prev_headers, time),
+            (Some(header), None) => header.bits,
+            (None, _) => Target::MAX_ATTAINABLE_REGTEST.to_compact_lossy(),
         };
 
         let mut header = PureHeader {
@@ -245,6 +648,38 @@ impl HeaderBuilder {
     }
 }
 
+/// Computes the standard Bitcoin merkle branch (authentication path) for the
+/// transaction at `index`: siblings are paired bottom-up in array order,
+/// duplicating the last node whenever a row has an odd length, and the
+/// sibling actually consumed at each level is recorded into the branch.
+fn compute_merkle_branch(txids: &[[u8; 32]], index: usize) -> (Vec<TxMerkleNode>, TxMerkleNode) {
+    let mut branch = Vec::new();
+    let mut index = index;
+    let mut level = txids.to_vec();
+
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(*level.last().unwrap());
+        }
+
+        branch.push(TxMerkleNode::from_byte_array(level[index ^ 1]));
+
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let mut data = Vec::with_capacity(64);
+                data.extend_from_slice(&pair[0]);
+                data.extend_from_slice(&pair[1]);
+                *sha256d::Hash::hash(&data).as_byte_array()
+            })
+            .collect();
+
+        index /= 2;
+    }
+
+    (branch, TxMerkleNode::from_byte_array(level[0]))
+}
+
 pub struct AuxPowBuilder {
     aux_block_hash: BlockHash,
     merkle_height: usize,
@@ -253,6 +688,7 @@ pub struct AuxPowBuilder {
     parent_chain_id: i32,
     base_version: i32,
     with_valid_pow: bool,
+    parent_transactions: Option<Vec<Transaction>>,
 }
 
 impl AuxPowBuilder {
@@ -265,6 +701,7 @@ impl AuxPowBuilder {
             parent_chain_id: DUMMY_CHAIN_ID,
             base_version: BASE_VERSION,
             with_valid_pow: true,
+            parent_transactions: None,
         }
     }
 
@@ -273,6 +710,26 @@ impl AuxPowBuilder {
         self
     }
 
+    /// Sets the chain id embedded in the parent block header's version
+    /// field, defaulting to `DUMMY_CHAIN_ID` -- distinct from
+    /// [`DOGECOIN_CHAIN_ID`] so callers exercising the "parent has our
+    /// chain id" rejection can pass `DOGECOIN_CHAIN_ID` here directly.
+    pub fn with_parent_chain_id(mut self, chain_id: i32) -> Self {
+        self.parent_chain_id = chain_id;
+        self
+    }
+
+    /// Embeds the merged-mining coinbase at index 0 of a parent `txdata`
+    /// that also includes `transactions`, instead of the degenerate
+    /// single-tx parent block. `coinbase_branch`/`coinbase_index` are
+    /// derived from the real merkle authentication path so the parent
+    /// header's `merkle_root` matches a block that the canister can verify
+    /// the coinbase inclusion proof against.
+    pub fn with_parent_transactions(mut self, transactions: Vec<Transaction>) -> Self {
+        self.parent_transactions = Some(transactions);
+        self
+    }
+
     pub fn build(self) -> AuxPow {
         let expected_index =
             AuxPow::get_expected_index(self.merkle_nonce, self.chain_id, self.merkle_height);
@@ -296,12 +753,27 @@ impl AuxPowBuilder {
             .with_coinbase_script(ScriptBuf::from_bytes(script_data))
             .build();
 
+        let (coinbase_branch, coinbase_index, parent_merkle_root) =
+            match self.parent_transactions {
+                Some(transactions) => {
+                    let txids: Vec<[u8; 32]> = std::iter::once(coinbase_tx.compute_txid())
+                        .chain(transactions.iter().map(|tx| tx.compute_txid()))
+                        .map(|txid| txid.to_byte_array())
+                        .collect();
+                    let (branch, root) = compute_merkle_branch(&txids, 0);
+                    (branch, 0, root)
+                }
+                None => (
+                    vec![], // Empty since coinbase is the only tx
+                    0,
+                    TxMerkleNode::from_byte_array(coinbase_tx.compute_txid().to_byte_array()),
+                ),
+            };
+
         let mut parent_block_header = HeaderBuilder::new()
             .with_version(self.base_version)
             .with_chain_id(self.parent_chain_id)
-            .with_merkle_root(TxMerkleNode::from_byte_array(
-                coinbase_tx.compute_txid().to_byte_array(),
-            ))
+            .with_merkle_root(parent_merkle_root)
             .build();
 
         mine_header_to_target(&mut parent_block_header, self.with_valid_pow);
@@ -309,8 +781,8 @@ impl AuxPowBuilder {
         AuxPow {
             coinbase_tx,
             parent_hash: BlockHash::all_zeros(),
-            coinbase_branch: vec![], // Empty since coinbase is the only tx
-            coinbase_index: 0,
+            coinbase_branch,
+            coinbase_index,
             blockchain_branch,
             blockchain_index: expected_index,
             parent_block_header,
@@ -428,12 +900,15 @@ pub fn build_regtest_chain(
         .unwrap()
         .assume_checked();
     let mut blocks = vec![genesis_block.clone()];
+    let mut headers = vec![*genesis_block.header()];
     let mut prev_block: Block = genesis_block;
     let mut value = 1;
 
     // Since we start with a genesis block, we need `num_blocks - 1` additional blocks.
     for i in 0..num_blocks - 1 {
-        let mut block_builder = BlockBuilder::new().with_prev_header(*prev_block.header());
+        let mut block_builder = BlockBuilder::new()
+            .with_prev_header(*prev_block.header())
+            .with_difficulty_adjustment(&headers);
 
         if with_auxpow && i >= dogecoin_network.params().auxpow_height {
             block_builder = block_builder.with_auxpow(true);
@@ -456,6 +931,107 @@ pub fn build_regtest_chain(
         }
 
         let block = Block::new(block_builder.build());
+        headers.push(*block.header());
+        blocks.push(block.clone());
+        prev_block = block;
+    }
+
+    blocks
+}
+
+/// As [`build_regtest_chain`], but spaces each block `spacing_secs` apart
+/// instead of the default 60s. Used to exercise the minimum-difficulty-block
+/// reset on Testnet/Regtest, which kicks in once a gap exceeds 2x the
+/// network's target spacing.
+pub fn build_regtest_chain_with_spacing(
+    num_blocks: u32,
+    num_transactions_per_block: u32,
+    spacing_secs: u32,
+) -> Vec<Block> {
+    let dogecoin_network = Network::Regtest;
+    let genesis_block = Block::new(genesis_block(dogecoin_network));
+
+    let address = Address::from_str("mhXcJVuNA48bZsrKq4t21jx1neSqyceqTM")
+        .unwrap()
+        .assume_checked();
+    let mut blocks = vec![genesis_block.clone()];
+    let mut headers = vec![*genesis_block.header()];
+    let mut prev_block: Block = genesis_block;
+    let mut value = 1;
+
+    for _ in 0..num_blocks - 1 {
+        let mut block_builder = BlockBuilder::new()
+            .with_prev_header(*prev_block.header())
+            .with_difficulty_adjustment(&headers)
+            .with_time_offset(spacing_secs);
+
+        let mut transactions = vec![];
+        for _ in 0..num_transactions_per_block {
+            transactions.push(
+                TransactionBuilder::new()
+                    .with_output(&address, value)
+                    .build(),
+            );
+            value += 1;
+        }
+
+        for transaction in transactions.iter() {
+            block_builder = block_builder.with_transaction(transaction.clone());
+        }
+
+        let block = Block::new(block_builder.build());
+        headers.push(*block.header());
+        blocks.push(block.clone());
+        prev_block = block;
+    }
+
+    blocks
+}
+
+/// Mines a divergent branch of `num_blocks` off `base`, treating the header
+/// at `fork_point` as the common ancestor. Coinbase outputs pay a different
+/// static address than [`build_regtest_chain`]'s, so forked block hashes
+/// never collide with the base chain, letting reorg tests build a
+/// side-chain that's heavier (more accumulated work) without necessarily
+/// being taller.
+pub fn build_regtest_fork(
+    base: &[Block],
+    fork_point: usize,
+    num_blocks: u32,
+    with_auxpow: bool,
+) -> Vec<Block> {
+    let dogecoin_network = Network::Regtest;
+
+    // Static address distinct from `build_regtest_chain`'s, so the forked
+    // branch's coinbase outputs (and thus block hashes) diverge from it.
+    let address = Address::from_str("n2KyQRUpCKxVQtzoQTMXKUaEKJW4wqS7XH")
+        .unwrap()
+        .assume_checked();
+
+    let mut headers: Vec<PureHeader> = base[..=fork_point].iter().map(|b| *b.header()).collect();
+    let mut prev_block = base[fork_point].clone();
+    let mut blocks = vec![];
+    let mut value = 1;
+
+    for i in 0..num_blocks {
+        let mut block_builder = BlockBuilder::new()
+            .with_prev_header(*prev_block.header())
+            .with_difficulty_adjustment(&headers)
+            .with_transaction(
+                TransactionBuilder::new()
+                    .with_output(&address, value)
+                    .build(),
+            );
+        // Vary the value of the coinbase output to ensure unique outpoints
+        // and, combined with the distinct address, unique block hashes.
+        value += 1;
+
+        if with_auxpow && fork_point as u32 + i >= dogecoin_network.params().auxpow_height {
+            block_builder = block_builder.with_auxpow(true);
+        }
+
+        let block = Block::new(block_builder.build());
+        headers.push(*block.header());
         blocks.push(block.clone());
         prev_block = block;
     }
@@ -0,0 +1,305 @@
+#[cfg(feature = "bitcoinconsensus")]
+pub mod consensus;
+pub mod import_export;
+#[cfg(test)]
+mod tests;
+
+use crate::header::doge::{DigishieldRetarget, DogecoinHeaderValidator};
+use crate::header::retarget::DifficultyRetarget;
+use crate::header::{AuxPowHeaderValidator, HeaderStore, HeaderValidator};
+use crate::{BlockHeight, ValidateHeaderError};
+use bitcoin::block::Header as PureHeader;
+use bitcoin::dogecoin::{Block, Network};
+use bitcoin::{BlockHash, CompactTarget, Target};
+use std::collections::{BTreeMap, HashSet};
+use std::time::Duration;
+
+#[cfg(feature = "bitcoinconsensus")]
+pub use consensus::{verify_transactions, ScriptError, SpentOutput};
+
+/// An error thrown when trying to validate a block.
+#[derive(Debug, PartialEq)]
+pub enum ValidateBlockError {
+    /// Used when the block's header fails validation.
+    InvalidBlockHeader(ValidateHeaderError),
+    /// Used when a block has no transactions at all (it is missing the
+    /// coinbase).
+    NoTransactions,
+    /// Used when the first transaction isn't a valid coinbase, or a
+    /// non-first transaction looks like one.
+    InvalidCoinbase,
+    /// Used when a block contains the same transaction more than once, which
+    /// would otherwise let a forged merkle root collide with a legitimate
+    /// one (CVE-2012-2459).
+    DuplicateTransactions,
+    /// Used when the merkle root committed to in the header doesn't match
+    /// the one computed from the block's transactions.
+    BadMerkleRoot,
+    /// Used when a block's weight exceeds the network's consensus limit.
+    BlockWeightExceedsLimit,
+    /// Used when an input spends a coinbase output that hasn't reached
+    /// [`COINBASE_MATURITY`] confirmations yet.
+    ImmatureCoinbaseSpend,
+    /// Used when a transaction's outputs are worth more than the outputs its
+    /// inputs spend.
+    NegativeFee,
+    /// Used when the coinbase output value exceeds the block subsidy plus
+    /// the fees collected from the other transactions.
+    SubsidyTooHigh,
+    /// Used when a transaction input's script fails `bitcoinconsensus`
+    /// verification against the output it spends.
+    #[cfg(feature = "bitcoinconsensus")]
+    InvalidScript(consensus::ScriptError),
+    /// Used when a block at or below the highest height in
+    /// [`BlockValidator`]'s checkpoint set has a hash pinned for its
+    /// height, but doesn't match it.
+    CheckpointMismatch {
+        height: BlockHeight,
+        expected: BlockHash,
+        got: BlockHash,
+    },
+}
+
+impl From<ValidateHeaderError> for ValidateBlockError {
+    fn from(err: ValidateHeaderError) -> Self {
+        ValidateBlockError::InvalidBlockHeader(err)
+    }
+}
+
+/// The number of confirmations a coinbase output must have before it can be
+/// spent.
+/// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/consensus/consensus.h#L13>
+pub const COINBASE_MATURITY: u32 = 100;
+
+/// Validates the parts of a block that don't depend on chain context: that
+/// it has a coinbase and only one, that it contains no duplicate
+/// transactions, and that the transactions committed to actually hash to
+/// the header's merkle root.
+pub(crate) fn validate_block(block: &Block) -> Result<(), ValidateBlockError> {
+    let Some(coinbase) = block.txdata.first() else {
+        return Err(ValidateBlockError::NoTransactions);
+    };
+
+    if !coinbase.is_coinbase() || block.txdata[1..].iter().any(|tx| tx.is_coinbase()) {
+        return Err(ValidateBlockError::InvalidCoinbase);
+    }
+
+    // CVE-2012-2459: a block that repeats one of its transactions can be
+    // crafted so that its merkle tree collides with that of the original,
+    // unduplicated block. Reject duplicates outright instead of trusting
+    // `check_merkle_root` to catch them.
+    let mut seen_txids = HashSet::with_capacity(block.txdata.len());
+    for tx in &block.txdata {
+        if !seen_txids.insert(tx.compute_txid()) {
+            return Err(ValidateBlockError::DuplicateTransactions);
+        }
+    }
+
+    if !block.check_merkle_root() {
+        return Err(ValidateBlockError::BadMerkleRoot);
+    }
+
+    Ok(())
+}
+
+/// Validates that a block is well-formed and extends a known, valid chain.
+///
+/// This only checks the things that can be verified from the block and its
+/// header store: the header itself, the coinbase shape, and the absence of
+/// duplicate/merkle-colliding transactions. It does not resolve or verify
+/// the scripts of spent inputs; for that, enable the `bitcoinconsensus`
+/// feature and use [`consensus::verify_transactions`] with the `TxOut`s
+/// resolved from the UTXO set.
+pub struct BlockValidator<T, R = DigishieldRetarget> {
+    header_validator: DogecoinHeaderValidator<T, R>,
+    /// Height -> expected block hash, for heights this validator trusts
+    /// without recomputing the block's merkle root or re-checking its
+    /// coinbase. Empty unless built via [`Self::with_checkpoints`].
+    checkpoints: BTreeMap<BlockHeight, BlockHash>,
+}
+
+impl<T: HeaderStore> BlockValidator<T> {
+    pub fn new(store: T, network: Network) -> Self {
+        Self::with_checkpoints(store, network, BTreeMap::new())
+    }
+
+    /// As [`Self::new`], but pins `checkpoints` (height -> expected block
+    /// hash) as a trusted-history fast path: a block at or below the
+    /// highest checkpointed height skips the merkle-root recomputation and
+    /// per-transaction coinbase checks [`Self::validate_block`] otherwise
+    /// performs, and -- if a checkpoint is pinned at that exact height --
+    /// must match it exactly or validation fails with
+    /// [`ValidateBlockError::CheckpointMismatch`]. The header itself is
+    /// still fully validated either way. Above the highest checkpointed
+    /// height, behavior is unchanged from [`Self::new`].
+    ///
+    /// This hardens against a deep reorg forging historical chain and cuts
+    /// the CPU cost of initial sync, the same way node implementations
+    /// ship a compiled-in list of known-good block hashes. This crate has
+    /// no network-specific checkpoint data compiled in -- same as
+    /// [`crate::CheckpointTable`], which is empty by default for the same
+    /// reason -- so a deployment that wants the fast path supplies its own
+    /// trusted list.
+    pub fn with_checkpoints(
+        store: T,
+        network: Network,
+        checkpoints: BTreeMap<BlockHeight, BlockHash>,
+    ) -> Self {
+        Self {
+            header_validator: DogecoinHeaderValidator::new(store, network),
+            checkpoints,
+        }
+    }
+}
+
+impl<T: HeaderStore, R: DifficultyRetarget<DogecoinHeaderValidator<T, R>>> BlockValidator<T, R> {
+    /// As [`Self::with_checkpoints`], but retargets difficulty using a
+    /// custom strategy instead of Dogecoin's own
+    /// [`DigishieldRetarget`] -- the same strategy surface
+    /// [`DogecoinHeaderValidator::with_retarget`] exposes, plumbed through
+    /// so a caller validating full blocks for a merge-mined or forked
+    /// chain (e.g. with
+    /// [`SlidingWindowRetarget`](crate::header::retarget::SlidingWindowRetarget))
+    /// isn't stuck with the Dogecoin mainnet rule.
+    pub fn with_retarget(
+        store: T,
+        network: Network,
+        retarget: R,
+        checkpoints: BTreeMap<BlockHeight, BlockHash>,
+    ) -> Self {
+        Self {
+            header_validator: DogecoinHeaderValidator::with_retarget(store, network, retarget),
+            checkpoints,
+        }
+    }
+
+    /// Validates a block's header first, then the block itself. The header
+    /// is always checked first: a block with both an invalid header and an
+    /// invalid body reports the header error.
+    ///
+    /// If this validator has a checkpoint at or above the block's height
+    /// (see [`Self::with_checkpoints`]), the merkle-root and coinbase
+    /// checks are skipped in favor of an exact hash match against any
+    /// checkpoint pinned at that specific height.
+    pub fn validate_block(
+        &self,
+        block: &Block,
+        current_time: Duration,
+    ) -> Result<(), ValidateBlockError> {
+        self.header_validator
+            .validate_auxpow_header(&block.header, current_time)?;
+
+        let height = self.header_validator.store().height() + 1;
+        let highest_checkpoint = self.checkpoints.keys().next_back().copied();
+
+        if highest_checkpoint.is_some_and(|highest| height <= highest) {
+            if let Some(&expected) = self.checkpoints.get(&height) {
+                let got = block.block_hash();
+                if got != expected {
+                    return Err(ValidateBlockError::CheckpointMismatch {
+                        height,
+                        expected,
+                        got,
+                    });
+                }
+            }
+            return Ok(());
+        }
+
+        validate_block(block)
+    }
+}
+
+/// Delegates to the inner [`DogecoinHeaderValidator`], the same decorator
+/// pattern [`BufferingHeaderValidator`](crate::header::buffer::BufferingHeaderValidator)
+/// and
+/// [`CheckpointedHeaderValidator`](crate::header::checkpoints::CheckpointedHeaderValidator)
+/// use. This is what actually wires fork-choice
+/// ([`evaluate_fork_choice`](HeaderValidator::evaluate_fork_choice),
+/// [`compare_chains`](HeaderValidator::compare_chains)) and cumulative-work
+/// queries ([`HeaderStore::total_work`] via [`store`](Self::store)) into
+/// the validator a caller actually holds: without this impl, a
+/// [`BlockValidator`] has no way to reach those methods at all, even though
+/// [`DogecoinHeaderValidator`] implements them.
+impl<T: HeaderStore, R: DifficultyRetarget<DogecoinHeaderValidator<T, R>>> HeaderValidator
+    for BlockValidator<T, R>
+{
+    type Network = Network;
+    type Store = T;
+
+    fn network(&self) -> &Self::Network {
+        self.header_validator.network()
+    }
+
+    fn store(&self) -> &Self::Store {
+        self.header_validator.store()
+    }
+
+    fn store_mut(&mut self) -> &mut Self::Store {
+        self.header_validator.store_mut()
+    }
+
+    fn max_target(&self) -> Target {
+        self.header_validator.max_target()
+    }
+
+    fn no_pow_retargeting(&self) -> bool {
+        self.header_validator.no_pow_retargeting()
+    }
+
+    fn pow_limit_bits(&self) -> CompactTarget {
+        self.header_validator.pow_limit_bits()
+    }
+
+    fn pow_target_spacing(&self) -> Duration {
+        self.header_validator.pow_target_spacing()
+    }
+
+    fn difficulty_adjustment_interval(&self, height: u32) -> u32 {
+        self.header_validator.difficulty_adjustment_interval(height)
+    }
+
+    fn allow_min_difficulty_blocks(&self, height: u32) -> bool {
+        self.header_validator.allow_min_difficulty_blocks(height)
+    }
+
+    fn digishield_activation_height(&self) -> u32 {
+        self.header_validator.digishield_activation_height()
+    }
+
+    fn validate_header(
+        &self,
+        header: &PureHeader,
+        current_time: Duration,
+    ) -> Result<(), ValidateHeaderError> {
+        self.header_validator.validate_header(header, current_time)
+    }
+
+    fn get_next_target(
+        &self,
+        prev_header: &PureHeader,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        self.header_validator
+            .get_next_target(prev_header, prev_height, timestamp)
+    }
+
+    fn find_next_difficulty_in_chain(
+        &self,
+        prev_header: &PureHeader,
+        prev_height: BlockHeight,
+    ) -> CompactTarget {
+        self.header_validator
+            .find_next_difficulty_in_chain(prev_header, prev_height)
+    }
+
+    fn compute_next_difficulty(
+        &self,
+        prev_header: &PureHeader,
+        prev_height: BlockHeight,
+    ) -> CompactTarget {
+        self.header_validator
+            .compute_next_difficulty(prev_header, prev_height)
+    }
+}
@@ -0,0 +1,474 @@
+//! Full transaction/script validation, backed by `bitcoinconsensus`.
+//!
+//! [`BlockValidator`](crate::BlockValidator) only checks what can be
+//! verified from a block and its header store: the header, the coinbase
+//! shape, and the absence of duplicate/merkle-colliding transactions. The
+//! functions in this module go further, checking everything that requires
+//! the `TxOut`s an input actually spends: script validity, coinbase
+//! maturity, block weight, and fee/subsidy sanity. Callers are expected to
+//! resolve those `TxOut`s from their own UTXO set and supply them as
+//! [`SpentOutput`]s.
+
+use crate::block::COINBASE_MATURITY;
+use crate::header::doge::DIGISHIELD_ACTIVATION_HEIGHT;
+use crate::{BlockHeight, ValidateBlockError};
+use bitcoin::dogecoin::{Block, Network};
+use bitcoin::{Amount, TxOut, Weight};
+
+/// The maximum serialized weight of a block.
+/// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/consensus/consensus.h#L10>
+pub const MAX_BLOCK_WEIGHT: Weight = Weight::from_wu(4_000_000);
+
+/// An output resolved from the UTXO set for one of a block's transaction
+/// inputs, together with the metadata needed to check coinbase maturity.
+#[derive(Debug, Clone)]
+pub struct SpentOutput {
+    /// The output being spent.
+    pub txout: TxOut,
+    /// The height at which the spent output's containing transaction was
+    /// confirmed.
+    pub height: BlockHeight,
+    /// Whether the spent output's containing transaction was a coinbase.
+    pub is_coinbase: bool,
+}
+
+/// A script verification failure reported by `bitcoinconsensus`, identifying
+/// which input of which transaction in the block failed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ScriptError {
+    /// Index of the offending transaction within the block.
+    pub tx_index: usize,
+    /// Index of the offending input within that transaction.
+    pub input_index: usize,
+    /// The underlying `bitcoinconsensus` error, rendered to a string since
+    /// the upstream error type doesn't implement `PartialEq`.
+    pub reason: String,
+}
+
+/// Verifies everything [`validate_block`](super::validate_block) cannot:
+/// every input's script, coinbase maturity, block weight, and that the
+/// coinbase doesn't pay out more than the subsidy plus collected fees.
+///
+/// `spent_outputs[i][j]` must be the resolved output spent by
+/// `block.txdata[i].input[j]`; the coinbase entry (`spent_outputs[0]`) is
+/// ignored and may be empty. Callers should only invoke this after
+/// [`BlockValidator::validate_block`](crate::BlockValidator::validate_block)
+/// has already accepted the block's header and structure.
+pub fn verify_transactions(
+    block: &Block,
+    height: BlockHeight,
+    network: Network,
+    spent_outputs: &[Vec<SpentOutput>],
+) -> Result<(), ValidateBlockError> {
+    if block.weight() > MAX_BLOCK_WEIGHT {
+        return Err(ValidateBlockError::BlockWeightExceedsLimit);
+    }
+
+    let flags = script_verify_flags(height, network);
+    let mut total_fee = Amount::ZERO;
+
+    for (tx_index, (tx, spent)) in block.txdata.iter().zip(spent_outputs).enumerate().skip(1) {
+        let tx_bytes = bitcoin::consensus::encode::serialize(tx);
+        let mut input_sum = Amount::ZERO;
+
+        for (input_index, spent_output) in spent.iter().enumerate() {
+            if spent_output.is_coinbase
+                && height.saturating_sub(spent_output.height) < COINBASE_MATURITY
+            {
+                return Err(ValidateBlockError::ImmatureCoinbaseSpend);
+            }
+
+            input_sum = input_sum
+                .checked_add(spent_output.txout.value)
+                .ok_or(ValidateBlockError::SubsidyTooHigh)?;
+
+            bitcoinconsensus::verify_with_flags(
+                spent_output.txout.script_pubkey.as_bytes(),
+                spent_output.txout.value.to_sat(),
+                &tx_bytes,
+                input_index,
+                flags,
+            )
+            .map_err(|err| {
+                ValidateBlockError::InvalidScript(ScriptError {
+                    tx_index,
+                    input_index,
+                    reason: format!("{err:?}"),
+                })
+            })?;
+        }
+
+        let output_sum = tx
+            .output
+            .iter()
+            .try_fold(Amount::ZERO, |sum, out| sum.checked_add(out.value))
+            .ok_or(ValidateBlockError::SubsidyTooHigh)?;
+        let fee = input_sum
+            .checked_sub(output_sum)
+            .ok_or(ValidateBlockError::NegativeFee)?;
+        total_fee = total_fee
+            .checked_add(fee)
+            .ok_or(ValidateBlockError::SubsidyTooHigh)?;
+    }
+
+    // Before the Digishield hard fork, the subsidy had a randomized
+    // component seeded from the block hash that can't be reproduced from
+    // the block alone, so the sanity check below only applies from that
+    // height onward.
+    if height >= DIGISHIELD_ACTIVATION_HEIGHT {
+        let coinbase_out = block.txdata[0]
+            .output
+            .iter()
+            .try_fold(Amount::ZERO, |sum, out| sum.checked_add(out.value))
+            .ok_or(ValidateBlockError::SubsidyTooHigh)?;
+        if coinbase_out > digishield_block_subsidy(height) + total_fee {
+            return Err(ValidateBlockError::SubsidyTooHigh);
+        }
+    }
+
+    Ok(())
+}
+
+/// Selects the `bitcoinconsensus` script verification flags active at
+/// `height`.
+///
+/// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/validation.cpp#L2860>
+///
+/// Dogecoin's block/transaction model validated here predates the segwit
+/// and taproot soft forks, so `VERIFY_WITNESS`/`VERIFY_TAPROOT` aren't
+/// modeled; a BTC-path block validator would need to gate those by their
+/// own activation heights once this crate grows a BTC block type.
+fn script_verify_flags(height: BlockHeight, network: Network) -> u32 {
+    // P2SH (BIP16) has been enforced since before Dogecoin's genesis block.
+    let mut flags = bitcoinconsensus::VERIFY_P2SH;
+
+    let params = network.params();
+    if height >= params.bip66_height {
+        flags |= bitcoinconsensus::VERIFY_DERSIG;
+    }
+    if height >= params.bip65_height {
+        flags |= bitcoinconsensus::VERIFY_CHECKLOCKTIMEVERIFY;
+        flags |= bitcoinconsensus::VERIFY_CHECKSEQUENCEVERIFY;
+    }
+
+    flags
+}
+
+/// The Dogecoin per-block subsidy under the schedule activated at the
+/// Digishield hard fork: 500,000 DOGE halved once per full 100,000 blocks
+/// of height (so 250,000 DOGE at heights 100,000-199,999, which include the
+/// fork height), and fixed at 10,000 DOGE from height 600,000 onward.
+/// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/validation.cpp#L1100>
+fn digishield_block_subsidy(height: BlockHeight) -> Amount {
+    if height >= 600_000 {
+        return Amount::from_int_btc(10_000);
+    }
+
+    Amount::from_int_btc(500_000) / 2u64.pow(height / 100_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::absolute::LockTime;
+    use bitcoin::hashes::{sha256d, Hash};
+    use bitcoin::secp256k1::{Message, Secp256k1, SecretKey};
+    use bitcoin::transaction::Version;
+    use bitcoin::script::PushBytesBuf;
+    use bitcoin::{
+        BlockHash, OutPoint, PublicKey, Script, ScriptBuf, Sequence, Transaction, TxIn, Txid,
+        Witness,
+    };
+
+    const SIGHASH_ALL: u32 = 1;
+
+    fn header() -> bitcoin::dogecoin::Header {
+        bitcoin::dogecoin::Header {
+            pure_header: bitcoin::block::Header {
+                version: bitcoin::block::Version::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: bitcoin::TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: bitcoin::CompactTarget::from_consensus(0x1d00ffff),
+                nonce: 0,
+            },
+            aux_pow: None,
+        }
+    }
+
+    fn coinbase_tx() -> Transaction {
+        Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value: Amount::from_int_btc(10_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    /// Computes the legacy (pre-segwit) transaction signature hash for
+    /// `tx`'s input at `input_index` spending `script_pubkey` -- the exact
+    /// algorithm `bitcoinconsensus` checks a script's signature against, so
+    /// a signature produced this way is one it will actually accept.
+    fn legacy_sighash(tx: &Transaction, input_index: usize, script_pubkey: &Script) -> Message {
+        let mut unsigned = tx.clone();
+        for (i, input) in unsigned.input.iter_mut().enumerate() {
+            input.script_sig = if i == input_index {
+                script_pubkey.to_owned()
+            } else {
+                ScriptBuf::new()
+            };
+        }
+
+        let mut bytes = bitcoin::consensus::encode::serialize(&unsigned);
+        bytes.extend_from_slice(&SIGHASH_ALL.to_le_bytes());
+        let digest = sha256d::Hash::hash(&bytes);
+        Message::from_digest_slice(digest.as_byte_array()).expect("32 bytes is a valid message")
+    }
+
+    /// Builds a transaction with one input spending `script_pubkey` (a P2PK
+    /// output locked to `public_key`) with a real ECDSA signature over it,
+    /// and one output. The returned scriptSig is one `bitcoinconsensus`
+    /// actually verifies successfully, not just well-formed bytes.
+    fn signed_p2pk_spend(
+        secret_key: &SecretKey,
+        script_pubkey: &Script,
+        previous_output: OutPoint,
+        value: Amount,
+    ) -> Transaction {
+        let secp = Secp256k1::new();
+        let mut tx = Transaction {
+            version: Version::ONE,
+            lock_time: LockTime::ZERO,
+            input: vec![TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }],
+            output: vec![TxOut {
+                value,
+                script_pubkey: ScriptBuf::new(),
+            }],
+        };
+
+        let message = legacy_sighash(&tx, 0, script_pubkey);
+        let signature = secp.sign_ecdsa(&message, secret_key);
+        let mut sig_bytes = signature.serialize_der().to_vec();
+        sig_bytes.push(SIGHASH_ALL as u8);
+
+        tx.input[0].script_sig = Script::builder()
+            .push_slice(PushBytesBuf::try_from(sig_bytes).unwrap())
+            .into_script();
+
+        tx
+    }
+
+    fn p2pk_script_pubkey(public_key: &PublicKey) -> ScriptBuf {
+        Script::builder()
+            .push_key(public_key)
+            .push_opcode(bitcoin::opcodes::all::OP_CHECKSIG)
+            .into_script()
+    }
+
+    #[test]
+    fn verify_transactions_accepts_a_real_signed_p2pk_spend() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let script_pubkey = p2pk_script_pubkey(&public_key);
+
+        let spend = signed_p2pk_spend(
+            &secret_key,
+            &script_pubkey,
+            OutPoint::new(Txid::all_zeros(), 0),
+            Amount::from_int_btc(40_000),
+        );
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase_tx(), spend],
+        };
+        let spent_outputs = vec![
+            vec![],
+            vec![SpentOutput {
+                txout: TxOut {
+                    value: Amount::from_int_btc(50_000),
+                    script_pubkey,
+                },
+                height: 0,
+                is_coinbase: false,
+            }],
+        ];
+
+        assert_eq!(
+            verify_transactions(&block, 1, Network::Dogecoin, &spent_outputs),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn verify_transactions_rejects_an_invalid_script() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let wrong_key = SecretKey::from_slice(&[9u8; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let script_pubkey = p2pk_script_pubkey(&public_key);
+
+        // Sign with the wrong key, so the scriptSig doesn't satisfy
+        // `script_pubkey`'s `OP_CHECKSIG`.
+        let spend = signed_p2pk_spend(
+            &wrong_key,
+            &script_pubkey,
+            OutPoint::new(Txid::all_zeros(), 0),
+            Amount::from_int_btc(40_000),
+        );
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase_tx(), spend],
+        };
+        let spent_outputs = vec![
+            vec![],
+            vec![SpentOutput {
+                txout: TxOut {
+                    value: Amount::from_int_btc(50_000),
+                    script_pubkey,
+                },
+                height: 0,
+                is_coinbase: false,
+            }],
+        ];
+
+        match verify_transactions(&block, 1, Network::Dogecoin, &spent_outputs) {
+            Err(ValidateBlockError::InvalidScript(ScriptError {
+                tx_index,
+                input_index,
+                ..
+            })) => {
+                assert_eq!(tx_index, 1);
+                assert_eq!(input_index, 0);
+            }
+            other => panic!("expected InvalidScript, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn verify_transactions_rejects_an_immature_coinbase_spend() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let script_pubkey = p2pk_script_pubkey(&public_key);
+
+        let spend = signed_p2pk_spend(
+            &secret_key,
+            &script_pubkey,
+            OutPoint::new(Txid::all_zeros(), 0),
+            Amount::from_int_btc(40_000),
+        );
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase_tx(), spend],
+        };
+        let spent_outputs = vec![
+            vec![],
+            vec![SpentOutput {
+                txout: TxOut {
+                    value: Amount::from_int_btc(50_000),
+                    script_pubkey,
+                },
+                height: 50,
+                is_coinbase: true,
+            }],
+        ];
+
+        // Spent at height 100, confirmed at height 50: only 50
+        // confirmations, short of `COINBASE_MATURITY`.
+        assert_eq!(
+            verify_transactions(&block, 100, Network::Dogecoin, &spent_outputs),
+            Err(ValidateBlockError::ImmatureCoinbaseSpend)
+        );
+    }
+
+    #[test]
+    fn verify_transactions_rejects_a_negative_fee() {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let public_key = PublicKey::new(secret_key.public_key(&secp));
+        let script_pubkey = p2pk_script_pubkey(&public_key);
+
+        // Spends a 1 DOGE output but pays out 2 DOGE: the input sum can't
+        // cover the output sum.
+        let spend = signed_p2pk_spend(
+            &secret_key,
+            &script_pubkey,
+            OutPoint::new(Txid::all_zeros(), 0),
+            Amount::from_int_btc(2),
+        );
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase_tx(), spend],
+        };
+        let spent_outputs = vec![
+            vec![],
+            vec![SpentOutput {
+                txout: TxOut {
+                    value: Amount::from_int_btc(1),
+                    script_pubkey,
+                },
+                height: 0,
+                is_coinbase: false,
+            }],
+        ];
+
+        assert_eq!(
+            verify_transactions(&block, 1, Network::Dogecoin, &spent_outputs),
+            Err(ValidateBlockError::NegativeFee)
+        );
+    }
+
+    #[test]
+    fn verify_transactions_rejects_a_coinbase_that_exceeds_subsidy_plus_fees() {
+        let height = 600_000; // Fixed 10,000 DOGE subsidy from here on.
+        let mut coinbase = coinbase_tx();
+        coinbase.output[0].value = Amount::from_int_btc(10_001);
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase],
+        };
+
+        assert_eq!(
+            verify_transactions(&block, height, Network::Dogecoin, &[vec![]]),
+            Err(ValidateBlockError::SubsidyTooHigh)
+        );
+    }
+
+    #[test]
+    fn verify_transactions_rejects_a_block_over_the_weight_limit() {
+        // A coinbase with an output script large enough alone to push the
+        // serialized (non-witness, so weight = 4x size) block past
+        // `MAX_BLOCK_WEIGHT`.
+        let mut coinbase = coinbase_tx();
+        coinbase.output[0].script_pubkey = ScriptBuf::from_bytes(vec![0u8; 1_100_000]);
+
+        let block = Block {
+            header: header(),
+            txdata: vec![coinbase],
+        };
+
+        assert_eq!(
+            verify_transactions(&block, 0, Network::Dogecoin, &[vec![]]),
+            Err(ValidateBlockError::BlockWeightExceedsLimit)
+        );
+    }
+}
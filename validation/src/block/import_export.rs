@@ -0,0 +1,169 @@
+//! Bulk-ingests blocks from the standard `bootstrap.dat` flat-file layout --
+//! a stream of `(4-byte network magic, 4-byte little-endian length, that
+//! many bytes of a consensus-encoded block)` records -- letting an operator
+//! sync or re-validate a node from a local file instead of pulling blocks
+//! one RPC at a time.
+//!
+//! Mirrors [`crate::header::import_export`]'s streaming-import shape: each
+//! record is read and validated in turn and only added to the store on
+//! success, but reads the reader incrementally rather than buffering whole
+//! rows, since blocks (unlike header rows) can be large.
+
+use crate::block::{BlockValidator, ValidateBlockError};
+use crate::header::{AuxPowHeaderValidator, HeaderStore, HeaderValidator};
+use crate::BlockHeight;
+use bitcoin::consensus::{encode, Decodable};
+use bitcoin::dogecoin::Block;
+use bitcoin::p2p::Magic;
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+/// A record that couldn't be read, didn't deserialize into a [`Block`], or
+/// failed validation.
+///
+/// Every variant carries the byte offset the record started at (its magic,
+/// not its payload), so a caller can reopen the file, skip past the
+/// offending record, and resume importing from the next one.
+#[derive(Debug)]
+pub enum BootstrapImportError {
+    /// The reader failed before a full record (magic, length and block
+    /// bytes) could be read.
+    Io { offset: u64, source: std::io::Error },
+    /// The record's magic doesn't match the network the validator was built
+    /// for -- e.g. a mainnet `bootstrap.dat` fed to a testnet validator, or
+    /// a corrupt record that desynced the reader from record boundaries.
+    UnexpectedMagic {
+        offset: u64,
+        expected: Magic,
+        found: Magic,
+    },
+    /// The record's declared length of bytes didn't deserialize into a
+    /// [`Block`].
+    BlockDecode { offset: u64, source: encode::Error },
+    /// The block deserialized but failed validation: full
+    /// [`BlockValidator::validate_block`] above the assume-valid height,
+    /// header-only [`AuxPowHeaderValidator::validate_auxpow_header`](crate::AuxPowHeaderValidator::validate_auxpow_header)
+    /// at or below it.
+    Block {
+        offset: u64,
+        height: BlockHeight,
+        source: ValidateBlockError,
+    },
+}
+
+/// Streams `bootstrap.dat`-formatted records from `reader` into
+/// `validator`'s store, one block at a time.
+///
+/// The store `validator` was built on must already contain the chain's
+/// genesis header (as [`BlockValidator::new`] requires); a leading record
+/// whose block hash matches the store's genesis is skipped rather than
+/// re-validated, so a `bootstrap.dat` that (as usual) starts from genesis
+/// can be fed in as-is.
+///
+/// If `assume_valid_up_to_height` is `Some(h)`, blocks at heights `<= h`
+/// still have their headers fully validated and linked into the store, but
+/// skip the transaction/merkle checks [`BlockValidator::validate_block`]
+/// otherwise runs -- mirroring how bulk importers trust early history
+/// instead of re-verifying every historical transaction.
+///
+/// Stops at the first record that fails to read, decode, or validate and
+/// returns that record's [`BootstrapImportError`]; blocks imported before it
+/// stay in the store. On a clean end of stream, returns how many blocks were
+/// imported (a skipped leading genesis record isn't counted).
+pub fn import_blocks_bootstrap<T: HeaderStore>(
+    validator: &mut BlockValidator<T>,
+    mut reader: impl Read,
+    current_time: Duration,
+    assume_valid_up_to_height: Option<BlockHeight>,
+) -> Result<u64, BootstrapImportError> {
+    let expected_magic = validator.header_validator.network().magic();
+    let genesis_hash = validator.header_validator.store_mut().get_initial_hash();
+
+    let mut offset = 0u64;
+    let mut imported = 0u64;
+
+    loop {
+        let mut magic_bytes = [0u8; 4];
+        match read_exact_or_eof(&mut reader, &mut magic_bytes) {
+            Ok(true) => break,
+            Ok(false) => {}
+            Err(source) => return Err(BootstrapImportError::Io { offset, source }),
+        }
+        let magic = Magic::from_bytes(magic_bytes);
+        if magic != expected_magic {
+            return Err(BootstrapImportError::UnexpectedMagic {
+                offset,
+                expected: expected_magic,
+                found: magic,
+            });
+        }
+
+        let mut length_bytes = [0u8; 4];
+        reader
+            .read_exact(&mut length_bytes)
+            .map_err(|source| BootstrapImportError::Io { offset, source })?;
+        let length = u32::from_le_bytes(length_bytes) as u64;
+
+        let mut block_bytes = vec![0u8; length as usize];
+        reader
+            .read_exact(&mut block_bytes)
+            .map_err(|source| BootstrapImportError::Io { offset, source })?;
+
+        let block = Block::consensus_decode(&mut block_bytes.as_slice())
+            .map_err(|source| BootstrapImportError::BlockDecode { offset, source })?;
+
+        if imported == 0 && block.block_hash() == genesis_hash {
+            offset += 8 + length;
+            continue;
+        }
+
+        let height = validator.header_validator.store_mut().height() + 1;
+        let below_checkpoint = assume_valid_up_to_height.is_some_and(|h| height <= h);
+
+        if below_checkpoint {
+            validator
+                .header_validator
+                .validate_auxpow_header(&block.header, current_time)
+                .map_err(ValidateBlockError::from)
+        } else {
+            validator.validate_block(&block, current_time)
+        }
+        .map_err(|source| BootstrapImportError::Block {
+            offset,
+            height,
+            source,
+        })?;
+
+        validator
+            .header_validator
+            .store_mut()
+            .add(block.header.pure_header);
+        imported += 1;
+        offset += 8 + length;
+    }
+
+    Ok(imported)
+}
+
+/// Reads exactly `buf.len()` bytes, treating an EOF hit before any byte is
+/// read as a clean end of stream (`Ok(true)`) rather than an error -- the
+/// boundary between records is the only place a `bootstrap.dat` is allowed
+/// to end.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) if read == 0 => return Ok(true),
+            Ok(0) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "record ended mid-magic",
+                ))
+            }
+            Ok(n) => read += n,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(false)
+}
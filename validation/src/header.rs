@@ -1,7 +1,13 @@
 #[cfg(feature = "btc")]
 pub mod btc;
+pub mod buffer;
+pub mod checkpoints;
+#[cfg(feature = "doge")]
+pub mod consensus;
 #[cfg(feature = "doge")]
 pub mod doge;
+pub mod import_export;
+pub mod retarget;
 #[cfg(test)]
 mod tests;
 
@@ -9,14 +15,16 @@ mod tests;
 use bitcoin::dogecoin::Header as AuxPowHeader;
 
 use crate::BlockHeight;
-use bitcoin::{block::Header, BlockHash, CompactTarget, Target};
+use bitcoin::{block::Header, BlockHash, CompactTarget, Target, Work};
+use std::collections::HashSet;
 use std::time::Duration;
 
 /// An error thrown when trying to validate a header.
 #[derive(Debug, PartialEq)]
 pub enum ValidateHeaderError {
-    /// Used when the timestamp in the header is lower than
-    /// the median of timestamps of past 11 headers.
+    /// The median-time-past (MTP) rule: used when the timestamp in the
+    /// header is not strictly greater than the median of timestamps of
+    /// past 11 headers.
     HeaderIsOld,
     /// Used when the timestamp in the header is more than 2 hours
     /// from the current time.
@@ -27,9 +35,22 @@ pub enum ValidateHeaderError {
     /// Used when the PoW in the header is invalid as per the target mentioned
     /// in the header.
     InvalidPoWForHeaderTarget,
+    /// Used when the target the retarget algorithm computed for the
+    /// header's height doesn't match the target the header itself claims.
+    TargetMismatch {
+        block_hash: BlockHash,
+        expected_target: CompactTarget,
+        header_target: CompactTarget,
+    },
     /// Used when the PoW in the header is invalid as per the target
-    /// computed based on the previous headers.
-    InvalidPoWForComputedTarget,
+    /// computed based on the previous headers: the header's hash doesn't
+    /// satisfy `target`, even though `target` itself matched what the
+    /// header claims.
+    InvalidPoWForComputedTarget {
+        block_hash: BlockHash,
+        target: CompactTarget,
+        computed_hash: BlockHash,
+    },
     /// Used when the target in the header is greater than the max possible
     /// value.
     TargetDifficultyAboveMax,
@@ -58,6 +79,10 @@ pub enum ValidateAuxPowHeaderError {
     InvalidAuxPoW,
     /// Used when the PoW in the parent block is invalid
     InvalidParentPoW,
+    /// Used when the AuxPow parent block's own version field claims the
+    /// same chain id as the chain being validated, which would let a
+    /// block serve as its own AuxPow parent.
+    ParentHasAuxChainId,
 }
 
 #[cfg(feature = "doge")]
@@ -67,6 +92,83 @@ impl From<ValidateAuxPowHeaderError> for ValidateHeaderError {
     }
 }
 
+impl ValidateHeaderError {
+    /// A short, stable label for this error variant, suitable for use as a
+    /// metric dimension (e.g. a counter of rejected headers by reason) by a
+    /// caller that ingests blocks validated through this crate.
+    pub fn as_metric_label(&self) -> &'static str {
+        match self {
+            ValidateHeaderError::HeaderIsOld => "header_is_old",
+            ValidateHeaderError::HeaderIsTooFarInFuture { .. } => "header_too_far_in_future",
+            ValidateHeaderError::InvalidPoWForHeaderTarget => "invalid_pow_for_header_target",
+            ValidateHeaderError::TargetMismatch { .. } => "target_mismatch",
+            ValidateHeaderError::InvalidPoWForComputedTarget { .. } => {
+                "invalid_pow_for_computed_target"
+            }
+            ValidateHeaderError::TargetDifficultyAboveMax => "target_difficulty_above_max",
+            ValidateHeaderError::PrevHeaderNotFound => "prev_header_not_found",
+            #[cfg(feature = "doge")]
+            ValidateHeaderError::ValidateAuxPowHeader(err) => err.as_metric_label(),
+        }
+    }
+}
+
+/// Whether a [`ValidateHeaderError`] is a permanent rejection, or might stop
+/// applying once `current_time` has advanced far enough, per
+/// [`ValidateHeaderError::retry_classification`].
+#[derive(Debug, PartialEq)]
+pub enum RetryClassification {
+    /// Revalidating the same header later won't help; nothing about the
+    /// rejection is a function of `current_time`.
+    Permanent,
+    /// The header was rejected only because `current_time` hasn't yet
+    /// caught up to it. Revalidating it again once `current_time` reaches
+    /// `earliest_valid_time` (in seconds) may succeed, assuming nothing
+    /// else about the header or the chain it extends has changed.
+    TemporarilyInvalidUntil { earliest_valid_time: u64 },
+}
+
+impl ValidateHeaderError {
+    /// Classifies this rejection as permanent or retryable, following the
+    /// pattern OpenEthereum's AuRa engine uses to "wait for future blocks"
+    /// rather than discarding them outright.
+    ///
+    /// [`HeaderIsTooFarInFuture`](Self::HeaderIsTooFarInFuture) is the only
+    /// retryable case: a header more than `max_future_drift` ahead of the
+    /// clock may become valid on its own once enough real time has passed,
+    /// without the header or its ancestors changing at all.
+    pub fn retry_classification(&self, max_future_drift: Duration) -> RetryClassification {
+        match self {
+            ValidateHeaderError::HeaderIsTooFarInFuture { block_time, .. } => {
+                RetryClassification::TemporarilyInvalidUntil {
+                    earliest_valid_time: block_time.saturating_sub(max_future_drift.as_secs()),
+                }
+            }
+            _ => RetryClassification::Permanent,
+        }
+    }
+}
+
+#[cfg(feature = "doge")]
+impl ValidateAuxPowHeaderError {
+    /// A short, stable label for this error variant, suitable for use as a
+    /// metric dimension for AuxPow verification failures specifically.
+    pub fn as_metric_label(&self) -> &'static str {
+        match self {
+            ValidateAuxPowHeaderError::VersionObsolete => "auxpow_version_obsolete",
+            ValidateAuxPowHeaderError::LegacyBlockNotAllowed => "auxpow_legacy_block_not_allowed",
+            ValidateAuxPowHeaderError::AuxPowBlockNotAllowed => "auxpow_block_not_allowed",
+            ValidateAuxPowHeaderError::InvalidChainId => "auxpow_invalid_chain_id",
+            ValidateAuxPowHeaderError::InconsistentAuxPowBitSet => {
+                "auxpow_inconsistent_bit_set"
+            }
+            ValidateAuxPowHeaderError::InvalidAuxPoW => "auxpow_invalid_proof",
+            ValidateAuxPowHeaderError::InvalidParentPoW => "auxpow_invalid_parent_pow",
+            ValidateAuxPowHeaderError::ParentHasAuxChainId => "auxpow_parent_has_aux_chain_id",
+        }
+    }
+}
+
 const ONE_HOUR: Duration = Duration::from_secs(3_600);
 
 pub trait HeaderStore {
@@ -88,13 +190,164 @@ pub trait HeaderStore {
 
     /// Adds a header to the store.
     fn add(&mut self, header: Header);
+
+    /// Returns the cumulative proof-of-work of the chain up to and
+    /// including the header at `height`, i.e. the sum of `work()` over
+    /// every header from height `0` to `height`.
+    ///
+    /// Used by retarget strategies (see
+    /// [`retarget`](crate::header::retarget)) that derive the next target
+    /// from chainwork produced over a sliding window rather than from a
+    /// fixed interval. The default implementation walks the whole chain on
+    /// every call; a store backing a long-lived chain should maintain a
+    /// running index and override this for O(1) lookups.
+    fn chainwork_at_height(&self, height: u32) -> Work {
+        let mut total = self
+            .get_with_height(0)
+            .expect("genesis header not found")
+            .work();
+        for h in 1..=height {
+            total = total
+                + self
+                    .get_with_height(h)
+                    .expect("header at height not found")
+                    .work();
+        }
+        total
+    }
+
+    /// Returns the median-time-past (MTP) at `prev_hash`: the median
+    /// timestamp among the up to [`MEDIAN_TIME_SPAN`] ancestors ending at
+    /// (and including) `prev_hash` itself (fewer near genesis, `0` if
+    /// `prev_hash` isn't in the store at all).
+    ///
+    /// Borrows parity-zcash's `median_timestamp` provider method and
+    /// subcoin's `MEDIAN_TIME_SPAN` constant, and is exposed as a
+    /// first-class query -- not just an internal detail of
+    /// [`HeaderValidator::is_timestamp_valid`] -- so reorg/fork-selection
+    /// code and external tooling can evaluate the MTP rule directly,
+    /// without re-deriving it.
+    fn median_time_past(&self, prev_hash: &BlockHash) -> u32 {
+        median_of(past_times(self, prev_hash))
+    }
+
+    /// As [`median_time_past`](Self::median_time_past), but folds
+    /// `candidate_time` -- e.g. the timestamp of a header not yet added to
+    /// the store -- into the window before taking the median, following
+    /// parity-zcash's `median_timestamp_inclusive`.
+    fn median_time_past_inclusive(&self, prev_hash: &BlockHash, candidate_time: u32) -> u32 {
+        let mut times = past_times(self, prev_hash);
+        times.push(candidate_time);
+        median_of(times)
+    }
+
+    /// Returns the cumulative proof-of-work of the chain ending at `hash`,
+    /// inclusive, i.e. the sum of `work()` over every header reachable from
+    /// `hash` by following `prev_blockhash` back to genesis.
+    ///
+    /// Unlike [`chainwork_at_height`](Self::chainwork_at_height), this is
+    /// keyed by hash rather than height, so it also works for a header on a
+    /// side branch that hasn't (or won't) become part of the canonical
+    /// height index. Draws on the chain-work accounting Neptune-core relies
+    /// on to compare competing chains by total work rather than length.
+    ///
+    /// Panics if `hash`, or any of its ancestors back to genesis, isn't in
+    /// the store.
+    fn total_work(&self, hash: &BlockHash) -> Work {
+        let mut total: Option<Work> = None;
+        let mut current_hash = *hash;
+        let initial_hash = self.get_initial_hash();
+        loop {
+            let header = self
+                .get_with_block_hash(&current_hash)
+                .expect("header not found while accumulating total work");
+            total = Some(match total {
+                Some(work) => work + header.work(),
+                None => header.work(),
+            });
+            if current_hash == initial_hash {
+                break;
+            }
+            current_hash = header.prev_blockhash;
+        }
+        total.expect("hash not found in store")
+    }
+
+    /// Returns the hash of the current best (most cumulative work) tip.
+    ///
+    /// The default implementation just returns the header at
+    /// [`height`](Self::height), since a store that only tracks a single
+    /// append-only sequence has no other tip to compare against. A store
+    /// that retains side branches should override this to pick the tip
+    /// with the highest [`total_work`](Self::total_work), following parity-zcash's
+    /// `best_header` provider method.
+    fn best_tip(&self) -> BlockHash {
+        self.get_with_height(self.height())
+            .expect("tip header not found")
+            .block_hash()
+    }
 }
 
-fn timestamp_is_at_most_2h_in_future(
+/// Number of ancestor timestamps the median-time-past (MTP) rule considers.
+///
+/// Bitcoin Protocol Rules wiki https://en.bitcoin.it/wiki/Protocol_rules says,
+/// "Reject if timestamp is the median time of the last 11 blocks or before".
+const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Collects up to [`MEDIAN_TIME_SPAN`] ancestor timestamps ending at (and
+/// including) `hash`, walking back towards genesis. Shared by
+/// [`HeaderStore::median_time_past`] and
+/// [`HeaderStore::median_time_past_inclusive`].
+fn past_times(store: &impl HeaderStore, hash: &BlockHash) -> Vec<u32> {
+    let mut times = Vec::with_capacity(MEDIAN_TIME_SPAN);
+    let mut current_hash = *hash;
+    let initial_hash = store.get_initial_hash();
+    for _ in 0..MEDIAN_TIME_SPAN {
+        let Some(header) = store.get_with_block_hash(&current_hash) else {
+            break;
+        };
+        times.push(header.time);
+        if current_hash == initial_hash {
+            break;
+        }
+        current_hash = header.prev_blockhash;
+    }
+    times
+}
+
+/// Returns the median of `times`, or `0` if it's empty -- i.e. there's
+/// nothing to compare against, so there's no MTP to enforce.
+fn median_of(mut times: Vec<u32>) -> u32 {
+    if times.is_empty() {
+        return 0;
+    }
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// The outcome of comparing a freshly validated header against the
+/// current best tip's cumulative work, as returned by
+/// [`HeaderValidator::evaluate_fork_choice`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForkChoice {
+    /// The header does not yet have more cumulative work than the current
+    /// best tip -- either because it extends the best chain already, or
+    /// because it's a side branch that hasn't caught up.
+    Extends,
+    /// The header's chain now has strictly more cumulative work than the
+    /// current best tip. `fork_path` lists the block hashes from (but not
+    /// including) the fork point up to the header, in order from oldest to
+    /// newest -- i.e. the headers a caller must apply, in this order, to
+    /// reorg onto the new best chain.
+    Reorg { fork_path: Vec<BlockHash> },
+}
+
+fn timestamp_is_at_most_max_drift_in_future(
     block_time: Duration,
     current_time: Duration,
+    max_future_drift: Duration,
 ) -> Result<(), ValidateHeaderError> {
-    let max_allowed_time = current_time + 2 * ONE_HOUR;
+    let max_allowed_time = current_time + max_future_drift;
 
     if block_time > max_allowed_time {
         return Err(ValidateHeaderError::HeaderIsTooFarInFuture {
@@ -106,36 +359,23 @@ fn timestamp_is_at_most_2h_in_future(
     Ok(())
 }
 
-/// Validates if a header's timestamp is valid.
-/// Bitcoin Protocol Rules wiki https://en.bitcoin.it/wiki/Protocol_rules says,
-/// "Reject if timestamp is the median time of the last 11 blocks or before"
-/// "Block timestamp must not be more than two hours in the future"
-fn is_timestamp_valid(
-    store: &impl HeaderStore,
-    header: &Header,
-    current_time: Duration,
-) -> Result<(), ValidateHeaderError> {
-    timestamp_is_at_most_2h_in_future(Duration::from_secs(header.time as u64), current_time)?;
-    let mut times = vec![];
-    let mut current_header: Header = *header;
-    let initial_hash = store.get_initial_hash();
-    for _ in 0..11 {
-        if let Some(prev_header) = store.get_with_block_hash(&current_header.prev_blockhash) {
-            times.push(prev_header.time);
-            if current_header.prev_blockhash == initial_hash {
-                break;
-            }
-            current_header = prev_header;
-        }
-    }
-
-    times.sort_unstable();
-    let median = times[times.len() / 2];
-    if header.time <= median {
-        return Err(ValidateHeaderError::HeaderIsOld);
-    }
-
-    Ok(())
+/// Runtime overrides for consensus parameters that are normally fixed at
+/// compile time per network, so tests can simulate mining at arbitrary
+/// speeds -- reaching a retarget or fork-activation boundary in a handful
+/// of blocks instead of thousands -- without touching mainnet rules.
+/// Takes its name and rationale from Neptune-core's `target_block_interval`
+/// mine-loop parameter.
+///
+/// Every field defaults to `None`, meaning "use the network's built-in
+/// value"; a validator consults an override only where it's `Some`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsensusParamsOverride {
+    /// Overrides [`HeaderValidator::pow_target_spacing`].
+    pub pow_target_spacing: Option<Duration>,
+    /// Overrides [`HeaderValidator::difficulty_adjustment_interval`].
+    pub difficulty_adjustment_interval: Option<u32>,
+    /// Overrides [`HeaderValidator::digishield_activation_height`].
+    pub digishield_activation_height: Option<u32>,
 }
 
 pub trait HeaderValidator {
@@ -160,6 +400,27 @@ pub trait HeaderValidator {
     /// Returns the PoW limit bits depending on the network
     fn pow_limit_bits(&self) -> CompactTarget;
 
+    /// Returns the maximum allowed drift between a header's timestamp and
+    /// `current_time` before it's rejected as too far in the future.
+    /// Defaults to 2 hours, matching Bitcoin/Dogecoin mainnet; override
+    /// for networks (e.g. regtest) that want a different window.
+    fn max_future_drift(&self) -> Duration {
+        2 * ONE_HOUR
+    }
+
+    /// Returns the median-time-past (MTP) of the chain at `header`, i.e.
+    /// the median timestamp of the up to 11 ancestors preceding it. See
+    /// [`HeaderStore::median_time_past`] for the exact semantics near
+    /// genesis.
+    ///
+    /// Exposed beyond [`is_timestamp_valid`](Self::is_timestamp_valid) so
+    /// callers can gate other rules against it directly, e.g. a
+    /// BIP113-style lock-time check that compares against MTP rather than
+    /// a block's own timestamp.
+    fn median_time_past(&self, header: &Header) -> u32 {
+        self.store().median_time_past(&header.prev_blockhash)
+    }
+
     /// Returns the target spacing between blocks in seconds.
     fn pow_target_spacing(&self) -> Duration;
 
@@ -169,8 +430,73 @@ pub trait HeaderValidator {
     /// Returns `true` if mining a min-difficulty block is allowed after some delay.
     fn allow_min_difficulty_blocks(&self, height: u32) -> bool;
 
+    /// Returns the height at which Dogecoin's Digishield per-block retarget
+    /// activates -- distinct from the later height at which
+    /// [`allow_min_difficulty_blocks`](Self::allow_min_difficulty_blocks)'s
+    /// delay rule kicks in. Only meaningful for
+    /// [`DigishieldRetarget`](crate::header::doge::DigishieldRetarget);
+    /// defaults to "never" so other chains don't have to think about it.
+    fn digishield_activation_height(&self) -> u32 {
+        u32::MAX
+    }
+
+    /// Returns `true` if Dogecoin's per-block Digishield retarget (rather
+    /// than the pre-fork 240-block interval rule) governs difficulty at
+    /// `height`, i.e. `height` is at or beyond
+    /// [`digishield_activation_height`](Self::digishield_activation_height).
+    fn is_digishield_activated(&self, height: u32) -> bool {
+        height >= self.digishield_activation_height()
+    }
+
+    /// Returns `true` if `height` may use [`max_target`](Self::max_target)
+    /// under Dogecoin's post-Digishield min-difficulty-after-a-delay rule,
+    /// i.e. [`allow_min_difficulty_blocks`](Self::allow_min_difficulty_blocks)
+    /// permits it, Digishield has activated (so this applies on *every*
+    /// block rather than only at a 240-block retarget boundary), and
+    /// `timestamp` is more than twice
+    /// [`pow_target_spacing`](Self::pow_target_spacing) after `prev_header`.
+    fn allow_digishield_min_difficulty_for_block(
+        &self,
+        prev_header: &Header,
+        height: u32,
+        timestamp: u32,
+    ) -> bool {
+        self.is_digishield_activated(height)
+            && self.allow_min_difficulty_blocks(height)
+            && timestamp > prev_header.time + (self.pow_target_spacing() * 2).as_secs() as u32
+    }
+
+    /// Validates a header's timestamp: it must be strictly greater than
+    /// [`median_time_past`](Self::median_time_past), and not more than
+    /// [`max_future_drift`](Self::max_future_drift) ahead of `current_time`.
+    fn is_timestamp_valid(
+        &self,
+        header: &Header,
+        current_time: Duration,
+    ) -> Result<(), ValidateHeaderError> {
+        timestamp_is_at_most_max_drift_in_future(
+            Duration::from_secs(header.time as u64),
+            current_time,
+            self.max_future_drift(),
+        )?;
+
+        if header.time <= self.median_time_past(header) {
+            return Err(ValidateHeaderError::HeaderIsOld);
+        }
+
+        Ok(())
+    }
+
     /// Validates a header. If a failure occurs, a
     /// [ValidateHeaderError](ValidateHeaderError) will be returned.
+    ///
+    /// Every implementation enforces, at minimum, the two context-dependent
+    /// consensus rules that a peer-supplied header must satisfy before it can
+    /// be accepted: median-time-past (the header's timestamp must be strictly
+    /// greater than the median of the 11 preceding headers, and not more than
+    /// 2 hours ahead of `current_time`) and proof-of-work (the header's
+    /// `nBits` must decode to a target at or below [`max_target`](Self::max_target),
+    /// and the header's hash must be at or below that target).
     fn validate_header(
         &self,
         header: &Header,
@@ -206,6 +532,152 @@ pub trait HeaderValidator {
         prev_header: &Header,
         prev_height: BlockHeight,
     ) -> CompactTarget;
+
+    /// Returns the `nBits` target that a block at `height` must satisfy,
+    /// without requiring that block's header to exist yet.
+    ///
+    /// This resolves the ancestor header at `height - 1` from the
+    /// [`store`](Self::store) and runs the same retarget computation
+    /// [`validate_header`](Self::validate_header) uses internally, so it
+    /// reflects DigiShield and min-difficulty-after-a-delay (more than twice
+    /// [`pow_target_spacing`](Self::pow_target_spacing)) behavior
+    /// exactly as consensus would. `timestamp` is the candidate block's
+    /// timestamp, since the min-difficulty rule depends on it.
+    ///
+    /// Returns `None` if the store doesn't hold an ancestor at
+    /// `height - 1`, e.g. because `height` is more than one block ahead
+    /// of the chain tip.
+    fn get_expected_target(&self, height: BlockHeight, timestamp: u32) -> Option<CompactTarget> {
+        let prev_height = height.checked_sub(1)?;
+        let prev_header = self.store().get_with_height(prev_height)?;
+        Some(
+            self.get_next_target(&prev_header, prev_height, timestamp)
+                .to_compact_lossy(),
+        )
+    }
+
+    /// Returns the `nBits` target that a block extending `prev_hash` at
+    /// `height` must satisfy, without requiring that block's header to
+    /// exist yet.
+    ///
+    /// As [`get_expected_target`](Self::get_expected_target), but keyed by
+    /// hash rather than height -- like [`median_time_past`](Self::median_time_past)
+    /// and [`total_work`](HeaderStore::total_work), this also works for a
+    /// candidate extending a side branch, not just the canonical height
+    /// index. Runs the same retarget computation
+    /// [`validate_header`](Self::validate_header) uses internally, so the
+    /// two can never diverge. `timestamp` is the candidate block's
+    /// timestamp, since the min-difficulty rule depends on it. Named after
+    /// parity-zcash's `expected_nbits`.
+    ///
+    /// Panics if `prev_hash` isn't in the [`store`](Self::store), or if
+    /// `height` is `0`.
+    fn expected_bits(
+        &self,
+        prev_hash: &BlockHash,
+        height: BlockHeight,
+        timestamp: u32,
+    ) -> CompactTarget {
+        let prev_height = height
+            .checked_sub(1)
+            .expect("expected_bits called with height 0, which has no predecessor");
+        let prev_header = self
+            .store()
+            .get_with_block_hash(prev_hash)
+            .expect("prev_hash not found in store");
+        self.get_next_target(&prev_header, prev_height, timestamp)
+            .to_compact_lossy()
+    }
+
+    /// As [`expected_bits`](Self::expected_bits), resolved against the
+    /// store's current tip -- the common case of asking "what bits must
+    /// the next block carry right now?"
+    fn expected_bits_at_tip(&self, timestamp: u32) -> CompactTarget {
+        let tip_height = self.store().height();
+        let tip_header = self
+            .store()
+            .get_with_height(tip_height)
+            .expect("tip header not found in store");
+        self.expected_bits(&tip_header.block_hash(), tip_height + 1, timestamp)
+    }
+
+    /// Evaluates a freshly validated `header` -- already added to
+    /// [`store`](Self::store), possibly on a side branch -- against the
+    /// current best tip's cumulative work, and reports whether it should
+    /// now be adopted as the most-work chain.
+    ///
+    /// This is the fork-choice entry point: callers that only ever append
+    /// to a single linear chain don't need it, but one tracking competing
+    /// tips (e.g. during a reorg race) calls this after every successfully
+    /// validated header to decide whether to switch chains, following
+    /// parity-zcash's `best_header` provider method.
+    fn evaluate_fork_choice(&self, header: &Header) -> ForkChoice {
+        let store = self.store();
+        let header_hash = header.block_hash();
+        let best_tip = store.best_tip();
+
+        if store.total_work(&header_hash) <= store.total_work(&best_tip) {
+            return ForkChoice::Extends;
+        }
+
+        let initial_hash = store.get_initial_hash();
+        let mut best_chain_ancestors = HashSet::new();
+        let mut cursor = best_tip;
+        loop {
+            best_chain_ancestors.insert(cursor);
+            if cursor == initial_hash {
+                break;
+            }
+            let Some(ancestor) = store.get_with_block_hash(&cursor) else {
+                break;
+            };
+            cursor = ancestor.prev_blockhash;
+        }
+
+        let mut fork_path = vec![header_hash];
+        let mut cursor = header.prev_blockhash;
+        while !best_chain_ancestors.contains(&cursor) {
+            fork_path.push(cursor);
+            let Some(ancestor) = store.get_with_block_hash(&cursor) else {
+                break;
+            };
+            cursor = ancestor.prev_blockhash;
+        }
+        fork_path.reverse();
+
+        ForkChoice::Reorg { fork_path }
+    }
+
+    /// Compares two candidate tips by accumulated proof-of-work, for callers
+    /// that need to weigh two chains directly rather than only against the
+    /// current best tip, as [`evaluate_fork_choice`](Self::evaluate_fork_choice)
+    /// does. Ties -- equal accumulated work -- favor `a`, the same
+    /// "don't reorg without strictly more work" convention
+    /// `evaluate_fork_choice` already applies, so a chain that was seen
+    /// first isn't displaced by an equally-good one seen later.
+    ///
+    /// Needed because Digishield retargets every block and min-difficulty
+    /// testnet blocks make height a poor proxy for which chain actually has
+    /// more work behind it.
+    fn compare_chains(&self, a: &BlockHash, b: &BlockHash) -> BetterChain {
+        let store = self.store();
+        if store.total_work(b) > store.total_work(a) {
+            BetterChain::Second
+        } else {
+            BetterChain::First
+        }
+    }
+}
+
+/// The result of [`HeaderValidator::compare_chains`]: which of the two
+/// compared tips has the greater accumulated proof-of-work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BetterChain {
+    /// The first tip passed to `compare_chains` has at least as much work
+    /// (including the tie case).
+    First,
+    /// The second tip passed to `compare_chains` has strictly more work.
+    Second,
 }
 
 #[cfg(feature = "doge")]
@@ -1,20 +1,82 @@
-use crate::header::{is_timestamp_valid, HeaderStore, HeaderValidator, ValidateHeaderError};
+use crate::header::retarget::DifficultyRetarget;
+use crate::header::{ConsensusParamsOverride, HeaderStore, HeaderValidator, ValidateHeaderError};
 use crate::BlockHeight;
 use bitcoin::network::Network as BitcoinNetwork;
 use bitcoin::{block::Header, CompactTarget, Target};
 use std::time::Duration;
 
 /// Expected number of blocks for 2 weeks in Bitcoin (2_016).
-pub const DIFFICULTY_ADJUSTMENT_INTERVAL: BlockHeight = 6 * 24 * 14;
+pub const DIFFICULTY_ADJUSTMENT_INTERVAL_BITCOIN: BlockHeight = 6 * 24 * 14;
 
-pub struct BitcoinHeaderValidator<T> {
+/// The default [`DifficultyRetarget`] strategy for Bitcoin: difficulty is
+/// readjusted every [`difficulty_adjustment_interval`](HeaderValidator::difficulty_adjustment_interval)
+/// blocks, with testnet and regtest additionally allowing a
+/// minimum-difficulty block if none has been found in twice the target
+/// spacing. This is the exact rule `BitcoinHeaderValidator` always
+/// enforced before the retarget algorithm became pluggable; kept as a
+/// standalone strategy so alternatives can be swapped in the same way
+/// [`DigishieldRetarget`](crate::header::doge::DigishieldRetarget) is for
+/// Dogecoin.
+pub struct BitcoinRetarget;
+
+impl<V> DifficultyRetarget<V> for BitcoinRetarget
+where
+    V: HeaderValidator<Network = BitcoinNetwork>,
+{
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        match validator.network() {
+            BitcoinNetwork::Testnet | BitcoinNetwork::Testnet4 | BitcoinNetwork::Regtest => {
+                if (prev_height + 1) % validator.difficulty_adjustment_interval(prev_height + 1)
+                    != 0
+                {
+                    // This branch is reached only for Regtest and Testnet networks.
+                    // Here is the quote from "https://en.bitcoin.it/wiki/Testnet"
+                    // "If no block has been found in 20 minutes, the difficulty automatically
+                    // resets back to the minimum for a single block, after which it
+                    // returns to its previous value."
+                    if timestamp
+                        > prev_header.time + (validator.pow_target_spacing() * 2).as_secs() as u32
+                    {
+                        // If no block has been found in twice the target spacing, then use the
+                        // maximum difficulty target
+                        validator.max_target()
+                    } else {
+                        // If the block has been found within twice the target spacing, then use
+                        // the previous difficulty target that is not equal to the maximum
+                        // difficulty target
+                        Target::from_compact(
+                            validator.find_next_difficulty_in_chain(prev_header, prev_height),
+                        )
+                    }
+                } else {
+                    Target::from_compact(
+                        validator.compute_next_difficulty(prev_header, prev_height),
+                    )
+                }
+            }
+            BitcoinNetwork::Bitcoin | BitcoinNetwork::Signet => {
+                Target::from_compact(validator.compute_next_difficulty(prev_header, prev_height))
+            }
+        }
+    }
+}
+
+pub struct BitcoinHeaderValidator<T, R = BitcoinRetarget> {
     store: T,
     network: BitcoinNetwork,
+    retarget: R,
+    params_override: ConsensusParamsOverride,
 }
 
-impl<T> BitcoinHeaderValidator<T> {
+impl<T> BitcoinHeaderValidator<T, BitcoinRetarget> {
     pub fn new(store: T, network: BitcoinNetwork) -> Self {
-        Self { store, network }
+        Self::with_retarget(store, network, BitcoinRetarget)
     }
 
     pub fn mainnet(store: T) -> Self {
@@ -28,15 +90,68 @@ impl<T> BitcoinHeaderValidator<T> {
     pub fn regtest(store: T) -> Self {
         Self::new(store, BitcoinNetwork::Regtest)
     }
+
+    /// Builds a validator with one or more consensus parameters overridden
+    /// at runtime, e.g. so a test can simulate mining at an arbitrary
+    /// speed and reach a retarget boundary in a handful of blocks instead
+    /// of thousands, without touching mainnet rules.
+    pub fn with_params_override(
+        store: T,
+        network: BitcoinNetwork,
+        params_override: ConsensusParamsOverride,
+    ) -> Self {
+        Self::with_retarget_and_params_override(store, network, BitcoinRetarget, params_override)
+    }
 }
 
-impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
+impl<T, R> BitcoinHeaderValidator<T, R> {
+    /// Builds a validator that retargets difficulty using a custom
+    /// strategy, e.g. for a Bitcoin-derived chain that needs a different
+    /// adjustment rule than [`BitcoinRetarget`].
+    pub fn with_retarget(store: T, network: BitcoinNetwork, retarget: R) -> Self {
+        Self::with_retarget_and_params_override(
+            store,
+            network,
+            retarget,
+            ConsensusParamsOverride::default(),
+        )
+    }
+
+    /// Builds a validator with both a custom retarget strategy and one or
+    /// more consensus parameters overridden at runtime.
+    pub fn with_retarget_and_params_override(
+        store: T,
+        network: BitcoinNetwork,
+        retarget: R,
+        params_override: ConsensusParamsOverride,
+    ) -> Self {
+        Self {
+            store,
+            network,
+            retarget,
+            params_override,
+        }
+    }
+}
+
+impl<T: HeaderStore, R: DifficultyRetarget<BitcoinHeaderValidator<T, R>>> HeaderValidator
+    for BitcoinHeaderValidator<T, R>
+{
     type Network = BitcoinNetwork;
+    type Store = T;
 
     fn network(&self) -> &Self::Network {
         &self.network
     }
 
+    fn store(&self) -> &Self::Store {
+        &self.store
+    }
+
+    fn store_mut(&mut self) -> &mut Self::Store {
+        &mut self.store
+    }
+
     fn max_target(&self) -> Target {
         self.network().params().max_attainable_target
     }
@@ -53,12 +168,18 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
     }
 
     fn pow_target_spacing(&self) -> Duration {
-        Duration::from_secs(self.network().params().pow_target_spacing)
+        self.params_override
+            .pow_target_spacing
+            .unwrap_or_else(|| Duration::from_secs(self.network().params().pow_target_spacing))
     }
 
     fn difficulty_adjustment_interval(&self, _height: u32) -> u32 {
-        (self.network().params().pow_target_timespan / self.network().params().pow_target_spacing)
-            as u32
+        self.params_override
+            .difficulty_adjustment_interval
+            .unwrap_or_else(|| {
+                (self.network().params().pow_target_timespan
+                    / self.network().params().pow_target_spacing) as u32
+            })
     }
 
     fn allow_min_difficulty_blocks(&self, _height: u32) -> bool {
@@ -84,7 +205,7 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         self.is_timestamp_valid(header, current_time)?;
 
         let header_target = header.target();
-        if header_target > max_target(&self.network) {
+        if header_target > self.max_target() {
             return Err(ValidateHeaderError::TargetDifficultyAboveMax);
         }
 
@@ -93,13 +214,12 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         }
 
         let target = self.get_next_target(&prev_header, prev_height, header.time);
-        if let Err(err) = header.validate_pow(target) {
-            match err {
-                bitcoin::block::ValidationError::BadProofOfWork => println!("bad proof of work"),
-                bitcoin::block::ValidationError::BadTarget => println!("bad target"),
-                _ => {}
-            };
-            return Err(ValidateHeaderError::InvalidPoWForComputedTarget);
+        if header.validate_pow(target).is_err() {
+            return Err(ValidateHeaderError::InvalidPoWForComputedTarget {
+                block_hash: header.block_hash(),
+                target: target.to_compact_lossy(),
+                computed_hash: header.block_hash(),
+            });
         }
         Ok(())
     }
@@ -110,33 +230,8 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         prev_height: BlockHeight,
         timestamp: u32,
     ) -> Target {
-        match self.network {
-            BitcoinNetwork::Testnet | BitcoinNetwork::Testnet4 | BitcoinNetwork::Regtest => {
-                if (prev_height + 1) % DIFFICULTY_ADJUSTMENT_INTERVAL != 0 {
-                    // This if statements is reached only for Regtest and Testnet networks
-                    // Here is the quote from "https://en.bitcoin.it/wiki/Testnet"
-                    // "If no block has been found in 20 minutes, the difficulty automatically
-                    // resets back to the minimum for a single block, after which it
-                    // returns to its previous value."
-                    if timestamp > prev_header.time + TEN_MINUTES * 2 {
-                        // If no block has been found in 20 minutes, then use the maximum difficulty
-                        // target
-                        max_target(&self.network)
-                    } else {
-                        // If the block has been found within 20 minutes, then use the previous
-                        // difficulty target that is not equal to the maximum difficulty target
-                        Target::from_compact(
-                            self.find_next_difficulty_in_chain(prev_header, prev_height),
-                        )
-                    }
-                } else {
-                    Target::from_compact(self.compute_next_difficulty(prev_header, prev_height))
-                }
-            }
-            BitcoinNetwork::Bitcoin | BitcoinNetwork::Signet => {
-                Target::from_compact(self.compute_next_difficulty(prev_header, prev_height))
-            }
-        }
+        self.retarget
+            .next_target(self, prev_header, prev_height, timestamp)
     }
 
     /// This method is only valid when used for testnet and regtest networks.
@@ -152,7 +247,7 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         prev_height: BlockHeight,
     ) -> CompactTarget {
         // This is the maximum difficulty target for the network
-        let pow_limit_bits = pow_limit_bits(&self.network);
+        let pow_limit_bits = self.pow_limit_bits();
         match self.network {
             BitcoinNetwork::Testnet | BitcoinNetwork::Testnet4 | BitcoinNetwork::Regtest => {
                 let mut current_header = *prev_header;
@@ -165,7 +260,7 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
                 loop {
                     // Check if non-limit PoW found or it's time to adjust difficulty.
                     if current_header.bits != pow_limit_bits
-                        || current_height % DIFFICULTY_ADJUSTMENT_INTERVAL == 0
+                        || current_height % self.difficulty_adjustment_interval(current_height) == 0
                     {
                         return current_header.bits;
                     }
@@ -204,15 +299,16 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         // regtest, simply return the previous difficulty target.
 
         let height = prev_height + 1;
-        if height % DIFFICULTY_ADJUSTMENT_INTERVAL != 0 || no_pow_retargeting(&self.network) {
+        let difficulty_adjustment_interval = self.difficulty_adjustment_interval(height);
+        if height % difficulty_adjustment_interval != 0 || self.no_pow_retargeting() {
             return prev_header.bits;
         }
         // Computing the `last_adjustment_header`.
         // `last_adjustment_header` is the last header with height multiple of 2016
-        let last_adjustment_height = if height < DIFFICULTY_ADJUSTMENT_INTERVAL {
+        let last_adjustment_height = if height < difficulty_adjustment_interval {
             0
         } else {
-            height - DIFFICULTY_ADJUSTMENT_INTERVAL
+            height - difficulty_adjustment_interval
         };
         let last_adjustment_header = self
             .store
@@ -225,7 +321,7 @@ impl<T: HeaderStore>  HeaderValidator for BitcoinHeaderValidator<T> {
         // the first block of the difficulty period is used as the base.
         // See https://github.com/bitcoin/bips/blob/master/bip-0094.mediawiki#block-storm-fix
         let last = match self.network {
-            Network::Testnet4 => last_adjustment_header.bits,
+            BitcoinNetwork::Testnet4 => last_adjustment_header.bits,
             _ => prev_header.bits,
         };
 
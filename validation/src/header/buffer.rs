@@ -0,0 +1,311 @@
+use crate::header::{HeaderValidator, RetryClassification, ValidateHeaderError};
+use crate::BlockHeight;
+use bitcoin::block::Header;
+use bitcoin::{CompactTarget, Target};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Wraps a [`HeaderValidator`] so that a header rejected only because it's
+/// temporarily ahead of the clock (see [`RetryClassification`]) is parked
+/// instead of discarded, and is automatically revalidated once
+/// `current_time` catches up to it, rather than being dropped forever over
+/// transient clock skew.
+///
+/// Parked headers are keyed by `earliest_valid_time`, the threshold
+/// [`ValidateHeaderError::retry_classification`] computes as
+/// `block_time - max_future_drift`. Every call to
+/// [`validate_header`](HeaderValidator::validate_header) first drains and
+/// revalidates any parked header whose threshold `current_time` has now
+/// reached; headers that pass are handed to the caller via
+/// [`take_promoted`](Self::take_promoted).
+pub struct BufferingHeaderValidator<V> {
+    inner: V,
+    pending: RefCell<BTreeMap<u64, Vec<Header>>>,
+    promoted: RefCell<Vec<Header>>,
+}
+
+impl<V> BufferingHeaderValidator<V> {
+    pub fn new(inner: V) -> Self {
+        Self {
+            inner,
+            pending: RefCell::new(BTreeMap::new()),
+            promoted: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Number of headers currently parked awaiting revalidation.
+    pub fn pending_len(&self) -> usize {
+        self.pending.borrow().values().map(Vec::len).sum()
+    }
+
+    /// Drains the headers most recently promoted out of the buffer by a
+    /// call to [`validate_header`](HeaderValidator::validate_header), so
+    /// the caller can add them to the store. Returns an empty `Vec` if
+    /// nothing became valid since the last call.
+    pub fn take_promoted(&self) -> Vec<Header> {
+        self.promoted.borrow_mut().drain(..).collect()
+    }
+}
+
+impl<V: HeaderValidator> BufferingHeaderValidator<V> {
+    /// Revalidates every parked header whose `earliest_valid_time` is at or
+    /// before `current_time`, moving the ones that now pass into
+    /// `promoted`. A header that still fails -- for any reason, including
+    /// still being ahead of the clock because `max_future_drift` changed --
+    /// is simply dropped, since this buffer only exists to smooth over
+    /// transient clock skew, not to retry indefinitely.
+    fn promote_ready(&self, current_time: Duration) {
+        let now = current_time.as_secs();
+        let ready_keys: Vec<u64> = self
+            .pending
+            .borrow()
+            .range(..=now)
+            .map(|(threshold, _)| *threshold)
+            .collect();
+
+        for threshold in ready_keys {
+            let headers = self.pending.borrow_mut().remove(&threshold).unwrap_or_default();
+            for header in headers {
+                if self.inner.validate_header(&header, current_time).is_ok() {
+                    self.promoted.borrow_mut().push(header);
+                }
+            }
+        }
+    }
+}
+
+impl<V: HeaderValidator> HeaderValidator for BufferingHeaderValidator<V> {
+    type Network = V::Network;
+    type Store = V::Store;
+
+    fn network(&self) -> &Self::Network {
+        self.inner.network()
+    }
+
+    fn store(&self) -> &Self::Store {
+        self.inner.store()
+    }
+
+    fn store_mut(&mut self) -> &mut Self::Store {
+        self.inner.store_mut()
+    }
+
+    fn max_target(&self) -> Target {
+        self.inner.max_target()
+    }
+
+    fn no_pow_retargeting(&self) -> bool {
+        self.inner.no_pow_retargeting()
+    }
+
+    fn pow_limit_bits(&self) -> CompactTarget {
+        self.inner.pow_limit_bits()
+    }
+
+    fn max_future_drift(&self) -> Duration {
+        self.inner.max_future_drift()
+    }
+
+    fn pow_target_spacing(&self) -> Duration {
+        self.inner.pow_target_spacing()
+    }
+
+    fn difficulty_adjustment_interval(&self, height: u32) -> u32 {
+        self.inner.difficulty_adjustment_interval(height)
+    }
+
+    fn allow_min_difficulty_blocks(&self, height: u32) -> bool {
+        self.inner.allow_min_difficulty_blocks(height)
+    }
+
+    fn validate_header(
+        &self,
+        header: &Header,
+        current_time: Duration,
+    ) -> Result<(), ValidateHeaderError> {
+        self.promote_ready(current_time);
+
+        match self.inner.validate_header(header, current_time) {
+            Err(err) => {
+                if let RetryClassification::TemporarilyInvalidUntil { earliest_valid_time } =
+                    err.retry_classification(self.max_future_drift())
+                {
+                    self.pending
+                        .borrow_mut()
+                        .entry(earliest_valid_time)
+                        .or_default()
+                        .push(*header);
+                }
+                Err(err)
+            }
+            ok => ok,
+        }
+    }
+
+    fn get_next_target(
+        &self,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        self.inner.get_next_target(prev_header, prev_height, timestamp)
+    }
+
+    fn find_next_difficulty_in_chain(
+        &self,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+    ) -> CompactTarget {
+        self.inner.find_next_difficulty_in_chain(prev_header, prev_height)
+    }
+
+    fn compute_next_difficulty(
+        &self,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+    ) -> CompactTarget {
+        self.inner.compute_next_difficulty(prev_header, prev_height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::HeaderStore;
+    use bitcoin::block::Version;
+    use bitcoin::{BlockHash, TxMerkleNode};
+    use std::str::FromStr;
+
+    struct FakeStore;
+
+    impl HeaderStore for FakeStore {
+        fn get_with_block_hash(&self, _hash: &BlockHash) -> Option<Header> {
+            None
+        }
+        fn get_with_height(&self, _height: u32) -> Option<Header> {
+            None
+        }
+        fn height(&self) -> u32 {
+            0
+        }
+        fn add(&mut self, _header: Header) {}
+    }
+
+    /// A minimal [`HeaderValidator`] whose only rule is the future-drift
+    /// check, so [`BufferingHeaderValidator`]'s park/promote logic can be
+    /// exercised without real chain data or proof-of-work.
+    struct FakeValidator(FakeStore);
+
+    impl HeaderValidator for FakeValidator {
+        type Network = ();
+        type Store = FakeStore;
+
+        fn network(&self) -> &Self::Network {
+            &()
+        }
+        fn store(&self) -> &Self::Store {
+            &self.0
+        }
+        fn store_mut(&mut self) -> &mut Self::Store {
+            &mut self.0
+        }
+        fn max_target(&self) -> Target {
+            Target::from_compact(CompactTarget::from_consensus(0x207fffff))
+        }
+        fn no_pow_retargeting(&self) -> bool {
+            true
+        }
+        fn pow_limit_bits(&self) -> CompactTarget {
+            CompactTarget::from_consensus(0x207fffff)
+        }
+        fn pow_target_spacing(&self) -> Duration {
+            Duration::from_secs(600)
+        }
+        fn difficulty_adjustment_interval(&self, _height: u32) -> u32 {
+            1
+        }
+        fn allow_min_difficulty_blocks(&self, _height: u32) -> bool {
+            false
+        }
+        fn validate_header(
+            &self,
+            header: &Header,
+            current_time: Duration,
+        ) -> Result<(), ValidateHeaderError> {
+            let max_allowed_time = (current_time + self.max_future_drift()).as_secs();
+            if header.time as u64 > max_allowed_time {
+                return Err(ValidateHeaderError::HeaderIsTooFarInFuture {
+                    block_time: header.time as u64,
+                    max_allowed_time,
+                });
+            }
+            Ok(())
+        }
+        fn get_next_target(&self, _prev_header: &Header, _prev_height: BlockHeight, _timestamp: u32) -> Target {
+            self.max_target()
+        }
+        fn find_next_difficulty_in_chain(&self, _prev_header: &Header, _prev_height: BlockHeight) -> CompactTarget {
+            self.pow_limit_bits()
+        }
+        fn compute_next_difficulty(&self, _prev_header: &Header, _prev_height: BlockHeight) -> CompactTarget {
+            self.pow_limit_bits()
+        }
+    }
+
+    fn header_with_time(time: u32) -> Header {
+        Header {
+            version: Version::from_consensus(1),
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::from_str(
+                "c120ff2ae1363593a0b92e0d281ec341a0cc989b4ee836dc3405c9f4215242a6",
+            )
+            .unwrap(),
+            time,
+            bits: CompactTarget::from_consensus(0x207fffff),
+            nonce: 0,
+        }
+    }
+
+    #[test]
+    fn parks_and_promotes_a_future_header_once_current_time_catches_up() {
+        use bitcoin::hashes::Hash;
+
+        let buffering = BufferingHeaderValidator::new(FakeValidator(FakeStore));
+        let max_future_drift = buffering.max_future_drift();
+        let current_time = Duration::from_secs(1_000_000);
+
+        // More than `max_future_drift` ahead: rejected and parked, rather
+        // than discarded outright.
+        let header = header_with_time((current_time + max_future_drift).as_secs() as u32 + 60);
+        assert_eq!(
+            buffering.validate_header(&header, current_time),
+            Err(ValidateHeaderError::HeaderIsTooFarInFuture {
+                block_time: header.time as u64,
+                max_allowed_time: (current_time + max_future_drift).as_secs(),
+            })
+        );
+        assert_eq!(buffering.pending_len(), 1);
+        assert!(buffering.take_promoted().is_empty());
+
+        // Still not due: an unrelated call at the same `current_time`
+        // changes nothing.
+        let unrelated = header_with_time(0);
+        assert_eq!(buffering.validate_header(&unrelated, current_time), Ok(()));
+        assert_eq!(buffering.pending_len(), 1);
+
+        // Once `current_time` reaches `block_time - max_future_drift`, the
+        // parked header is promoted automatically -- the caller never has
+        // to resubmit it.
+        let earliest_valid_time = Duration::from_secs(header.time as u64) - max_future_drift;
+        assert_eq!(
+            buffering.validate_header(&unrelated, earliest_valid_time),
+            Ok(())
+        );
+        assert_eq!(buffering.pending_len(), 0);
+        assert_eq!(buffering.take_promoted(), vec![header]);
+
+        // Already drained: nothing left to take.
+        assert!(buffering.take_promoted().is_empty());
+    }
+}
@@ -0,0 +1,306 @@
+use crate::header::{HeaderStore, HeaderValidator, ValidateHeaderError};
+use crate::BlockHeight;
+use bitcoin::block::Header;
+use bitcoin::hashes::{sha256d, Hash};
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// Number of consecutive headers a single [`BatchCheckpoint`] covers.
+pub const BATCH_SIZE: usize = 512;
+
+/// A "hash of hashes" checkpoint over one [`BATCH_SIZE`]-header span of a
+/// known-good chain: the sha256d of the batch's block hashes concatenated
+/// in order, oldest to newest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchCheckpoint {
+    /// Height of the last header covered by this checkpoint.
+    pub height: BlockHeight,
+    /// sha256d over the concatenated block hashes of the batch ending at
+    /// `height`.
+    pub hash_of_hashes: sha256d::Hash,
+}
+
+/// Computes the [`BatchCheckpoint::hash_of_hashes`] value for `headers`:
+/// a single sha256d over their block hashes, concatenated in order.
+pub fn hash_of_hashes(headers: &[Header]) -> sha256d::Hash {
+    let mut buf = Vec::with_capacity(headers.len() * 32);
+    for header in headers {
+        buf.extend_from_slice(header.block_hash().as_ref());
+    }
+    sha256d::Hash::hash(&buf)
+}
+
+/// A compiled-in, ordered table of [`BatchCheckpoint`]s for a known-good
+/// header chain, used by [`CheckpointedHeaderValidator`] to fast-sync large
+/// spans of historical headers without paying per-header PoW validation
+/// cost for each of them.
+///
+/// Empty by default -- a deployment that wants the fast path populates this
+/// by generating checkpoints (in [`BATCH_SIZE`]-header batches via
+/// [`hash_of_hashes`]) from a trusted copy of the chain and shipping the
+/// resulting array as static data, the same way node implementations ship
+/// a compiled-in checkpoint list.
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointTable {
+    checkpoints: Vec<BatchCheckpoint>,
+}
+
+impl CheckpointTable {
+    /// Builds a table from `checkpoints`, which must already be ordered by
+    /// ascending `height`.
+    pub fn new(checkpoints: Vec<BatchCheckpoint>) -> Self {
+        Self { checkpoints }
+    }
+
+    /// Height of the highest checkpointed batch, or `None` if the table has
+    /// no checkpoints.
+    pub fn highest_height(&self) -> Option<BlockHeight> {
+        self.checkpoints.last().map(|checkpoint| checkpoint.height)
+    }
+
+    fn get(&self, height: BlockHeight) -> Option<&BatchCheckpoint> {
+        self.checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.height == height)
+    }
+}
+
+/// Wraps a [`HeaderValidator`] with a checkpoint-accelerated batch
+/// ingestion path: a caller feeding a long, contiguous span of headers
+/// (e.g. during initial sync) can call
+/// [`validate_headers`](Self::validate_headers) instead of validating one
+/// header at a time, and any [`BATCH_SIZE`]-header span whose
+/// [`hash_of_hashes`] matches an entry in the [`CheckpointTable`] is
+/// admitted after only a `prev_blockhash` continuity check and a per-header
+/// timestamp check -- skipping the PoW, retarget, and (for AuxPow chains)
+/// merge-mining validation [`HeaderValidator::validate_header`] would
+/// otherwise perform. Headers above the highest checkpoint, or a batch
+/// whose hash doesn't match, fall back to full validation via the wrapped
+/// validator.
+///
+/// Tracks how many headers took each path via
+/// [`checkpointed_count`](Self::checkpointed_count) and
+/// [`fully_verified_count`](Self::fully_verified_count), so a caller (e.g.
+/// a canister's `get_metrics` endpoint) can report initial-sync progress
+/// and how much of it was checkpoint-accelerated.
+pub struct CheckpointedHeaderValidator<V> {
+    inner: V,
+    table: CheckpointTable,
+    checkpointed_count: RefCell<u64>,
+    fully_verified_count: RefCell<u64>,
+}
+
+impl<V> CheckpointedHeaderValidator<V> {
+    pub fn new(inner: V, table: CheckpointTable) -> Self {
+        Self {
+            inner,
+            table,
+            checkpointed_count: RefCell::new(0),
+            fully_verified_count: RefCell::new(0),
+        }
+    }
+
+    /// Number of headers admitted so far via a matching [`BatchCheckpoint`],
+    /// with only the continuity and per-header timestamp checks.
+    pub fn checkpointed_count(&self) -> u64 {
+        *self.checkpointed_count.borrow()
+    }
+
+    /// Number of headers admitted so far via full validation by the
+    /// wrapped validator.
+    pub fn fully_verified_count(&self) -> u64 {
+        *self.fully_verified_count.borrow()
+    }
+
+    /// Unwraps this validator, discarding the checkpoint table and counters.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V: HeaderValidator> CheckpointedHeaderValidator<V> {
+    /// Validates and admits a contiguous run of `headers` extending the
+    /// current tip, fast-pathing any prefix covered by the
+    /// [`CheckpointTable`]. See the type-level docs for the admission
+    /// rules. Headers are added to the wrapped [`HeaderStore`] as they're
+    /// admitted, so later headers in `headers` can extend earlier ones in
+    /// the same call.
+    pub fn validate_headers(
+        &mut self,
+        headers: &[Header],
+        current_time: Duration,
+    ) -> Result<(), ValidateHeaderError> {
+        let mut index = 0;
+        while index < headers.len() {
+            let height = self.inner.store().height();
+            let batch_len = (headers.len() - index).min(BATCH_SIZE);
+            let batch = &headers[index..index + batch_len];
+            let batch_end_height = height + batch_len as BlockHeight;
+
+            let checkpoint_hash_matches = self
+                .table
+                .get(batch_end_height)
+                .map(|checkpoint| checkpoint.hash_of_hashes == hash_of_hashes(batch))
+                .unwrap_or(false);
+
+            if batch_len == BATCH_SIZE && checkpoint_hash_matches {
+                let mut prev_hash = self
+                    .inner
+                    .store()
+                    .get_with_height(height)
+                    .expect("tip header not found in store")
+                    .block_hash();
+                for header in batch {
+                    if header.prev_blockhash != prev_hash {
+                        return Err(ValidateHeaderError::PrevHeaderNotFound);
+                    }
+                    // A matching hash-of-hashes only vouches for the batch's
+                    // internal consistency with the trusted chain it was
+                    // computed from; it says nothing about the *context*
+                    // this particular caller is ingesting into, so
+                    // timestamp validity is still checked per header even
+                    // though PoW/AuxPow verification is skipped.
+                    self.inner.is_timestamp_valid(header, current_time)?;
+                    prev_hash = header.block_hash();
+                    self.inner.store_mut().add(*header);
+                }
+                *self.checkpointed_count.borrow_mut() += batch_len as u64;
+                index += batch_len;
+            } else {
+                let header = &headers[index];
+                self.inner.validate_header(header, current_time)?;
+                self.inner.store_mut().add(*header);
+                *self.fully_verified_count.borrow_mut() += 1;
+                index += 1;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SimpleHeaderStore;
+    use crate::header::tests::utils::{build_header_chain, MOCK_CURRENT_TIME};
+    use crate::DogecoinHeaderValidator;
+    use bitcoin::dogecoin::constants::genesis_block;
+    use bitcoin::dogecoin::Network as DogecoinNetwork;
+
+    fn chain_of(len: u32) -> (DogecoinHeaderValidator<SimpleHeaderStore>, Vec<Header>) {
+        let genesis_header = genesis_block(DogecoinNetwork::Regtest).header;
+        let store = SimpleHeaderStore::new(*genesis_header, 0);
+        let mut validator = DogecoinHeaderValidator::regtest(store);
+        build_header_chain(&mut validator, len + 1);
+
+        let mut headers = Vec::new();
+        for height in 1..=len {
+            headers.push(validator.store().get_with_height(height).unwrap());
+        }
+        (validator, headers)
+    }
+
+    #[test]
+    fn checkpointed_batch_is_admitted_without_full_validation() {
+        let (validator, headers) = chain_of(BATCH_SIZE as u32);
+        let checkpoint = BatchCheckpoint {
+            height: BATCH_SIZE as BlockHeight,
+            hash_of_hashes: hash_of_hashes(&headers),
+        };
+
+        let genesis_header = genesis_block(DogecoinNetwork::Regtest).header;
+        let store = SimpleHeaderStore::new(*genesis_header, 0);
+        let fresh_validator = DogecoinHeaderValidator::regtest(store);
+        let mut checkpointed = CheckpointedHeaderValidator::new(
+            fresh_validator,
+            CheckpointTable::new(vec![checkpoint]),
+        );
+
+        assert!(checkpointed
+            .validate_headers(&headers, MOCK_CURRENT_TIME)
+            .is_ok());
+        assert_eq!(checkpointed.checkpointed_count(), BATCH_SIZE as u64);
+        assert_eq!(checkpointed.fully_verified_count(), 0);
+        assert_eq!(checkpointed.inner.store().height(), BATCH_SIZE as u32);
+
+        let _ = validator;
+    }
+
+    #[test]
+    fn checkpointed_batch_still_rejects_a_header_older_than_the_mtp() {
+        let (_, mut headers) = chain_of(BATCH_SIZE as u32);
+
+        // Rewind the last header's timestamp below the median of its
+        // ancestors, then checkpoint the batch as mutated -- the
+        // hash-of-hashes matches, so the fast path is taken, but per-header
+        // timestamp validity must still be enforced within it.
+        let last = headers.len() - 1;
+        headers[last].time = headers[0].time;
+
+        let checkpoint = BatchCheckpoint {
+            height: BATCH_SIZE as BlockHeight,
+            hash_of_hashes: hash_of_hashes(&headers),
+        };
+
+        let genesis_header = genesis_block(DogecoinNetwork::Regtest).header;
+        let store = SimpleHeaderStore::new(*genesis_header, 0);
+        let fresh_validator = DogecoinHeaderValidator::regtest(store);
+        let mut checkpointed = CheckpointedHeaderValidator::new(
+            fresh_validator,
+            CheckpointTable::new(vec![checkpoint]),
+        );
+
+        assert_eq!(
+            checkpointed.validate_headers(&headers, MOCK_CURRENT_TIME),
+            Err(ValidateHeaderError::HeaderIsOld)
+        );
+    }
+
+    #[test]
+    fn mismatched_batch_falls_back_to_full_validation() {
+        let (_, headers) = chain_of(BATCH_SIZE as u32);
+        // A checkpoint whose hash doesn't correspond to the real batch.
+        let bogus_checkpoint = BatchCheckpoint {
+            height: BATCH_SIZE as BlockHeight,
+            hash_of_hashes: sha256d::Hash::hash(b"not the real batch"),
+        };
+
+        let genesis_header = genesis_block(DogecoinNetwork::Regtest).header;
+        let store = SimpleHeaderStore::new(*genesis_header, 0);
+        let fresh_validator = DogecoinHeaderValidator::regtest(store);
+        let mut checkpointed = CheckpointedHeaderValidator::new(
+            fresh_validator,
+            CheckpointTable::new(vec![bogus_checkpoint]),
+        );
+
+        assert!(checkpointed
+            .validate_headers(&headers, MOCK_CURRENT_TIME)
+            .is_ok());
+        assert_eq!(checkpointed.checkpointed_count(), 0);
+        assert_eq!(checkpointed.fully_verified_count(), BATCH_SIZE as u64);
+    }
+
+    #[test]
+    fn headers_above_the_highest_checkpoint_are_fully_verified() {
+        let (_, headers) = chain_of(BATCH_SIZE as u32 + 10);
+        let checkpoint = BatchCheckpoint {
+            height: BATCH_SIZE as BlockHeight,
+            hash_of_hashes: hash_of_hashes(&headers[..BATCH_SIZE]),
+        };
+
+        let genesis_header = genesis_block(DogecoinNetwork::Regtest).header;
+        let store = SimpleHeaderStore::new(*genesis_header, 0);
+        let fresh_validator = DogecoinHeaderValidator::regtest(store);
+        let mut checkpointed = CheckpointedHeaderValidator::new(
+            fresh_validator,
+            CheckpointTable::new(vec![checkpoint]),
+        );
+
+        assert!(checkpointed
+            .validate_headers(&headers, MOCK_CURRENT_TIME)
+            .is_ok());
+        assert_eq!(checkpointed.checkpointed_count(), BATCH_SIZE as u64);
+        assert_eq!(checkpointed.fully_verified_count(), 10);
+    }
+}
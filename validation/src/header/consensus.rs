@@ -0,0 +1,98 @@
+use crate::header::doge::DIGISHIELD_ACTIVATION_HEIGHT;
+use crate::header::ConsensusParamsOverride;
+use bitcoin::dogecoin::Network as DogecoinNetwork;
+use bitcoin::{CompactTarget, Target};
+use std::time::Duration;
+
+/// Centralizes the chain-specific consensus constants that drive Dogecoin
+/// header and AuxPow validation, built once from a [`DogecoinNetwork`]
+/// (plus any [`ConsensusParamsOverride`]) instead of having each validator
+/// method re-derive them from `network.params()` -- and, for Regtest, an
+/// `unreachable!()`-guarded match on the network enum.
+///
+/// Mirrors how comparable node implementations thread a single
+/// consensus-parameters object (fork points, network magic, etc.) through
+/// verification rather than branching on a network enum throughout. Since
+/// it's just a plain value, a test can also build one directly to inspect a
+/// network's fork points without constructing a validator at all.
+#[derive(Debug, Clone, Copy)]
+pub struct ConsensusParams {
+    network: DogecoinNetwork,
+    /// Target spacing between blocks.
+    pub pow_target_spacing: Duration,
+    /// Maximum difficulty target (i.e. the minimum difficulty) allowed on
+    /// the network.
+    pub max_target: Target,
+    /// [`max_target`](Self::max_target), as compact bits.
+    pub pow_limit_bits: CompactTarget,
+    /// Height at which Dogecoin's per-block Digishield retarget activates.
+    /// Distinct from the (later) height at which the
+    /// min-difficulty-after-a-delay rule becomes active within the
+    /// Digishield era -- see
+    /// [`allow_min_difficulty_blocks`](crate::header::HeaderValidator::allow_min_difficulty_blocks),
+    /// which is sourced straight from `network.params()` instead.
+    pub digishield_activation_height: u32,
+    /// The AuxPow chain id this network mines under, and that its
+    /// merged-mining parent must *not* also claim.
+    pub auxpow_chain_id: i32,
+    /// Whether a header with a chain id other than
+    /// [`auxpow_chain_id`](Self::auxpow_chain_id) is rejected outright.
+    pub strict_chain_id: bool,
+}
+
+impl ConsensusParams {
+    /// Builds the consensus parameters for `network`, with any of the
+    /// runtime [`overrides`](ConsensusParamsOverride) applied on top --
+    /// e.g. a zero or small `digishield_activation_height` so a Regtest
+    /// test can reach the fork boundary in a handful of blocks.
+    pub fn new(network: DogecoinNetwork, overrides: ConsensusParamsOverride) -> Self {
+        let params = network.params();
+        Self {
+            network,
+            pow_target_spacing: overrides
+                .pow_target_spacing
+                .unwrap_or_else(|| Duration::from_secs(params.pow_target_spacing as u64)),
+            max_target: params.max_attainable_target,
+            pow_limit_bits: params.max_attainable_target.to_compact_lossy(),
+            digishield_activation_height: overrides
+                .digishield_activation_height
+                .unwrap_or(DIGISHIELD_ACTIVATION_HEIGHT),
+            auxpow_chain_id: params.auxpow_chain_id,
+            strict_chain_id: params.strict_chain_id,
+        }
+    }
+
+    /// The network these parameters were derived from.
+    pub fn network(&self) -> DogecoinNetwork {
+        self.network
+    }
+}
+
+impl From<DogecoinNetwork> for ConsensusParams {
+    fn from(network: DogecoinNetwork) -> Self {
+        Self::new(network, ConsensusParamsOverride::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regtest_override_replaces_mainnet_activation_height() {
+        let mainnet = ConsensusParams::from(DogecoinNetwork::Dogecoin);
+        assert_eq!(
+            mainnet.digishield_activation_height,
+            DIGISHIELD_ACTIVATION_HEIGHT
+        );
+
+        let regtest = ConsensusParams::new(
+            DogecoinNetwork::Regtest,
+            ConsensusParamsOverride {
+                digishield_activation_height: Some(0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(regtest.digishield_activation_height, 0);
+    }
+}
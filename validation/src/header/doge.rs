@@ -1,5 +1,7 @@
+use crate::header::consensus::ConsensusParams;
+use crate::header::retarget::DifficultyRetarget;
 use crate::header::{
-    is_timestamp_valid, AuxPowHeaderValidator, HeaderStore, HeaderValidator,
+    AuxPowHeaderValidator, ConsensusParamsOverride, HeaderStore, HeaderValidator,
     ValidateAuxPowHeaderError, ValidateHeaderError,
 };
 use crate::BlockHeight;
@@ -13,18 +15,118 @@ use std::time::Duration;
 /// Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/dogecoin.cpp#L33>
 pub(crate) const ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT: u32 = 157_500;
 
-pub struct DogecoinHeaderValidator<T> {
+/// Mainnet height at which Dogecoin's per-block Digishield retarget itself
+/// activates -- a distinct, earlier height than
+/// [`ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT`], which only gates the
+/// min-difficulty-after-a-delay rule *within* the Digishield era.
+/// Ref: <https://github.com/dogecoin/dogecoin/blob/1be681a1b97b686f838af90682a57f2030d26015/src/pow.cpp#L32>
+pub(crate) const DIGISHIELD_ACTIVATION_HEIGHT: u32 = 145_000;
+
+/// Dogecoin's pre-Digishield retarget: difficulty is readjusted only on
+/// fixed [`difficulty_adjustment_interval`](HeaderValidator::difficulty_adjustment_interval)
+/// boundaries, with the min-difficulty-after-a-delay rule (for testnet and
+/// regtest) falling back to
+/// [`find_next_difficulty_in_chain`](HeaderValidator::find_next_difficulty_in_chain)
+/// between boundaries. Used for any height before
+/// [`is_digishield_activated`](HeaderValidator::is_digishield_activated);
+/// kept independently testable so the Time-Warp-fix `-1` offset in
+/// [`compute_next_difficulty`](HeaderValidator::compute_next_difficulty)
+/// and the interval-boundary logic here can each be exercised in isolation.
+pub struct LegacyRetarget;
+
+impl<V> DifficultyRetarget<V> for LegacyRetarget
+where
+    V: HeaderValidator<Network = DogecoinNetwork>,
+{
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &PureHeader,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        let height = prev_height + 1;
+
+        if height % validator.difficulty_adjustment_interval(height) != 0 {
+            if validator.allow_min_difficulty_blocks(height) {
+                if timestamp
+                    > prev_header.time + (validator.pow_target_spacing() * 2).as_secs() as u32
+                {
+                    // If no block has been found in twice `pow_target_spacing`, then use
+                    // the maximum difficulty target
+                    return validator.max_target();
+                } else {
+                    // If the block has been found within twice `pow_target_spacing`, then
+                    // use the previous difficulty target that is not equal to the maximum
+                    // difficulty target
+                    return Target::from_compact(
+                        validator.find_next_difficulty_in_chain(prev_header, prev_height),
+                    );
+                };
+            }
+            return Target::from_compact(prev_header.bits);
+        };
+
+        Target::from_compact(validator.compute_next_difficulty(prev_header, prev_height))
+    }
+}
+
+/// The default [`DifficultyRetarget`] strategy: Dogecoin's own
+/// height-gated retarget, which switches from [`LegacyRetarget`]'s fixed
+/// 240-block interval to an every-block ("Digishield") adjustment at
+/// [`DIGISHIELD_ACTIVATION_HEIGHT`]. This is the exact rule
+/// `DogecoinHeaderValidator` always enforced before the retarget algorithm
+/// became pluggable; it's kept as a standalone, independently testable
+/// strategy so alternatives (like
+/// [`SlidingWindowRetarget`](crate::header::retarget::SlidingWindowRetarget))
+/// can be swapped in for chains that need a different rule.
+pub struct DigishieldRetarget;
+
+impl<V> DifficultyRetarget<V> for DigishieldRetarget
+where
+    V: HeaderValidator<Network = DogecoinNetwork>,
+{
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &PureHeader,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        // Dogecoin core ref: <https://github.com/dogecoin/dogecoin/blob/1be681a1b97b686f838af90682a57f2030d26015/src/pow.cpp#L32>
+        let height = prev_height + 1;
+
+        if !validator.is_digishield_activated(height) {
+            return LegacyRetarget.next_target(validator, prev_header, prev_height, timestamp);
+        }
+
+        if validator.allow_digishield_min_difficulty_for_block(prev_header, height, timestamp) {
+            // If no block has been found in twice `pow_target_spacing`, then use
+            // the maximum difficulty target
+            return validator.max_target();
+        }
+
+        // Once Digishield is active, `difficulty_adjustment_interval` is 1,
+        // so every block is an interval boundary: go straight to
+        // recomputing the target rather than routing back through
+        // `LegacyRetarget`'s boundary check.
+        Target::from_compact(validator.compute_next_difficulty(prev_header, prev_height))
+    }
+}
+
+pub struct DogecoinHeaderValidator<T, R = DigishieldRetarget> {
     store: T,
     network: DogecoinNetwork,
+    retarget: R,
+    params_override: ConsensusParamsOverride,
+    consensus_params: ConsensusParams,
 }
 
-impl<T> DogecoinHeaderValidator<T> {
+impl<T> DogecoinHeaderValidator<T, DigishieldRetarget> {
     pub fn new(store: T, network: DogecoinNetwork) -> Self {
-        Self { store, network }
+        Self::with_retarget(store, network, DigishieldRetarget)
     }
-}
 
-impl<T: HeaderStore> DogecoinHeaderValidator<T> {
     pub fn mainnet(store: T) -> Self {
         Self::new(store, DogecoinNetwork::Dogecoin)
     }
@@ -36,7 +138,55 @@ impl<T: HeaderStore> DogecoinHeaderValidator<T> {
     pub fn regtest(store: T) -> Self {
         Self::new(store, DogecoinNetwork::Regtest)
     }
+}
 
+impl<T, R> DogecoinHeaderValidator<T, R> {
+    /// Builds a validator that retargets difficulty using a custom
+    /// strategy, e.g.
+    /// [`SlidingWindowRetarget`](crate::header::retarget::SlidingWindowRetarget)
+    /// for merge-mined or forked chains that retarget every block instead
+    /// of on Dogecoin's own interval.
+    pub fn with_retarget(store: T, network: DogecoinNetwork, retarget: R) -> Self {
+        Self::with_retarget_and_params_override(
+            store,
+            network,
+            retarget,
+            ConsensusParamsOverride::default(),
+        )
+    }
+
+    /// Builds a validator with both a custom retarget strategy and one or
+    /// more consensus parameters overridden at runtime, so a test can
+    /// simulate mining at an arbitrary speed -- reaching a retarget or
+    /// Digishield-activation boundary in a handful of blocks instead of
+    /// thousands -- without touching mainnet rules.
+    pub fn with_retarget_and_params_override(
+        store: T,
+        network: DogecoinNetwork,
+        retarget: R,
+        params_override: ConsensusParamsOverride,
+    ) -> Self {
+        let consensus_params = ConsensusParams::new(network, params_override);
+        Self {
+            store,
+            network,
+            retarget,
+            params_override,
+            consensus_params,
+        }
+    }
+
+    /// The [`ConsensusParams`] in effect for this validator, i.e. the
+    /// network's built-in constants with any
+    /// [`ConsensusParamsOverride`] applied on top.
+    pub fn consensus_params(&self) -> &ConsensusParams {
+        &self.consensus_params
+    }
+}
+
+impl<T: HeaderStore, R: DifficultyRetarget<DogecoinHeaderValidator<T, R>>>
+    DogecoinHeaderValidator<T, R>
+{
     /// Context-dependent header validity checks
     /// Ref: <https://github.com/dogecoin/dogecoin/blob/215fc33d08ef55cdb52a639bb2d8ce0af502c126/src/validation.cpp#L3065>
     fn contextual_check_header(
@@ -61,7 +211,7 @@ impl<T: HeaderStore> DogecoinHeaderValidator<T> {
             return Err(ValidateAuxPowHeaderError::AuxPowBlockNotAllowed.into());
         }
 
-        is_timestamp_valid(&self.store, header, current_time)?;
+        self.is_timestamp_valid(header, current_time)?;
 
         if (header.extract_base_version() < 3 && height >= self.network().params().bip66_height)
             || (header.extract_base_version() < 4 && height >= self.network().params().bip65_height)
@@ -78,15 +228,20 @@ impl<T: HeaderStore> DogecoinHeaderValidator<T> {
 
         let header_target = header.target();
         if target != header_target {
-            println!("bad target");
-            return Err(ValidateHeaderError::InvalidPoWForComputedTarget);
+            return Err(ValidateHeaderError::TargetMismatch {
+                block_hash: header.block_hash(),
+                expected_target: target.to_compact_lossy(),
+                header_target: header_target.to_compact_lossy(),
+            });
         }
 
         Ok(target)
     }
 }
 
-impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
+impl<T: HeaderStore, R: DifficultyRetarget<DogecoinHeaderValidator<T, R>>> HeaderValidator
+    for DogecoinHeaderValidator<T, R>
+{
     type Network = DogecoinNetwork;
     type Store = T;
 
@@ -103,7 +258,7 @@ impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
     }
 
     fn max_target(&self) -> Target {
-        self.network().params().max_attainable_target
+        self.consensus_params.max_target
     }
 
     fn no_pow_retargeting(&self) -> bool {
@@ -111,25 +266,30 @@ impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
     }
 
     fn pow_limit_bits(&self) -> CompactTarget {
-        self.network()
-            .params()
-            .max_attainable_target
-            .to_compact_lossy()
+        self.consensus_params.pow_limit_bits
     }
 
     fn pow_target_spacing(&self) -> Duration {
-        Duration::from_secs(self.network().params().pow_target_spacing as u64)
+        self.consensus_params.pow_target_spacing
     }
 
     fn difficulty_adjustment_interval(&self, height: u32) -> u32 {
-        (self.network().params().pow_target_timespan(height)
-            / self.network().params().pow_target_spacing) as u32
+        self.params_override
+            .difficulty_adjustment_interval
+            .unwrap_or_else(|| {
+                (self.network().params().pow_target_timespan(height)
+                    / self.network().params().pow_target_spacing) as u32
+            })
     }
 
     fn allow_min_difficulty_blocks(&self, height: u32) -> bool {
         self.network().params().allow_min_difficulty_blocks(height)
     }
 
+    fn digishield_activation_height(&self) -> u32 {
+        self.consensus_params.digishield_activation_height
+    }
+
     fn validate_header(
         &self,
         header: &PureHeader,
@@ -137,13 +297,12 @@ impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
     ) -> Result<(), ValidateHeaderError> {
         let target = self.contextual_check_header(header, current_time)?;
 
-        if let Err(err) = header.validate_pow_with_scrypt(target) {
-            match err {
-                bitcoin::block::ValidationError::BadProofOfWork => println!("bad proof of work"),
-                bitcoin::block::ValidationError::BadTarget => println!("bad target"),
-                _ => {}
-            };
-            return Err(ValidateHeaderError::InvalidPoWForComputedTarget);
+        if header.validate_pow_with_scrypt(target).is_err() {
+            return Err(ValidateHeaderError::InvalidPoWForComputedTarget {
+                block_hash: header.block_hash(),
+                target: target.to_compact_lossy(),
+                computed_hash: header.block_hash_with_scrypt(),
+            });
         }
 
         Ok(())
@@ -155,38 +314,8 @@ impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
         prev_height: BlockHeight,
         timestamp: u32,
     ) -> Target {
-        // Dogecoin core ref: <https://github.com/dogecoin/dogecoin/blob/1be681a1b97b686f838af90682a57f2030d26015/src/pow.cpp#L32>
-        let height = prev_height + 1;
-
-        if height >= ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT
-            && self.allow_min_difficulty_blocks(height)
-            && timestamp > prev_header.time + (self.pow_target_spacing() * 2).as_secs() as u32
-        {
-            // If no block has been found in `pow_target_spacing * 2` minutes, then use
-            // the maximum difficulty target
-            return self.max_target();
-        }
-
-        if height % self.difficulty_adjustment_interval(height) != 0 {
-            if self.allow_min_difficulty_blocks(height) {
-                if timestamp > prev_header.time + (self.pow_target_spacing() * 2).as_secs() as u32 {
-                    // If no block has been found in `pow_target_spacing * 2` minutes, then use
-                    // the maximum difficulty target
-                    return self.max_target();
-                } else {
-                    // If the block has been found within `pow_target_spacing * 2` minutes, then
-                    // use the previous difficulty target that is not equal to the maximum
-                    // difficulty target
-                    return Target::from_compact(self.find_next_difficulty_in_chain(
-                        prev_header,
-                        prev_height,
-                    ));
-                };
-            }
-            return Target::from_compact(prev_header.bits);
-        };
-
-        Target::from_compact(self.compute_next_difficulty(prev_header, prev_height))
+        self.retarget
+            .next_target(self, prev_header, prev_height, timestamp)
     }
 
     fn find_next_difficulty_in_chain(
@@ -284,13 +413,15 @@ impl<T: HeaderStore> HeaderValidator for DogecoinHeaderValidator<T> {
     }
 }
 
-impl<T: HeaderStore> AuxPowHeaderValidator for DogecoinHeaderValidator<T> {
+impl<T: HeaderStore, R: DifficultyRetarget<DogecoinHeaderValidator<T, R>>> AuxPowHeaderValidator
+    for DogecoinHeaderValidator<T, R>
+{
     fn strict_chain_id(&self) -> bool {
-        self.network().params().strict_chain_id
+        self.consensus_params.strict_chain_id
     }
 
     fn auxpow_chain_id(&self) -> i32 {
-        self.network().params().auxpow_chain_id
+        self.consensus_params.auxpow_chain_id
     }
 
     fn allow_legacy_blocks(&self, height: u32) -> bool {
@@ -321,6 +452,14 @@ impl<T: HeaderStore> AuxPowHeaderValidator for DogecoinHeaderValidator<T> {
             if !target.is_met_by(aux_pow.parent_block_header.block_hash_with_scrypt()) {
                 return Err(ValidateAuxPowHeaderError::InvalidParentPoW.into());
             }
+            // The parent block must not itself claim our chain id -- otherwise a
+            // block could serve as its own AuxPow parent.
+            // Ref: <https://github.com/dogecoin/dogecoin/blob/51cbc1fd5d0d045dda2ad84f53572bbf524c6a8e/src/auxpow.cpp#L75>
+            if self.strict_chain_id()
+                && aux_pow.parent_block_header.extract_chain_id() == self.auxpow_chain_id()
+            {
+                return Err(ValidateAuxPowHeaderError::ParentHasAuxChainId.into());
+            }
             if aux_pow
                 .check(
                     header.block_hash(),
@@ -0,0 +1,201 @@
+//! Bootstraps a [`HeaderStore`] from an operator-supplied flat file instead
+//! of syncing headers from peers, and snapshots a store back out the same
+//! way.
+//!
+//! Reuses the CSV schema the test fixtures already use (see
+//! `tests/utils.rs`): one row per header, `version, prev_blockhash,
+//! merkle_root, time, bits, nonce`, with seven more fields appended for
+//! AuxPow headers (`coinbase_tx, parent_hash, coinbase_branch,
+//! coinbase_index, blockchain_branch, blockchain_index,
+//! parent_block_header`). Unlike the test helpers, every row here is run
+//! through full [`HeaderValidator`] checks before being added, and a
+//! malformed or invalid row is reported rather than panicking.
+
+#[cfg(feature = "doge")]
+use crate::header::AuxPowHeaderValidator;
+use crate::header::{HeaderValidator, ValidateHeaderError};
+use crate::HeaderStore;
+use bitcoin::block::{Header, Version};
+use bitcoin::consensus::deserialize;
+#[cfg(feature = "doge")]
+use bitcoin::dogecoin::{auxpow::AuxPow, Header as AuxPowHeader};
+use bitcoin::hashes::hex::FromHex;
+use bitcoin::{BlockHash, CompactTarget, TxMerkleNode};
+use csv::{StringRecord, Writer};
+use std::io::{Read, Write as IoWrite};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A row that couldn't be turned into a header, or a header that failed
+/// [`HeaderValidator::validate_header`] / [`AuxPowHeaderValidator::validate_auxpow_header`].
+///
+/// Carries the zero-based CSV row index so an operator can locate the
+/// offending line in the source file.
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    /// The CSV reader itself failed (short row, I/O error, ...).
+    Csv { row: usize, message: String },
+    /// A field parsed but didn't decode into the type it's supposed to be.
+    MalformedField { row: usize, field: &'static str },
+    /// The reconstructed header failed validation.
+    Validation { row: usize, source: ValidateHeaderError },
+}
+
+fn field<'a>(record: &'a StringRecord, index: usize, name: &'static str) -> Result<&'a str, &'static str> {
+    record.get(index).ok_or(name)
+}
+
+fn header_from_csv_record(record: &StringRecord) -> Result<Header, &'static str> {
+    let version = i32::from_str_radix(field(record, 0, "version")?, 16).map_err(|_| "version")?;
+    let prev_blockhash =
+        BlockHash::from_str(field(record, 1, "prev_blockhash")?).map_err(|_| "prev_blockhash")?;
+    let merkle_root =
+        TxMerkleNode::from_str(field(record, 2, "merkle_root")?).map_err(|_| "merkle_root")?;
+    let time = u32::from_str_radix(field(record, 3, "time")?, 16).map_err(|_| "time")?;
+    let bits = u32::from_str_radix(field(record, 4, "bits")?, 16).map_err(|_| "bits")?;
+    let nonce = u32::from_str_radix(field(record, 5, "nonce")?, 16).map_err(|_| "nonce")?;
+    Ok(Header {
+        version: Version::from_consensus(version),
+        prev_blockhash,
+        merkle_root,
+        time,
+        bits: CompactTarget::from_consensus(bits),
+        nonce,
+    })
+}
+
+#[cfg(feature = "doge")]
+fn auxpow_from_csv_record(record: &StringRecord) -> Result<AuxPow, &'static str> {
+    let coinbase_tx_bytes =
+        Vec::from_hex(field(record, 6, "coinbase_tx")?).map_err(|_| "coinbase_tx")?;
+    let parent_hash =
+        BlockHash::from_str(field(record, 7, "parent_hash")?).map_err(|_| "parent_hash")?;
+    let coinbase_branch_bytes =
+        Vec::from_hex(field(record, 8, "coinbase_branch")?).map_err(|_| "coinbase_branch")?;
+    let coinbase_index_bytes =
+        hex::decode(field(record, 9, "coinbase_index")?).map_err(|_| "coinbase_index")?;
+    let blockchain_branch_bytes =
+        Vec::from_hex(field(record, 10, "blockchain_branch")?).map_err(|_| "blockchain_branch")?;
+    let blockchain_index_bytes =
+        hex::decode(field(record, 11, "blockchain_index")?).map_err(|_| "blockchain_index")?;
+    let parent_block_header_bytes =
+        Vec::from_hex(field(record, 12, "parent_block_header")?).map_err(|_| "parent_block_header")?;
+
+    Ok(AuxPow {
+        coinbase_tx: deserialize(&coinbase_tx_bytes).map_err(|_| "coinbase_tx")?,
+        parent_hash,
+        coinbase_branch: deserialize(&coinbase_branch_bytes).map_err(|_| "coinbase_branch")?,
+        coinbase_index: i32::from_le_bytes(
+            coinbase_index_bytes.try_into().map_err(|_| "coinbase_index")?,
+        ),
+        blockchain_branch: deserialize(&blockchain_branch_bytes)
+            .map_err(|_| "blockchain_branch")?,
+        blockchain_index: i32::from_le_bytes(
+            blockchain_index_bytes
+                .try_into()
+                .map_err(|_| "blockchain_index")?,
+        ),
+        parent_block_header: deserialize(&parent_block_header_bytes)
+            .map_err(|_| "parent_block_header")?,
+    })
+}
+
+/// Streams pure (non-AuxPow) headers from `reader` into `validator`'s store,
+/// one row at a time: each row is parsed, run through
+/// [`HeaderValidator::validate_header`] -- which also rejects a row whose
+/// `prev_blockhash` doesn't extend a header already in the store, via
+/// [`ValidateHeaderError::PrevHeaderNotFound`] -- and only added on success.
+///
+/// Stops at the first row that fails to parse or validate and returns its
+/// [`ImportError`]; headers added before it stay in the store. Otherwise
+/// returns how many headers were added.
+pub fn import_headers_csv<V: HeaderValidator>(
+    validator: &mut V,
+    reader: impl Read,
+    current_time: Duration,
+) -> Result<usize, ImportError> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let mut imported = 0;
+    for (row, result) in rdr.records().enumerate() {
+        let record = result.map_err(|err| ImportError::Csv {
+            row,
+            message: err.to_string(),
+        })?;
+        let header =
+            header_from_csv_record(&record).map_err(|field| ImportError::MalformedField { row, field })?;
+        validator
+            .validate_header(&header, current_time)
+            .map_err(|source| ImportError::Validation { row, source })?;
+        validator.store_mut().add(header);
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+/// As [`import_headers_csv`], but for rows that may carry AuxPow data: a row
+/// whose parsed header has [`Header::has_auxpow_bit`] set is expected to
+/// also have the seven trailing AuxPow fields, and is run through
+/// [`AuxPowHeaderValidator::validate_auxpow_header`] instead of
+/// [`HeaderValidator::validate_header`]. Only the pure header is retained in
+/// the store, matching [`HeaderStore::add`]'s signature -- the AuxPow proof
+/// itself is not persisted.
+#[cfg(feature = "doge")]
+pub fn import_auxpow_headers_csv<V: AuxPowHeaderValidator>(
+    validator: &mut V,
+    reader: impl Read,
+    current_time: Duration,
+) -> Result<usize, ImportError> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let mut imported = 0;
+    for (row, result) in rdr.records().enumerate() {
+        let record = result.map_err(|err| ImportError::Csv {
+            row,
+            message: err.to_string(),
+        })?;
+        let pure_header =
+            header_from_csv_record(&record).map_err(|field| ImportError::MalformedField { row, field })?;
+        let aux_pow = pure_header
+            .has_auxpow_bit()
+            .then(|| auxpow_from_csv_record(&record))
+            .transpose()
+            .map_err(|field| ImportError::MalformedField { row, field })?;
+        let header = AuxPowHeader { pure_header, aux_pow };
+        validator
+            .validate_auxpow_header(&header, current_time)
+            .map_err(|source| ImportError::Validation { row, source })?;
+        validator.store_mut().add(header.pure_header);
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+/// Writes every header from height `0` to `store.height()` out to `writer`
+/// in the same six-field schema [`import_headers_csv`] reads, so a snapshot
+/// taken with this function round-trips through that import function.
+///
+/// Only the pure header fields are emitted: a [`HeaderStore`] retains
+/// headers, not the AuxPow proofs that accompanied them on the way in, so
+/// this alone cannot reconstruct the AuxPow columns
+/// [`import_auxpow_headers_csv`] expects -- a deployment that needs a fully
+/// round-trippable AuxPow snapshot must retain those proofs separately.
+pub fn export_headers_csv<S: HeaderStore>(
+    store: &S,
+    writer: impl IoWrite,
+) -> Result<(), csv::Error> {
+    let mut wtr = Writer::from_writer(writer);
+    for height in 0..=store.height() {
+        let header = store
+            .get_with_height(height)
+            .expect("header within [0, store.height()] must be present");
+        wtr.write_record([
+            format!("{:08x}", header.version.to_consensus()),
+            header.prev_blockhash.to_string(),
+            header.merkle_root.to_string(),
+            format!("{:08x}", header.time),
+            format!("{:08x}", header.bits.to_consensus()),
+            format!("{:08x}", header.nonce),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
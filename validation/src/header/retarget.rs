@@ -0,0 +1,338 @@
+use crate::header::{HeaderStore, HeaderValidator};
+use crate::BlockHeight;
+use bitcoin::block::Header;
+use bitcoin::{CompactTarget, Target};
+use std::time::Duration;
+
+/// A pluggable difficulty-retarget algorithm.
+///
+/// `HeaderValidator::get_next_target` used to hard-code one retarget rule
+/// per network. Implementing this trait instead lets a
+/// [`HeaderValidator`] accept any retarget rule as a strategy object, so
+/// new ones (e.g. for merge-mined or forked chains that retarget every
+/// block) can be added, and tested in isolation, without touching the
+/// validator itself.
+pub trait DifficultyRetarget<V: HeaderValidator> {
+    /// Computes the target the header following `prev_header` (at
+    /// `prev_height + 1`, with the given `timestamp`) must meet.
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target;
+}
+
+/// The window, in blocks, over which [`SlidingWindowRetarget`] measures
+/// chainwork and elapsed time.
+const WINDOW: BlockHeight = 144;
+
+/// A "cw-144" sliding-window retarget, as used by some merge-mined Dogecoin
+/// forks to adjust difficulty every block instead of on a fixed interval.
+///
+/// The target is derived from the chainwork produced over the last
+/// [`WINDOW`] blocks and the time it took to produce it: `B_last` and
+/// `B_first` are each the median-timestamp block of a 3-block window (at
+/// the tip, and `WINDOW` blocks back respectively), and the new target is
+/// set so that the chainwork observed between them would, at the network's
+/// target spacing, take exactly as long to reproduce.
+pub struct SlidingWindowRetarget {
+    max_target: Target,
+    pow_target_spacing: Duration,
+}
+
+impl SlidingWindowRetarget {
+    pub fn new(max_target: Target, pow_target_spacing: Duration) -> Self {
+        Self {
+            max_target,
+            pow_target_spacing,
+        }
+    }
+
+    /// Returns the height and header of the median-by-timestamp block among
+    /// the 3 blocks ending at (and including) `height`.
+    fn median_of_three<S: HeaderStore>(store: &S, height: BlockHeight) -> (BlockHeight, Header) {
+        let mut window = [height, height - 1, height - 2]
+            .map(|h| (h, store.get_with_height(h).expect("header must be in store")));
+        window.sort_by_key(|(_, header)| header.time);
+        window[1]
+    }
+}
+
+impl<V: HeaderValidator> DifficultyRetarget<V> for SlidingWindowRetarget {
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+        _timestamp: u32,
+    ) -> Target {
+        let height = prev_height + 1;
+
+        // Not enough history yet to form two 3-block medians `WINDOW` blocks
+        // apart; keep the tip's own target until there is.
+        if height <= WINDOW + 2 {
+            return prev_header.target();
+        }
+
+        let store = validator.store();
+        let (b_last_height, b_last) = Self::median_of_three(store, prev_height);
+        let (b_first_height, b_first) = Self::median_of_three(store, prev_height - WINDOW);
+
+        let spacing = self.pow_target_spacing.as_secs() as i64;
+        let timespan = (b_last.time as i64 - b_first.time as i64).clamp(72 * spacing, 288 * spacing);
+
+        let work = store.chainwork_at_height(b_last_height) - store.chainwork_at_height(b_first_height);
+        let projected = (work * spacing as u32) / timespan as u32;
+
+        let target = projected.to_target();
+        if target > self.max_target {
+            self.max_target
+        } else {
+            target
+        }
+    }
+}
+
+/// Number of blocks to look back when measuring whether mining has
+/// stalled, per the Bitcoin Cash Nov-2017 emergency difficulty adjustment
+/// (EDA).
+const STALL_LOOKBACK: BlockHeight = 6;
+
+/// The median-time-past gap, in seconds, beyond which mining is considered
+/// stalled and the emergency adjustment kicks in: 12 hours, per the EDA.
+const STALL_THRESHOLD_SECS: u32 = 12 * 60 * 60;
+
+/// Wraps a [`DifficultyRetarget`] strategy so that, between normal
+/// retargets, the target is relaxed by 25% whenever mining has stalled, as
+/// introduced by the Bitcoin Cash Nov-2017 emergency difficulty adjustment
+/// (EDA).
+///
+/// At every block -- not only interval boundaries -- this compares the
+/// median-time-past (MTP) at the tip against the MTP [`STALL_LOOKBACK`]
+/// blocks earlier. If the gap exceeds [`STALL_THRESHOLD_SECS`], the inner
+/// strategy's target is relaxed by a quarter (`target + (target >> 2)`,
+/// clamped to the validator's [`max_target`](HeaderValidator::max_target))
+/// instead of being used as-is. This mirrors the dampening Dogecoin's
+/// Digishield already performs, but for stall recovery between intervals
+/// rather than every block.
+pub struct EmergencyDifficultyAdjustment<Inner> {
+    inner: Inner,
+}
+
+impl<Inner> EmergencyDifficultyAdjustment<Inner> {
+    pub fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl<V: HeaderValidator, Inner: DifficultyRetarget<V>> DifficultyRetarget<V>
+    for EmergencyDifficultyAdjustment<Inner>
+{
+    fn next_target(
+        &self,
+        validator: &V,
+        prev_header: &Header,
+        prev_height: BlockHeight,
+        timestamp: u32,
+    ) -> Target {
+        let target = self
+            .inner
+            .next_target(validator, prev_header, prev_height, timestamp);
+
+        // Not enough history yet to compare the tip's MTP against one
+        // `STALL_LOOKBACK` blocks earlier; defer to the inner strategy.
+        if prev_height < STALL_LOOKBACK {
+            return target;
+        }
+
+        let store = validator.store();
+        let tip_mtp = store.median_time_past(&prev_header.block_hash());
+        let six_back = store
+            .get_with_height(prev_height - STALL_LOOKBACK)
+            .expect("header must be in store");
+        let six_back_mtp = store.median_time_past(&six_back.block_hash());
+
+        if tip_mtp.saturating_sub(six_back_mtp) <= STALL_THRESHOLD_SECS {
+            return target;
+        }
+
+        let relaxed = target + (target >> 2);
+        if relaxed > validator.max_target() {
+            validator.max_target()
+        } else {
+            relaxed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::SimpleHeaderStore;
+    use crate::header::ValidateHeaderError;
+    use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, TxMerkleNode};
+    use std::str::FromStr;
+
+    /// A trivial inner strategy that always keeps `prev_header`'s own
+    /// target, so these tests exercise only the emergency-adjustment
+    /// wrapper, not a real interval-based retarget rule.
+    struct KeepPreviousTarget;
+
+    impl<V: HeaderValidator> DifficultyRetarget<V> for KeepPreviousTarget {
+        fn next_target(
+            &self,
+            _validator: &V,
+            prev_header: &Header,
+            _prev_height: BlockHeight,
+            _timestamp: u32,
+        ) -> Target {
+            prev_header.target()
+        }
+    }
+
+    /// A minimal [`HeaderValidator`] exposing just enough to drive
+    /// [`EmergencyDifficultyAdjustment`] in isolation, in the spirit of
+    /// `buffer::tests::FakeValidator`.
+    struct FakeValidator(SimpleHeaderStore);
+
+    impl HeaderValidator for FakeValidator {
+        type Network = ();
+        type Store = SimpleHeaderStore;
+
+        fn network(&self) -> &Self::Network {
+            &()
+        }
+        fn store(&self) -> &Self::Store {
+            &self.0
+        }
+        fn store_mut(&mut self) -> &mut Self::Store {
+            &mut self.0
+        }
+        fn max_target(&self) -> Target {
+            Target::from_compact(CompactTarget::from_consensus(0x207fffff))
+        }
+        fn no_pow_retargeting(&self) -> bool {
+            true
+        }
+        fn pow_limit_bits(&self) -> CompactTarget {
+            CompactTarget::from_consensus(0x207fffff)
+        }
+        fn pow_target_spacing(&self) -> Duration {
+            Duration::from_secs(600)
+        }
+        fn difficulty_adjustment_interval(&self, _height: u32) -> u32 {
+            u32::MAX
+        }
+        fn allow_min_difficulty_blocks(&self, _height: u32) -> bool {
+            false
+        }
+        fn validate_header(
+            &self,
+            _header: &Header,
+            _current_time: Duration,
+        ) -> Result<(), ValidateHeaderError> {
+            unimplemented!("not exercised by these tests")
+        }
+        fn get_next_target(
+            &self,
+            prev_header: &Header,
+            prev_height: BlockHeight,
+            timestamp: u32,
+        ) -> Target {
+            EmergencyDifficultyAdjustment::new(KeepPreviousTarget).next_target(
+                self,
+                prev_header,
+                prev_height,
+                timestamp,
+            )
+        }
+        fn find_next_difficulty_in_chain(
+            &self,
+            _prev_header: &Header,
+            _prev_height: BlockHeight,
+        ) -> CompactTarget {
+            self.pow_limit_bits()
+        }
+        fn compute_next_difficulty(
+            &self,
+            _prev_header: &Header,
+            _prev_height: BlockHeight,
+        ) -> CompactTarget {
+            self.pow_limit_bits()
+        }
+    }
+
+    fn genesis_header(time: u32, bits: CompactTarget) -> Header {
+        Header {
+            version: Version::from_consensus(1),
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::from_str(
+                "c120ff2ae1363593a0b92e0d281ec341a0cc989b4ee836dc3405c9f4215242a6",
+            )
+            .unwrap(),
+            time,
+            bits,
+            nonce: 0,
+        }
+    }
+
+    /// Builds a store holding `genesis` followed by 20 more headers, each
+    /// `spacing` seconds after the last, all at `bits` difficulty -- deep
+    /// enough that both the tip's MTP and the MTP 6 blocks back are each
+    /// computed over a full 11-block window.
+    fn chain_with_spacing(bits: CompactTarget, spacing: u32) -> (SimpleHeaderStore, Header) {
+        let genesis = genesis_header(1_000_000, bits);
+        let mut store = SimpleHeaderStore::new(genesis, 0);
+        let mut last = genesis;
+        for _ in 0..20 {
+            let next = Header {
+                prev_blockhash: last.block_hash(),
+                time: last.time + spacing,
+                ..last
+            };
+            store.add(next);
+            last = next;
+        }
+        (store, last)
+    }
+
+    #[test]
+    fn keeps_the_inner_target_when_mining_has_not_stalled() {
+        let bits = CompactTarget::from_consensus(0x1d00ffff);
+        let (store, tip) = chain_with_spacing(bits, 600); // 10 minutes apart.
+        let validator = FakeValidator(store);
+
+        let target = validator.get_next_target(&tip, validator.store().height(), tip.time + 600);
+        assert_eq!(target, Target::from_compact(bits));
+    }
+
+    #[test]
+    fn relaxes_the_target_by_a_quarter_once_mining_has_stalled_past_12_hours() {
+        let bits = CompactTarget::from_consensus(0x1d00ffff);
+        // 3 hours apart: the 6-block MTP gap is 18 hours, past the
+        // 12-hour stall threshold.
+        let (store, tip) = chain_with_spacing(bits, 3 * 60 * 60);
+        let validator = FakeValidator(store);
+
+        let target = validator.get_next_target(&tip, validator.store().height(), tip.time + 600);
+
+        let inner_target = Target::from_compact(bits);
+        assert_eq!(target, inner_target + (inner_target >> 2));
+    }
+
+    #[test]
+    fn clamps_the_relaxed_target_to_the_pow_limit() {
+        // Already at the pow limit: relaxing by a quarter would exceed
+        // it, so the result must clamp rather than overflow past it.
+        let bits = CompactTarget::from_consensus(0x207fffff);
+        let (store, tip) = chain_with_spacing(bits, 3 * 60 * 60);
+        let validator = FakeValidator(store);
+
+        let target = validator.get_next_target(&tip, validator.store().height(), tip.time + 600);
+        assert_eq!(target, validator.max_target());
+    }
+}
@@ -5,8 +5,8 @@ mod doge;
 mod utils;
 
 use crate::header::tests::utils::test_data_file;
-use crate::header::timestamp_is_at_most_2h_in_future;
-use crate::header::{is_timestamp_valid, HeaderValidator, ONE_HOUR};
+use crate::header::timestamp_is_at_most_max_drift_in_future;
+use crate::header::{HeaderValidator, ONE_HOUR};
 #[cfg(feature = "doge")]
 use crate::header::{tests::utils::get_auxpow_headers, AuxPowHeaderValidator};
 use crate::HeaderStore;
@@ -46,6 +46,46 @@ fn verify_header_sequence<T: HeaderValidator>(mut validator: T, file: &str) {
     }
 }
 
+// Regression test for `HeaderValidator::expected_bits`/`expected_bits_at_tip`:
+// replays a real chain of headers one at a time and, before each one is
+// added to the store, asserts that the validator's own prediction for its
+// `nBits` matches what the network actually produced. `file` should span
+// at least one difficulty-adjustment boundary so both the adjustment and
+// non-adjustment paths get exercised.
+fn verify_expected_bits_matches_real_chain<T: HeaderValidator>(mut validator: T, file: &str) {
+    let headers = get_headers(file);
+    for header in headers.iter() {
+        let tip_height = validator.store().height();
+        let prev_hash = validator
+            .store()
+            .get_with_height(tip_height)
+            .unwrap()
+            .block_hash();
+
+        assert_eq!(
+            validator.expected_bits_at_tip(header.time),
+            header.bits,
+            "expected_bits_at_tip mismatch for header {}",
+            header.block_hash()
+        );
+        assert_eq!(
+            validator.expected_bits(&prev_hash, tip_height + 1, header.time),
+            header.bits,
+            "expected_bits mismatch for header {}",
+            header.block_hash()
+        );
+
+        let result = validator.validate_header(header, MOCK_CURRENT_TIME);
+        assert!(
+            result.is_ok(),
+            "header {} failed validation: {:?}",
+            header.block_hash(),
+            result
+        );
+        validator.store_mut().add(*header);
+    }
+}
+
 #[cfg(feature = "doge")]
 fn verify_header_sequence_auxpow<T: AuxPowHeaderValidator>(mut validator: T, file: &str) {
     let headers = get_auxpow_headers(file);
@@ -76,7 +116,7 @@ fn verify_with_invalid_pow<T: HeaderValidator>(validator: &T, mut header: Header
     assert!(matches!(
         result,
         Err(ValidateHeaderError::InvalidPoWForHeaderTarget)
-            | Err(ValidateHeaderError::InvalidPoWForComputedTarget)
+            | Err(ValidateHeaderError::InvalidPoWForComputedTarget { .. })
     ));
 }
 
@@ -97,7 +137,7 @@ fn verify_with_invalid_pow_with_computed_target<T: HeaderValidator>(
     let result = validator_regtest.validate_header(&h3, MOCK_CURRENT_TIME);
     assert!(matches!(
         result,
-        Err(ValidateHeaderError::InvalidPoWForComputedTarget)
+        Err(ValidateHeaderError::InvalidPoWForComputedTarget { .. })
     ));
 }
 
@@ -220,12 +260,12 @@ fn verify_timestamp_rules<T: HeaderValidator>(validator: &T, height_start_header
         bits: CompactTarget::from_consensus(0x170e0408),
         nonce: 0xb48e8b0a,
     };
-    assert!(is_timestamp_valid(validator.store(), &header, MOCK_CURRENT_TIME).is_ok());
+    assert!(validator.is_timestamp_valid(&header, MOCK_CURRENT_TIME).is_ok());
 
     // Mon Apr 16 2012 15:06:40
     header.time = 1334588800;
     assert!(matches!(
-        is_timestamp_valid(validator.store(), &header, MOCK_CURRENT_TIME),
+        validator.is_timestamp_valid(&header, MOCK_CURRENT_TIME),
         Err(ValidateHeaderError::HeaderIsOld)
     ));
 
@@ -234,11 +274,11 @@ fn verify_timestamp_rules<T: HeaderValidator>(validator: &T, height_start_header
 
     header.time = (MOCK_CURRENT_TIME - ONE_HOUR).as_secs() as u32;
 
-    assert!(is_timestamp_valid(validator.store(), &header, MOCK_CURRENT_TIME).is_ok());
+    assert!(validator.is_timestamp_valid(&header, MOCK_CURRENT_TIME).is_ok());
 
     header.time = (MOCK_CURRENT_TIME + 2 * ONE_HOUR + Duration::from_secs(10)).as_secs() as u32;
     assert_eq!(
-        is_timestamp_valid(validator.store(), &header, MOCK_CURRENT_TIME),
+        validator.is_timestamp_valid(&header, MOCK_CURRENT_TIME),
         Err(ValidateHeaderError::HeaderIsTooFarInFuture {
             block_time: header.time as u64,
             max_allowed_time: (MOCK_CURRENT_TIME + 2 * ONE_HOUR).as_secs()
@@ -260,31 +300,48 @@ fn test_timestamp_is_at_most_2h_in_future() {
     // Time is represented as the number of seconds after 01.01.1970 00:00.
     // Hence, if block time is 10 seconds after that time,
     // 'test_timestamp_is_at_most_2h_in_future' should return true.
+    let max_drift = 2 * ONE_HOUR;
 
-    assert!(timestamp_is_at_most_2h_in_future(Duration::from_secs(10), MOCK_CURRENT_TIME).is_ok());
-
-    assert!(
-        timestamp_is_at_most_2h_in_future(MOCK_CURRENT_TIME - ONE_HOUR, MOCK_CURRENT_TIME).is_ok()
-    );
+    assert!(timestamp_is_at_most_max_drift_in_future(
+        Duration::from_secs(10),
+        MOCK_CURRENT_TIME,
+        max_drift
+    )
+    .is_ok());
 
-    assert!(timestamp_is_at_most_2h_in_future(MOCK_CURRENT_TIME, MOCK_CURRENT_TIME).is_ok());
+    assert!(timestamp_is_at_most_max_drift_in_future(
+        MOCK_CURRENT_TIME - ONE_HOUR,
+        MOCK_CURRENT_TIME,
+        max_drift
+    )
+    .is_ok());
 
     assert!(
-        timestamp_is_at_most_2h_in_future(MOCK_CURRENT_TIME + ONE_HOUR, MOCK_CURRENT_TIME).is_ok()
+        timestamp_is_at_most_max_drift_in_future(MOCK_CURRENT_TIME, MOCK_CURRENT_TIME, max_drift)
+            .is_ok()
     );
 
-    assert!(timestamp_is_at_most_2h_in_future(
+    assert!(timestamp_is_at_most_max_drift_in_future(
+        MOCK_CURRENT_TIME + ONE_HOUR,
+        MOCK_CURRENT_TIME,
+        max_drift
+    )
+    .is_ok());
+
+    assert!(timestamp_is_at_most_max_drift_in_future(
         MOCK_CURRENT_TIME + 2 * ONE_HOUR - Duration::from_secs(5),
-        MOCK_CURRENT_TIME
+        MOCK_CURRENT_TIME,
+        max_drift
     )
     .is_ok());
 
     // 'test_timestamp_is_at_most_2h_in_future' should return false
     // because the time is more than 2 hours from the current time.
     assert_eq!(
-        timestamp_is_at_most_2h_in_future(
+        timestamp_is_at_most_max_drift_in_future(
             MOCK_CURRENT_TIME + 2 * ONE_HOUR + Duration::from_secs(10),
-            MOCK_CURRENT_TIME
+            MOCK_CURRENT_TIME,
+            max_drift
         ),
         Err(ValidateHeaderError::HeaderIsTooFarInFuture {
             block_time: (MOCK_CURRENT_TIME + 2 * ONE_HOUR).as_secs() + 10,
@@ -292,3 +349,93 @@ fn test_timestamp_is_at_most_2h_in_future() {
         })
     );
 }
+
+#[test]
+fn test_median_time_past_handles_near_genesis_chains() {
+    use crate::fixtures::SimpleHeaderStore;
+    use bitcoin::hashes::Hash;
+
+    let genesis = Header {
+        version: Version::from_consensus(1),
+        prev_blockhash: BlockHash::all_zeros(),
+        merkle_root: TxMerkleNode::from_str(
+            "c120ff2ae1363593a0b92e0d281ec341a0cc989b4ee836dc3405c9f4215242a6",
+        )
+        .unwrap(),
+        time: 1_231_006_505,
+        bits: CompactTarget::from_consensus(0x1d00ffff),
+        nonce: 0,
+    };
+
+    // At genesis there are no ancestors at all: MTP degrades to 0 rather
+    // than indexing into an empty vector of timestamps.
+    let store = SimpleHeaderStore::new(genesis, 0);
+    assert_eq!(store.median_time_past(&genesis.prev_blockhash), 0);
+
+    // One block in: exactly one ancestor (genesis) is available, so MTP
+    // is just its timestamp, not a panic on an empty/singleton slice.
+    let mut next = genesis;
+    next.prev_blockhash = genesis.block_hash();
+    next.time = genesis.time + 600;
+    let mut store = SimpleHeaderStore::new(genesis, 0);
+    store.add(next);
+    assert_eq!(store.median_time_past(&next.prev_blockhash), genesis.time);
+
+    // The `_inclusive` variant folds `next`'s own time into the window
+    // before it's added to the store, rather than requiring it be added first.
+    // The window is then `[genesis.time, next.time]`, and with an even count
+    // the median is the upper of the two middle samples.
+    assert_eq!(
+        store.median_time_past_inclusive(&next.prev_blockhash, next.time),
+        next.time
+    );
+}
+
+#[test]
+fn test_median_time_past_sorts_out_of_order_ancestor_timestamps() {
+    use crate::fixtures::SimpleHeaderStore;
+    use bitcoin::hashes::Hash;
+
+    let merkle_root = TxMerkleNode::from_str(
+        "c120ff2ae1363593a0b92e0d281ec341a0cc989b4ee836dc3405c9f4215242a6",
+    )
+    .unwrap();
+    let mut header = Header {
+        version: Version::from_consensus(1),
+        prev_blockhash: BlockHash::all_zeros(),
+        merkle_root,
+        time: 1_231_006_505,
+        bits: CompactTarget::from_consensus(0x1d00ffff),
+        nonce: 0,
+    };
+
+    // Out-of-order timestamps, as the Median-Time-Past rule allows: each
+    // ancestor's own timestamp only has to exceed the *median* of the 11
+    // before it, not its immediate predecessor's.
+    let timestamps = [100, 90, 80, 70, 200, 60, 50, 40, 30, 20, 10];
+    let mut store = SimpleHeaderStore::new(header, 0);
+    for time in timestamps {
+        let prev_hash = header.block_hash();
+        header.prev_blockhash = prev_hash;
+        header.time = time;
+        store.add(header);
+    }
+
+    let mut sorted = timestamps;
+    sorted.sort_unstable();
+    let expected_median = sorted[sorted.len() / 2];
+
+    let tip_prev_hash = header.block_hash();
+    assert_eq!(store.median_time_past(&tip_prev_hash), expected_median);
+
+    // Folding in a candidate's own timestamp shifts the window to 12
+    // samples, which can move the median.
+    let mut with_candidate = timestamps.to_vec();
+    with_candidate.push(header.time + 1);
+    with_candidate.sort_unstable();
+    let expected_inclusive_median = with_candidate[with_candidate.len() / 2];
+    assert_eq!(
+        store.median_time_past_inclusive(&tip_prev_hash, header.time + 1),
+        expected_inclusive_median
+    );
+}
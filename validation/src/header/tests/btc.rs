@@ -3,9 +3,10 @@ use crate::header::btc::DIFFICULTY_ADJUSTMENT_INTERVAL_BITCOIN;
 use crate::header::tests::utils::{bitcoin_genesis_header, btc_files, deserialize_header};
 use crate::header::tests::{
     verify_backdated_block_difficulty, verify_consecutive_headers, verify_difficulty_adjustment,
-    verify_header_sequence, verify_regtest_difficulty_calculation, verify_timestamp_rules,
-    verify_with_excessive_target, verify_with_invalid_pow,
-    verify_with_invalid_pow_with_computed_target, verify_with_missing_parent,
+    verify_expected_bits_matches_real_chain, verify_header_sequence,
+    verify_regtest_difficulty_calculation, verify_timestamp_rules, verify_with_excessive_target,
+    verify_with_invalid_pow, verify_with_invalid_pow_with_computed_target,
+    verify_with_missing_parent,
 };
 use crate::header::HeaderValidator;
 use crate::BitcoinHeaderValidator;
@@ -57,6 +58,19 @@ fn test_sequential_header_validation_mainnet() {
     );
 }
 
+#[test]
+fn test_expected_bits_matches_mainnet_retarget_boundary() {
+    // 586_657..=589_289 crosses height 588_672 (2016 * 292), a real
+    // difficulty-adjustment boundary, so this exercises both the
+    // adjustment and ordinary non-adjustment paths of `expected_bits`.
+    let start_header = deserialize_header(MAINNET_HEADER_586656);
+    let store = SimpleHeaderStore::new(start_header, 586_656);
+    verify_expected_bits_matches_real_chain(
+        BitcoinHeaderValidator::mainnet(store),
+        btc_files::MAINNET_HEADERS_586657_589289_PARSED,
+    );
+}
+
 #[test]
 fn test_sequential_header_validation_testnet() {
     let start_header = bitcoin_genesis_block(BitcoinNetwork::Testnet).header;
@@ -161,3 +175,81 @@ fn test_timestamp_validation_mainnet() {
     store.add(deserialize_header(MAINNET_HEADER_705602));
     verify_timestamp_rules(&BitcoinHeaderValidator::mainnet(store), start_header_height);
 }
+
+#[test]
+fn test_fork_choice_reports_reorg_for_a_harder_side_branch() {
+    use crate::{ForkChoice, HeaderStore};
+
+    let regtest_pow = CompactTarget::from_consensus(0x207fffff);
+    let genesis = bitcoin_genesis_header(BitcoinNetwork::Regtest, regtest_pow);
+    let store = SimpleHeaderStore::new(genesis, 0);
+    let mut validator = BitcoinHeaderValidator::regtest(store);
+
+    // The first low-difficulty block on top of genesis.
+    let a1 = next_block_header(&validator, genesis, regtest_pow);
+    validator.store_mut().add(a1);
+
+    // A side branch off genesis, a single block but at a far harder
+    // difficulty than the regtest minimum -- enough to outweigh both of
+    // the low-difficulty blocks on the `a` branch combined. Added before
+    // `a2` so it isn't the store's current tip by the time fork choice is
+    // evaluated below.
+    let hard_bits = CompactTarget::from_consensus(0x1d00ffff);
+    let b1 = next_block_header(&validator, genesis, hard_bits);
+    validator.store_mut().add(b1);
+
+    // Extends the `a` branch past `b1`'s single block; this becomes the
+    // store's current tip.
+    let a2 = next_block_header(&validator, a1, regtest_pow);
+    validator.store_mut().add(a2);
+
+    // `a1` never had, and still doesn't have, more work than the current
+    // tip (`a2`), so it isn't a fork-choice candidate.
+    assert_eq!(validator.evaluate_fork_choice(&a1), ForkChoice::Extends);
+
+    // `b1` alone outweighs `a1` + `a2`, so it should win fork choice even
+    // though it's shorter, and the reorg path is just the one block back
+    // to the fork point at genesis.
+    assert_eq!(
+        validator.evaluate_fork_choice(&b1),
+        ForkChoice::Reorg {
+            fork_path: vec![b1.block_hash()]
+        }
+    );
+    assert!(validator.store().total_work(&b1.block_hash()) > validator.store().total_work(&a2.block_hash()));
+}
+
+#[test]
+fn test_compare_chains_prefers_more_accumulated_work() {
+    use crate::header::BetterChain;
+
+    let regtest_pow = CompactTarget::from_consensus(0x207fffff);
+    let genesis = bitcoin_genesis_header(BitcoinNetwork::Regtest, regtest_pow);
+    let store = SimpleHeaderStore::new(genesis, 0);
+    let mut validator = BitcoinHeaderValidator::regtest(store);
+
+    let a1 = next_block_header(&validator, genesis, regtest_pow);
+    validator.store_mut().add(a1);
+
+    let hard_bits = CompactTarget::from_consensus(0x1d00ffff);
+    let b1 = next_block_header(&validator, genesis, hard_bits);
+    validator.store_mut().add(b1);
+
+    // `b1` alone outweighs `a1`, so it should be preferred.
+    assert_eq!(
+        validator.compare_chains(&a1.block_hash(), &b1.block_hash()),
+        BetterChain::Second
+    );
+    assert_eq!(
+        validator.compare_chains(&b1.block_hash(), &a1.block_hash()),
+        BetterChain::First
+    );
+
+    // Equal work (comparing a hash against itself) favors the first side,
+    // consistent with `evaluate_fork_choice` never reorging without
+    // strictly more work.
+    assert_eq!(
+        validator.compare_chains(&a1.block_hash(), &a1.block_hash()),
+        BetterChain::First
+    );
+}
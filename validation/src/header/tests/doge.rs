@@ -8,7 +8,12 @@ use crate::constants::doge::test::{
     TESTNET_HEADER_DOGE_158380, TESTNET_HEADER_DOGE_293098, TESTNET_HEADER_DOGE_293099,
     TESTNET_HEADER_DOGE_88, TESTNET_HEADER_DOGE_89,
 };
-use crate::header::doge::ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT;
+use crate::fixtures::SimpleHeaderStore;
+use crate::header::doge::{
+    DigishieldRetarget, LegacyRetarget, ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT,
+    DIGISHIELD_ACTIVATION_HEIGHT,
+};
+use crate::header::retarget::DifficultyRetarget;
 use crate::header::tests::utils::{deserialize_header, doge_files, dogecoin_genesis_header};
 use crate::header::tests::{
     verify_backdated_block_difficulty, verify_consecutive_headers,
@@ -17,7 +22,7 @@ use crate::header::tests::{
     verify_with_excessive_target, verify_with_invalid_pow,
     verify_with_invalid_pow_with_computed_target, verify_with_missing_parent,
 };
-use crate::{DogecoinHeaderValidator, HeaderValidator};
+use crate::{ConsensusParamsOverride, DogecoinHeaderValidator, HeaderValidator};
 use bitcoin::dogecoin::constants::genesis_block as dogecoin_genesis_block;
 use bitcoin::dogecoin::Network as DogecoinNetwork;
 use bitcoin::{CompactTarget, Target};
@@ -205,6 +210,54 @@ fn test_timestamp_validation_mainnet() {
     );
 }
 
+#[test]
+fn test_difficulty_adjustment_interval_switches_to_digishield() {
+    // Mainnet retargets every 240 blocks pre-Digishield and every block from
+    // height 145_000 onward, so the ingestion/verification pipeline rejects a
+    // block whose `bits` was computed with the wrong interval for its height.
+    let validator = DogecoinHeaderValidator::mainnet();
+
+    assert_eq!(
+        validator.difficulty_adjustment_interval(DIGISHIELD_ACTIVATION_HEIGHT - 1),
+        240
+    );
+    assert_eq!(
+        validator.difficulty_adjustment_interval(DIGISHIELD_ACTIVATION_HEIGHT),
+        1
+    );
+
+    assert!(!validator.is_digishield_activated(DIGISHIELD_ACTIVATION_HEIGHT - 1));
+    assert!(validator.is_digishield_activated(DIGISHIELD_ACTIVATION_HEIGHT));
+}
+
+#[test]
+fn test_legacy_retarget_matches_digishield_retarget_before_activation() {
+    // Before Digishield activates, `DigishieldRetarget` just delegates to
+    // `LegacyRetarget` -- so, with activation pushed out of reach via an
+    // override, the two strategies must agree on every target.
+    let validator = DogecoinHeaderValidator::with_retarget_and_params_override(
+        SimpleHeaderStore::new(
+            dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1)),
+            0,
+        ),
+        DogecoinNetwork::Regtest,
+        DigishieldRetarget,
+        ConsensusParamsOverride {
+            digishield_activation_height: Some(u32::MAX),
+            ..Default::default()
+        },
+    );
+
+    let prev_header =
+        dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1));
+    let timestamp = prev_header.time + 1;
+
+    assert_eq!(
+        DigishieldRetarget.next_target(&validator, &prev_header, 0, timestamp),
+        LegacyRetarget.next_target(&validator, &prev_header, 0, timestamp),
+    );
+}
+
 #[test]
 fn test_digishield_with_min_difficulty_height() {
     let networks = [DogecoinNetwork::Testnet, DogecoinNetwork::Regtest];
@@ -214,3 +267,92 @@ fn test_digishield_with_min_difficulty_height() {
             .is_digishield_activated(ALLOW_DIGISHIELD_MIN_DIFFICULTY_HEIGHT));
     }
 }
+
+// Reaching Digishield activation on any real network takes hundreds of
+// thousands of blocks (`DIGISHIELD_ACTIVATION_HEIGHT` is 145_000). A
+// `ConsensusParamsOverride` lets a test bring that boundary down to a
+// handful of blocks instead, without touching mainnet rules.
+#[test]
+fn test_params_override_moves_digishield_activation_height() {
+    let default_validator = DogecoinHeaderValidator::regtest(SimpleHeaderStore::new(
+        dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1)),
+        0,
+    ));
+    assert_eq!(
+        default_validator.digishield_activation_height(),
+        DIGISHIELD_ACTIVATION_HEIGHT
+    );
+
+    let overridden_validator = DogecoinHeaderValidator::with_retarget_and_params_override(
+        SimpleHeaderStore::new(
+            dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1)),
+            0,
+        ),
+        DogecoinNetwork::Regtest,
+        DigishieldRetarget,
+        ConsensusParamsOverride {
+            digishield_activation_height: Some(3),
+            ..Default::default()
+        },
+    );
+    assert_eq!(overridden_validator.digishield_activation_height(), 3);
+}
+
+#[test]
+fn test_params_override_shrinks_difficulty_adjustment_interval() {
+    let validator = DogecoinHeaderValidator::with_retarget_and_params_override(
+        SimpleHeaderStore::new(
+            dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1)),
+            0,
+        ),
+        DogecoinNetwork::Regtest,
+        DigishieldRetarget,
+        ConsensusParamsOverride {
+            difficulty_adjustment_interval: Some(4),
+            ..Default::default()
+        },
+    );
+    // Regtest normally retargets every block; the override takes priority
+    // over the network's built-in interval regardless.
+    assert_eq!(validator.difficulty_adjustment_interval(0), 4);
+}
+
+#[test]
+fn test_allow_digishield_min_difficulty_for_block_applies_every_block_once_activated() {
+    // Unlike Bitcoin's min-difficulty reset, which only applies on a
+    // non-retarget-boundary block, Digishield's version must apply to
+    // *every* block once active, since every block is a retarget.
+    let genesis = dogecoin_genesis_header(&DogecoinNetwork::Regtest, CompactTarget::from_consensus(1));
+    let validator = DogecoinHeaderValidator::with_retarget_and_params_override(
+        SimpleHeaderStore::new(genesis, 0),
+        DogecoinNetwork::Regtest,
+        DigishieldRetarget,
+        ConsensusParamsOverride {
+            digishield_activation_height: Some(0),
+            ..Default::default()
+        },
+    );
+
+    let spacing = validator.pow_target_spacing().as_secs() as u32;
+    let gap_timestamp = genesis.time + 2 * spacing + 1;
+    let normal_timestamp = genesis.time + spacing;
+
+    assert!(validator.allow_digishield_min_difficulty_for_block(&genesis, 1, gap_timestamp));
+    assert!(!validator.allow_digishield_min_difficulty_for_block(&genesis, 1, normal_timestamp));
+
+    // Before activation, the rule never applies regardless of the gap.
+    let pre_activation_validator = DogecoinHeaderValidator::with_retarget_and_params_override(
+        SimpleHeaderStore::new(genesis, 0),
+        DogecoinNetwork::Regtest,
+        DigishieldRetarget,
+        ConsensusParamsOverride {
+            digishield_activation_height: Some(u32::MAX),
+            ..Default::default()
+        },
+    );
+    assert!(!pre_activation_validator.allow_digishield_min_difficulty_for_block(
+        &genesis,
+        1,
+        gap_timestamp
+    ));
+}
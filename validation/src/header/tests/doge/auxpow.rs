@@ -183,10 +183,10 @@ fn test_without_auxpow_data() {
         .with_valid_pow(false)
         .build()
         .into();
-    assert_eq!(
+    assert!(matches!(
         validator.validate_auxpow_header(&dogecoin_header, CURRENT_TIME),
-        Err(ValidateHeaderError::InvalidPoWForComputedTarget)
-    );
+        Err(ValidateHeaderError::InvalidPoWForComputedTarget { .. })
+    ));
 
     // AuxPow flag set but no AuxPow data - should fail
     let dogecoin_header = HeaderBuilder::default()
@@ -287,6 +287,34 @@ fn test_with_auxpow_data() {
     );
 }
 
+#[test]
+fn test_parent_with_our_chain_id_rejected() {
+    let (validator, prev_header_auxpow) = create_header_store_after_auxpow_activation_regtest();
+
+    let pure_header = HeaderBuilder::default()
+        .with_prev_header(prev_header_auxpow)
+        .with_version(BASE_VERSION)
+        .with_chain_id(DOGECOIN_CHAIN_ID)
+        .with_auxpow_bit(true)
+        .with_valid_pow(false)
+        .build();
+    // Parent header claims the same chain id as the aux chain it's
+    // supposedly mining for -- should be rejected even though the rest of
+    // the proof is otherwise valid.
+    let aux_pow = AuxPowBuilder::new(pure_header.block_hash())
+        .with_valid_pow(true)
+        .with_parent_chain_id(DOGECOIN_CHAIN_ID)
+        .build();
+    let dogecoin_header = DogecoinHeader {
+        pure_header,
+        aux_pow: Some(aux_pow),
+    };
+    assert_eq!(
+        validator.validate_auxpow_header(&dogecoin_header, CURRENT_TIME),
+        Err(ValidateAuxPowHeaderError::ParentHasAuxChainId.into())
+    );
+}
+
 #[test]
 fn test_header_modification_invalidates_auxpow_proof() {
     let (validator, prev_header_auxpow) = create_header_store_after_auxpow_activation_regtest();
@@ -6,15 +6,25 @@ mod header;
 mod fixtures;
 
 #[cfg(feature = "doge")]
-pub use crate::block::{BlockValidator, ValidateBlockError};
-pub use crate::header::{HeaderStore, HeaderValidator, ValidateHeaderError};
+pub use crate::block::{BlockValidator, ValidateBlockError, COINBASE_MATURITY};
+#[cfg(feature = "doge")]
+pub use crate::block::import_export::{import_blocks_bootstrap, BootstrapImportError};
+#[cfg(all(feature = "doge", feature = "bitcoinconsensus"))]
+pub use crate::block::{verify_transactions, ScriptError, SpentOutput};
+pub use crate::header::{
+    buffer::BufferingHeaderValidator,
+    checkpoints::{BatchCheckpoint, CheckpointTable, CheckpointedHeaderValidator, BATCH_SIZE},
+    BetterChain, ConsensusParamsOverride, ForkChoice, HeaderStore, HeaderValidator,
+    RetryClassification, ValidateHeaderError,
+};
 
 #[cfg(feature = "btc")]
 pub use crate::header::btc::BitcoinHeaderValidator;
 
 #[cfg(feature = "doge")]
 pub use crate::header::{
-    doge::DogecoinHeaderValidator, AuxPowHeaderValidator, ValidateAuxPowHeaderError,
+    consensus::ConsensusParams, doge::DogecoinHeaderValidator, AuxPowHeaderValidator,
+    ValidateAuxPowHeaderError,
 };
 
 type BlockHeight = u32;